@@ -112,6 +112,10 @@ fn get_boringssl_platform_output_path() -> String {
             std::env::var("OPT_LEVEL").expect("OPT_LEVEL variable not defined in env");
 
         let subdir = match &opt_env_var[..] {
+            // The `minimal` feature overrides the build type of BoringSSL builds, see `main`.
+            _ if cfg!(feature = "minimal") && std::env::var_os("BORING_BSSL_PATH").is_none() => {
+                "MinSizeRel"
+            }
             "0" => "Debug",
             "1" | "2" | "3" => {
                 if deb_info {
@@ -228,6 +232,27 @@ fn get_boringssl_cmake_config() -> cmake::Config {
     boringssl_cmake
 }
 
+/// Returns the number of parallel jobs the BoringSSL build may use, if limited.
+///
+/// `BORING_BSSL_BUILD_JOBS` takes precedence over the `NUM_JOBS` cargo derives from `-j` and
+/// `CARGO_BUILD_JOBS`.
+fn get_boringssl_build_jobs() -> Option<String> {
+    // `NUM_JOBS` is deliberately not tracked, as a different `-j` should not rebuild BoringSSL.
+    println!("cargo:rerun-if-env-changed=BORING_BSSL_BUILD_JOBS");
+
+    if let Ok(jobs) = std::env::var("BORING_BSSL_BUILD_JOBS") {
+        match jobs.parse::<usize>() {
+            Ok(n) if n > 0 => return Some(jobs),
+            _ => panic!(
+                "BORING_BSSL_BUILD_JOBS must be a positive integer, got \"{}\"",
+                jobs
+            ),
+        }
+    }
+
+    std::env::var("NUM_JOBS").ok()
+}
+
 /// Verify that the toolchains match https://csrc.nist.gov/CSRC/media/projects/cryptographic-module-validation-program/documents/security-policies/140sp3678.pdf
 /// See "Installation Instructions" under section 12.1.
 // TODO: maybe this should also verify the Go and Ninja versions? But those haven't been an issue in practice ...
@@ -358,8 +383,32 @@ fn main() {
             cfg.define("CMAKE_ASM_COMPILER", clang);
             cfg.define("FIPS", "1");
         }
+        if cfg!(feature = "minimal") {
+            // Trades speed for size: smaller precomputed tables and no unrolled code paths.
+            cfg.define("OPENSSL_SMALL", "1");
+            // cmake appends the flags of the build type after `CMAKE_C_FLAGS`, so a plain `-Os`
+            // cflag would be overridden by the `-O3` of the release build type.
+            cfg.profile("MinSizeRel");
+            if !cfg!(target_env = "msvc") {
+                // Strips the debug info, which makes up most of a debug build's archives, and
+                // gives every function its own section so unused ones can be garbage collected.
+                for flag in ["-g0", "-ffunction-sections", "-fdata-sections"] {
+                    cfg.cflag(flag).cxxflag(flag);
+                }
+            }
+        }
+
+        if let Some(jobs) = get_boringssl_build_jobs() {
+            // cmake-rs forwards NUM_JOBS to `cmake --build --parallel`, while cmake itself falls
+            // back to CMAKE_BUILD_PARALLEL_LEVEL, so set both to keep ninja and make bounded.
+            env::set_var("NUM_JOBS", &jobs);
+            cfg.env("CMAKE_BUILD_PARALLEL_LEVEL", &jobs);
+        }
 
         cfg.build_target("ssl").build();
+        if cfg!(feature = "decrepit") {
+            cfg.build_target("decrepit").build();
+        }
         cfg.build_target("crypto").build().display().to_string()
     });
 
@@ -373,6 +422,12 @@ fn main() {
             "cargo:rustc-link-search=native={}/build/ssl/{}",
             bssl_dir, build_path
         );
+        if cfg!(feature = "decrepit") {
+            println!(
+                "cargo:rustc-link-search=native={}/build/decrepit/{}",
+                bssl_dir, build_path
+            );
+        }
     } else {
         println!(
             "cargo:rustc-link-search=native={}/build/{}",
@@ -380,6 +435,9 @@ fn main() {
         );
     }
 
+    if cfg!(feature = "decrepit") {
+        println!("cargo:rustc-link-lib=static=decrepit");
+    }
     println!("cargo:rustc-link-lib=static=crypto");
     println!("cargo:rustc-link-lib=static=ssl");
 
@@ -430,6 +488,16 @@ fn main() {
         _ => {}
     }
 
+    // Faster and more portable binding generation, for targets where the comment parser or the
+    // layout tests misbehave. The derives stay enabled because `boring` relies on them.
+    println!("cargo:rerun-if-env-changed=BORING_BINDGEN_MINIMAL");
+    if std::env::var_os("BORING_BINDGEN_MINIMAL").map_or(false, |v| v != "0") {
+        builder = builder
+            .generate_comments(false)
+            .layout_tests(false)
+            .rustfmt_bindings(false);
+    }
+
     let headers = [
         "aes.h",
         "asn1_mac.h",
@@ -437,6 +505,7 @@ fn main() {
         #[cfg(not(feature = "fips"))]
         "blake2.h",
         "blowfish.h",
+        "bytestring.h",
         "cast.h",
         "chacha.h",
         "cmac.h",
@@ -445,6 +514,8 @@ fn main() {
         "des.h",
         "dtls1.h",
         "hkdf.h",
+        #[cfg(not(feature = "fips"))]
+        "hpke.h",
         "hrss.h",
         "md4.h",
         "md5.h",
@@ -453,7 +524,9 @@ fn main() {
         "opensslv.h",
         "ossl_typ.h",
         "pkcs12.h",
+        "pkcs7.h",
         "poly1305.h",
+        "pool.h",
         "rand.h",
         "rc4.h",
         "ripemd.h",
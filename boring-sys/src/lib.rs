@@ -64,6 +64,10 @@ const_fn! {
     }
 }
 
+/// Initializes BoringSSL.
+///
+/// This is idempotent and may be called concurrently from any number of threads; the underlying
+/// initialization runs exactly once.
 pub fn init() {
     use std::ptr;
     use std::sync::Once;
@@ -74,6 +78,7 @@ pub fn init() {
     let init_options = OPENSSL_INIT_LOAD_SSL_STRINGS;
 
     INIT.call_once(|| {
+        unsafe { CRYPTO_library_init() };
         assert_eq!(
             unsafe { OPENSSL_init_ssl(init_options.try_into().unwrap(), ptr::null_mut()) },
             1
@@ -38,6 +38,7 @@ use crate::bio::MemBio;
 use crate::bn::{BigNum, BigNumRef};
 use crate::error::ErrorStack;
 use crate::nid::Nid;
+use crate::stack::Stackable;
 use crate::string::OpensslString;
 use crate::{cvt, cvt_p};
 
@@ -462,6 +463,54 @@ impl Asn1BitStringRef {
     }
 }
 
+foreign_type_and_impl_send_sync! {
+    type CType = ffi::ASN1_OCTET_STRING;
+    fn drop = ffi::ASN1_OCTET_STRING_free;
+    /// ASN.1 OCTET STRING type
+    ///
+    /// This is used, for example, to hold the DER-encoded value of an `X509` extension.
+    pub struct Asn1OctetString;
+}
+
+impl Asn1OctetString {
+    /// Creates an `Asn1OctetString` from bytes.
+    ///
+    /// This corresponds to [`ASN1_OCTET_STRING_set`].
+    ///
+    /// [`ASN1_OCTET_STRING_set`]: https://commondatastorage.googleapis.com/chromium-boringssl-docs/asn1.h.html#ASN1_OCTET_STRING_set
+    pub fn new_from_bytes(value: &[u8]) -> Result<Self, ErrorStack> {
+        unsafe {
+            ffi::init();
+            let s = cvt_p(ffi::ASN1_OCTET_STRING_new())?;
+            let s = Asn1OctetString::from_ptr(s);
+            assert!(value.len() <= c_int::max_value() as usize);
+            cvt(ffi::ASN1_OCTET_STRING_set(
+                s.as_ptr(),
+                value.as_ptr(),
+                value.len() as c_int,
+            ))?;
+            Ok(s)
+        }
+    }
+}
+
+impl Asn1OctetStringRef {
+    /// Returns the octet string as a slice.
+    pub fn as_slice(&self) -> &[u8] {
+        unsafe { slice::from_raw_parts(ASN1_STRING_get0_data(self.as_ptr()), self.len()) }
+    }
+
+    /// Returns the number of bytes in the octet string.
+    pub fn len(&self) -> usize {
+        unsafe { ffi::ASN1_STRING_length(self.as_ptr()) as usize }
+    }
+
+    /// Determines if the string is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
 foreign_type_and_impl_send_sync! {
     type CType = ffi::ASN1_OBJECT;
     fn drop = ffi::ASN1_OBJECT_free;
@@ -498,6 +547,24 @@ impl Asn1Object {
             Ok(Asn1Object::from_ptr(obj))
         }
     }
+
+    /// Returns the ASN.1 Object Identifier registered for `nid`.
+    ///
+    /// This corresponds to [`OBJ_nid2obj`].
+    ///
+    /// [`OBJ_nid2obj`]: https://www.openssl.org/docs/man1.1.0/crypto/OBJ_nid2obj.html
+    pub fn from_nid(nid: Nid) -> Result<Asn1Object, ErrorStack> {
+        unsafe {
+            ffi::init();
+            let obj = cvt_p(ffi::OBJ_nid2obj(nid.as_raw()))?;
+            // Objects owned by the library's tables must not be freed, so hand out a copy.
+            cvt_p(ffi::OBJ_dup(obj)).map(|p| Asn1Object::from_ptr(p))
+        }
+    }
+}
+
+impl Stackable for Asn1Object {
+    type StackType = ffi::stack_st_ASN1_OBJECT;
 }
 
 impl Asn1ObjectRef {
@@ -618,6 +685,20 @@ mod tests {
         assert_eq!(object.nid(), Nid::SHA256);
     }
 
+    #[test]
+    fn object_from_nid() {
+        let object = Asn1Object::from_nid(Nid::SHA256).unwrap();
+        assert_eq!(object.nid(), Nid::SHA256);
+        assert_eq!(object.to_string(), "sha256");
+    }
+
+    #[test]
+    fn octet_string() {
+        let s = Asn1OctetString::new_from_bytes(b"hello world").unwrap();
+        assert_eq!(s.as_slice(), b"hello world");
+        assert_eq!(s.len(), 11);
+    }
+
     #[test]
     fn object_from_str_with_invalid_input() {
         Asn1Object::from_str("NOT AN OID")
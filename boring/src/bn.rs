@@ -21,7 +21,21 @@
 //! }
 //! ```
 //!
+//! # Constant time
+//!
+//! The bit-level operations of [`BigNumRef`], such as [`is_bit_set`], [`set_bit`] and
+//! [`mask_bits`], operate on the value's significant bits, so their timing and that of most
+//! arithmetic reveals the size of the number. BoringSSL does not export a constant-time
+//! conditional swap like OpenSSL's `BN_consttime_swap`, so algorithms such as Montgomery ladders
+//! which must not leak secret bits should use the higher-level primitives of modules like
+//! [`ec`] instead of being built on `BigNum`.
+//!
 //! [`BIGNUM`]: https://wiki.openssl.org/index.php/Manual:Bn_internal(3)
+//! [`BigNumRef`]: struct.BigNumRef.html
+//! [`is_bit_set`]: struct.BigNumRef.html#method.is_bit_set
+//! [`set_bit`]: struct.BigNumRef.html#method.set_bit
+//! [`mask_bits`]: struct.BigNumRef.html#method.mask_bits
+//! [`ec`]: ../ec/index.html
 use crate::ffi;
 use foreign_types::{ForeignType, ForeignTypeRef};
 use libc::{c_int, size_t};
@@ -996,6 +1010,27 @@ impl BigNum {
             .map(|p| BigNum::from_ptr(p))
         }
     }
+
+    /// Generates a new random prime of `bits` bits.
+    ///
+    /// If `safe` is `true`, the prime `p` is a safe prime, meaning `(p - 1) / 2` is also prime.
+    /// See [`BigNumRef::generate_prime`] for control over the congruence of the result.
+    ///
+    /// ```
+    /// # use boring::bn::{BigNum, BigNumContext};
+    /// let p = BigNum::new_prime(128, false).unwrap();
+    /// let mut ctx = BigNumContext::new().unwrap();
+    ///
+    /// assert_eq!(p.num_bits(), 128);
+    /// assert!(p.is_prime(20, &mut ctx).unwrap());
+    /// ```
+    ///
+    /// [`BigNumRef::generate_prime`]: struct.BigNumRef.html#method.generate_prime
+    pub fn new_prime(bits: i32, safe: bool) -> Result<BigNum, ErrorStack> {
+        let mut p = BigNum::new()?;
+        p.generate_prime(bits, safe, None, None)?;
+        Ok(p)
+    }
 }
 
 impl fmt::Debug for BigNumRef {
@@ -1279,6 +1314,34 @@ mod tests {
         assert_eq!(a, &(&a << 1) >> 1);
     }
 
+    #[test]
+    fn test_bits() {
+        let mut a = BigNum::new().unwrap();
+        a.set_bit(0).unwrap();
+        a.set_bit(5).unwrap();
+        a.set_bit(70).unwrap();
+        assert_eq!(a.num_bits(), 71);
+        assert_eq!(a.num_bytes(), 9);
+        assert!(a.is_bit_set(0) && a.is_bit_set(5) && a.is_bit_set(70));
+        assert!(!a.is_bit_set(1) && !a.is_bit_set(69) && !a.is_bit_set(71));
+
+        a.clear_bit(70).unwrap();
+        assert!(!a.is_bit_set(70));
+        assert_eq!(a.num_bits(), 6);
+        assert_eq!(a, BigNum::from_u32(0b10_0001).unwrap());
+
+        let mut b = BigNum::new().unwrap();
+        b.lshift(&a, 64).unwrap();
+        assert_eq!(b.num_bits(), 70);
+        assert!(b.is_bit_set(64) && b.is_bit_set(69));
+        b.mask_bits(68).unwrap();
+        assert!(b.is_bit_set(64) && !b.is_bit_set(69));
+
+        let mut c = BigNum::new().unwrap();
+        c.rshift(&b, 64).unwrap();
+        assert_eq!(c, BigNum::from_u32(1).unwrap());
+    }
+
     #[test]
     fn test_rand_range() {
         let range = BigNum::from_u32(909_829_283).unwrap();
@@ -1305,4 +1368,32 @@ mod tests {
         assert!(p.is_prime(100, &mut ctx).unwrap());
         assert!(p.is_prime_fasttest(100, &mut ctx, true).unwrap());
     }
+
+    #[test]
+    fn test_new_prime() {
+        let p = BigNum::new_prime(512, false).unwrap();
+        assert_eq!(p.num_bits(), 512);
+
+        let mut ctx = BigNumContext::new().unwrap();
+        assert!(p.is_prime(64, &mut ctx).unwrap());
+
+        let mut composite = BigNum::new().unwrap();
+        composite.checked_mul(&p, &p, &mut ctx).unwrap();
+        assert!(!composite.is_prime(64, &mut ctx).unwrap());
+    }
+
+    #[test]
+    fn test_gcd() {
+        let a = BigNum::from_u32(1071).unwrap();
+        let b = BigNum::from_u32(462).unwrap();
+        let mut ctx = BigNumContext::new().unwrap();
+
+        let mut r = BigNum::new().unwrap();
+        r.gcd(&a, &b, &mut ctx).unwrap();
+        assert_eq!(r, BigNum::from_u32(21).unwrap());
+
+        let p = BigNum::from_u32(65_537).unwrap();
+        r.gcd(&a, &p, &mut ctx).unwrap();
+        assert_eq!(r, BigNum::from_u32(1).unwrap());
+    }
 }
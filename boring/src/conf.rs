@@ -34,6 +34,9 @@ foreign_type_and_impl_send_sync! {
 impl Conf {
     /// Create a configuration parser.
     pub fn new(method: ConfMethod) -> Result<Conf, ErrorStack> {
-        unsafe { cvt_p(ffi::NCONF_new(method.as_ptr())).map(|p| Conf::from_ptr(p)) }
+        unsafe {
+            ffi::init();
+            cvt_p(ffi::NCONF_new(method.as_ptr())).map(|p| Conf::from_ptr(p))
+        }
     }
 }
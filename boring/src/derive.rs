@@ -26,6 +26,8 @@ impl<'a> Deriver<'a> {
         T: HasPrivate,
     {
         unsafe {
+            ffi::init();
+
             cvt_p(ffi::EVP_PKEY_CTX_new(key.as_ptr(), ptr::null_mut()))
                 .map(|p| Deriver(p, PhantomData))
                 .and_then(|ctx| cvt(ffi::EVP_PKEY_derive_init(ctx.0)).map(|_| ctx))
@@ -4,9 +4,9 @@ use foreign_types::{ForeignType, ForeignTypeRef};
 use std::mem;
 use std::ptr;
 
-use crate::bn::BigNum;
-use crate::pkey::{HasParams, Params};
-use crate::{cvt, cvt_p};
+use crate::bn::{BigNum, BigNumRef};
+use crate::pkey::{HasParams, HasPrivate, HasPublic, Params, Private};
+use crate::{cvt, cvt_n, cvt_p};
 
 generic_foreign_type_and_impl_send_sync! {
     type CType = ffi::DH;
@@ -47,6 +47,7 @@ where
 impl Dh<Params> {
     pub fn from_params(p: BigNum, g: BigNum, q: BigNum) -> Result<Dh<Params>, ErrorStack> {
         unsafe {
+            ffi::init();
             let dh = Dh::from_ptr(cvt_p(ffi::DH_new())?);
             cvt(DH_set0_pqg(dh.0, p.as_ptr(), q.as_ptr(), g.as_ptr()))?;
             mem::forget((p, g, q));
@@ -54,6 +55,42 @@ impl Dh<Params> {
         }
     }
 
+    /// Generates new DH parameters with a safe prime `p` of `prime_len` bits and generator
+    /// `generator`, which is usually 2 or 5.
+    ///
+    /// This is slow for realistic sizes. Prefer well-known parameters where possible.
+    ///
+    /// This corresponds to [`DH_generate_parameters_ex`].
+    ///
+    /// [`DH_generate_parameters_ex`]: https://www.openssl.org/docs/man1.1.0/crypto/DH_generate_parameters_ex.html
+    pub fn generate_params(prime_len: u32, generator: u32) -> Result<Dh<Params>, ErrorStack> {
+        unsafe {
+            ffi::init();
+            let dh = Dh::from_ptr(cvt_p(ffi::DH_new())?);
+            cvt(ffi::DH_generate_parameters_ex(
+                dh.0,
+                prime_len as i32,
+                generator as i32,
+                ptr::null_mut(),
+            ))?;
+            Ok(dh)
+        }
+    }
+
+    /// Generates a key pair from these parameters.
+    ///
+    /// This corresponds to [`DH_generate_key`].
+    ///
+    /// [`DH_generate_key`]: https://www.openssl.org/docs/man1.1.0/crypto/DH_generate_key.html
+    pub fn generate_key(self) -> Result<Dh<Private>, ErrorStack> {
+        unsafe {
+            let dh_ptr = self.0;
+            cvt(ffi::DH_generate_key(dh_ptr))?;
+            mem::forget(self);
+            Ok(Dh::from_ptr(dh_ptr))
+        }
+    }
+
     from_pem! {
         /// Deserializes a PEM-encoded PKCS#3 DHpararameters structure.
         ///
@@ -80,6 +117,45 @@ impl Dh<Params> {
     }
 }
 
+impl<T> DhRef<T>
+where
+    T: HasPublic,
+{
+    /// Returns the public key.
+    ///
+    /// This corresponds to [`DH_get0_pub_key`].
+    ///
+    /// [`DH_get0_pub_key`]: https://www.openssl.org/docs/man1.1.0/crypto/DH_get0_pub_key.html
+    pub fn public_key(&self) -> &BigNumRef {
+        unsafe { BigNumRef::from_ptr(ffi::DH_get0_pub_key(self.as_ptr()) as *mut _) }
+    }
+}
+
+impl<T> DhRef<T>
+where
+    T: HasPrivate,
+{
+    /// Computes the shared secret with the peer's public key.
+    ///
+    /// Leading zero bytes of the secret are stripped, as in OpenSSL.
+    ///
+    /// This corresponds to [`DH_compute_key`].
+    ///
+    /// [`DH_compute_key`]: https://www.openssl.org/docs/man1.1.0/crypto/DH_compute_key.html
+    pub fn compute_key(&self, public_key: &BigNumRef) -> Result<Vec<u8>, ErrorStack> {
+        unsafe {
+            let mut key = vec![0; ffi::DH_size(self.as_ptr()) as usize];
+            let len = cvt_n(ffi::DH_compute_key(
+                key.as_mut_ptr(),
+                public_key.as_ptr(),
+                self.as_ptr(),
+            ))?;
+            key.truncate(len as usize);
+            Ok(key)
+        }
+    }
+}
+
 use crate::ffi::DH_set0_pqg;
 
 #[cfg(test)]
@@ -123,6 +199,27 @@ mod tests {
         ctx.set_tmp_dh(&dh).unwrap();
     }
 
+    #[test]
+    fn test_dh_key_agreement() {
+        let params = include_bytes!("../test/dhparams.pem");
+        let alice = Dh::params_from_pem(params).unwrap().generate_key().unwrap();
+        let bob = Dh::params_from_pem(params).unwrap().generate_key().unwrap();
+        assert_ne!(alice.public_key(), bob.public_key());
+
+        let alice_secret = alice.compute_key(bob.public_key()).unwrap();
+        let bob_secret = bob.compute_key(alice.public_key()).unwrap();
+        assert!(!alice_secret.is_empty());
+        assert_eq!(alice_secret, bob_secret);
+    }
+
+    #[test]
+    fn test_dh_generate_params() {
+        let dh = Dh::generate_params(512, 2).unwrap();
+        let pem = dh.params_to_pem().unwrap();
+        let key = Dh::params_from_pem(&pem).unwrap().generate_key().unwrap();
+        assert!(key.public_key().num_bits() <= 512);
+    }
+
     #[test]
     fn test_dh_from_der() {
         let params = include_bytes!("../test/dhparams.pem");
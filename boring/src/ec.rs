@@ -18,6 +18,7 @@
 use crate::ffi;
 use foreign_types::{ForeignType, ForeignTypeRef};
 use libc::c_int;
+use std::convert::TryFrom;
 use std::fmt;
 use std::ptr;
 
@@ -441,6 +442,35 @@ impl EcPointRef {
         }
     }
 
+    /// Determines if this point lies on the curve described by `group`.
+    ///
+    /// OpenSSL documentation at [`EC_POINT_is_on_curve`]
+    ///
+    /// [`EC_POINT_is_on_curve`]: https://www.openssl.org/docs/man1.1.0/crypto/EC_POINT_is_on_curve.html
+    pub fn is_on_curve(
+        &self,
+        group: &EcGroupRef,
+        ctx: &mut BigNumContextRef,
+    ) -> Result<bool, ErrorStack> {
+        unsafe {
+            let res = cvt_n(ffi::EC_POINT_is_on_curve(
+                group.as_ptr(),
+                self.as_ptr(),
+                ctx.as_ptr(),
+            ))?;
+            Ok(res == 1)
+        }
+    }
+
+    /// Determines if this point is the point at infinity.
+    ///
+    /// OpenSSL documentation at [`EC_POINT_is_at_infinity`]
+    ///
+    /// [`EC_POINT_is_at_infinity`]: https://www.openssl.org/docs/man1.1.0/crypto/EC_POINT_is_at_infinity.html
+    pub fn is_infinity(&self, group: &EcGroupRef) -> bool {
+        unsafe { ffi::EC_POINT_is_at_infinity(group.as_ptr(), self.as_ptr()) == 1 }
+    }
+
     /// Place affine coordinates of a curve over a prime field in the provided
     /// `x` and `y` `BigNum`s
     ///
@@ -474,7 +504,10 @@ impl EcPoint {
     ///
     /// [`EC_POINT_new`]: https://www.openssl.org/docs/man1.1.0/crypto/EC_POINT_new.html
     pub fn new(group: &EcGroupRef) -> Result<EcPoint, ErrorStack> {
-        unsafe { cvt_p(ffi::EC_POINT_new(group.as_ptr())).map(|p| EcPoint::from_ptr(p)) }
+        unsafe {
+            init();
+            cvt_p(ffi::EC_POINT_new(group.as_ptr())).map(|p| EcPoint::from_ptr(p))
+        }
     }
 
     /// Creates point from a binary representation
@@ -665,6 +698,7 @@ impl EcKey<Params> {
     /// [`EC_KEY_set_group`]: https://www.openssl.org/docs/man1.1.0/crypto/EC_KEY_new.html
     pub fn from_group(group: &EcGroupRef) -> Result<EcKey<Params>, ErrorStack> {
         unsafe {
+            init();
             cvt_p(ffi::EC_KEY_new())
                 .map(|p| EcKey::from_ptr(p))
                 .and_then(|key| {
@@ -701,6 +735,7 @@ impl EcKey<Public> {
         public_key: &EcPointRef,
     ) -> Result<EcKey<Public>, ErrorStack> {
         unsafe {
+            init();
             cvt_p(ffi::EC_KEY_new())
                 .map(|p| EcKey::from_ptr(p))
                 .and_then(|key| {
@@ -723,6 +758,7 @@ impl EcKey<Public> {
         y: &BigNumRef,
     ) -> Result<EcKey<Public>, ErrorStack> {
         unsafe {
+            init();
             cvt_p(ffi::EC_KEY_new())
                 .map(|p| EcKey::from_ptr(p))
                 .and_then(|key| {
@@ -769,6 +805,7 @@ impl EcKey<Private> {
     /// Generates a new public/private key pair on the specified curve.
     pub fn generate(group: &EcGroupRef) -> Result<EcKey<Private>, ErrorStack> {
         unsafe {
+            init();
             cvt_p(ffi::EC_KEY_new())
                 .map(|p| EcKey::from_ptr(p))
                 .and_then(|key| {
@@ -785,6 +822,7 @@ impl EcKey<Private> {
         public_key: &EcPointRef,
     ) -> Result<EcKey<Private>, ErrorStack> {
         unsafe {
+            init();
             cvt_p(ffi::EC_KEY_new())
                 .map(|p| EcKey::from_ptr(p))
                 .and_then(|key| {
@@ -847,6 +885,18 @@ impl EcKey<Private> {
     }
 }
 
+impl<'a> TryFrom<&'a [u8]> for EcKey<Public> {
+    type Error = ErrorStack;
+
+    /// Decodes a DER-encoded SubjectPublicKeyInfo structure containing a EC key, like
+    /// [`EcKey::public_key_from_der`].
+    ///
+    /// [`EcKey::public_key_from_der`]: struct.EcKey.html#method.public_key_from_der
+    fn try_from(der: &'a [u8]) -> Result<EcKey<Public>, ErrorStack> {
+        EcKey::public_key_from_der(der)
+    }
+}
+
 impl<T> Clone for EcKey<T> {
     fn clone(&self) -> EcKey<T> {
         (**self).to_owned()
@@ -948,6 +998,71 @@ mod test {
         assert!(ecp.eq(&group, gen, &mut ctx).unwrap());
     }
 
+    #[test]
+    fn point_arithmetic() {
+        let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).unwrap();
+        let mut ctx = BigNumContext::new().unwrap();
+
+        // NIST P-256 point multiplication test vector
+        let k = BigNum::from_dec_str("112233445566778899").unwrap();
+        let mut p = EcPoint::new(&group).unwrap();
+        p.mul(&group, group.generator(), &k, &ctx).unwrap();
+        assert!(p.is_on_curve(&group, &mut ctx).unwrap());
+
+        let mut x = BigNum::new().unwrap();
+        let mut y = BigNum::new().unwrap();
+        p.affine_coordinates_gfp(&group, &mut x, &mut y, &mut ctx)
+            .unwrap();
+        assert_eq!(
+            x,
+            BigNum::from_hex_str(
+                "339150844EC15234807FE862A86BE77977DBFB3AE3D96F4C22795513AEAAB82F"
+            )
+            .unwrap()
+        );
+        assert_eq!(
+            y,
+            BigNum::from_hex_str(
+                "B1C14DDFDC8EC1B2583F51E85A5EB3A155840F2034730E9B5ADA38B674336A21"
+            )
+            .unwrap()
+        );
+
+        // k * G + k * G == 2k * G
+        let mut sum = EcPoint::new(&group).unwrap();
+        sum.add(&group, &p, &p, &mut ctx).unwrap();
+        let mut two_k = BigNum::new().unwrap();
+        two_k.checked_add(&k, &k).unwrap();
+        let mut doubled = EcPoint::new(&group).unwrap();
+        doubled.mul_generator(&group, &two_k, &ctx).unwrap();
+        assert!(sum.eq(&group, &doubled, &mut ctx).unwrap());
+
+        // k * G + -(k * G) is the point at infinity
+        let mut neg = p.to_owned(&group).unwrap();
+        neg.invert(&group, &ctx).unwrap();
+        let mut zero = EcPoint::new(&group).unwrap();
+        zero.add(&group, &p, &neg, &mut ctx).unwrap();
+        assert!(zero.is_infinity(&group));
+        assert!(!p.is_infinity(&group));
+
+        let bytes = p
+            .to_bytes(&group, PointConversionForm::COMPRESSED, &mut ctx)
+            .unwrap();
+        let p2 = EcPoint::from_bytes(&group, &bytes, &mut ctx).unwrap();
+        assert!(p.eq(&group, &p2, &mut ctx).unwrap());
+    }
+
+    #[test]
+    fn public_key_try_from() {
+        let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).unwrap();
+        let key = EcKey::generate(&group).unwrap();
+        let der = key.public_key_to_der().unwrap();
+
+        let public = EcKey::try_from(&der[..]).unwrap();
+        assert_eq!(public.public_key_to_der().unwrap(), der);
+        assert!(EcKey::<Public>::try_from(&b"garbage"[..]).is_err());
+    }
+
     #[test]
     fn key_from_public_key() {
         let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).unwrap();
@@ -15,7 +15,7 @@
 //!     Err(e) => println!("Parsing Error: {:?}", e),
 //! }
 //! ```
-use libc::{c_char, c_uint};
+use libc::{c_char, c_int, c_uint};
 use std::borrow::Cow;
 use std::error;
 use std::ffi::CStr;
@@ -26,6 +26,23 @@ use std::str;
 
 use crate::ffi;
 
+/// Pushes an error with the given library and reason codes onto the error stack, and returns the
+/// stack.
+///
+/// This lets argument checks done on the Rust side fail the same way as BoringSSL's own.
+pub(crate) fn put_error(lib: c_int, reason: c_int) -> ErrorStack {
+    unsafe {
+        ffi::ERR_put_error(
+            lib,
+            0,
+            reason,
+            concat!(file!(), "\0").as_ptr() as *const _,
+            line!(),
+        );
+    }
+    ErrorStack::get()
+}
+
 /// Collection of [`Error`]s from OpenSSL.
 ///
 /// [`Error`]: struct.Error.html
@@ -48,6 +65,19 @@ impl ErrorStack {
             error.put();
         }
     }
+
+    /// Clears the current thread's OpenSSL error stack.
+    ///
+    /// Wrappers in this crate call this before invoking BoringSSL functions whose failure is
+    /// reported through the error stack, so that a subsequent `ErrorStack::get` only reflects
+    /// errors from that call rather than stale entries left behind by earlier operations.
+    ///
+    /// This corresponds to [`ERR_clear_error`].
+    ///
+    /// [`ERR_clear_error`]: https://commondatastorage.googleapis.com/chromium-boringssl-docs/err.h.html#ERR_clear_error
+    pub fn clear() {
+        unsafe { ffi::ERR_clear_error() }
+    }
 }
 
 impl ErrorStack {
@@ -77,6 +107,10 @@ impl fmt::Display for ErrorStack {
 
 impl error::Error for ErrorStack {}
 
+/// Wraps the `ErrorStack` in an `io::Error` of kind `ErrorKind::Other`.
+///
+/// The `ErrorStack` is kept as the custom error payload, and can be recovered with
+/// `io::Error::get_ref` or `io::Error::into_inner` followed by a downcast.
 impl From<ErrorStack> for io::Error {
     fn from(e: ErrorStack) -> io::Error {
         io::Error::new(io::ErrorKind::Other, e)
@@ -264,3 +298,43 @@ impl fmt::Display for Error {
 }
 
 impl error::Error for Error {}
+
+#[cfg(test)]
+mod tests {
+    use super::ErrorStack;
+    use crate::bn::BigNum;
+    use crate::x509::X509;
+    use std::io;
+
+    #[test]
+    fn stale_errors_are_cleared() {
+        let stale = BigNum::from_dec_str("Cannot parse letters").unwrap_err();
+        assert!(!stale.errors().is_empty());
+        stale.put();
+
+        X509::from_pem(include_bytes!("../test/cert.pem")).unwrap();
+        assert!(ErrorStack::get().errors().is_empty());
+    }
+
+    #[test]
+    fn clear() {
+        BigNum::from_dec_str("Cannot parse letters")
+            .unwrap_err()
+            .put();
+        ErrorStack::clear();
+        assert!(ErrorStack::get().errors().is_empty());
+    }
+
+    #[test]
+    fn into_io_error() {
+        let stack = BigNum::from_dec_str("Cannot parse letters").unwrap_err();
+        let description = stack.to_string();
+
+        let err = io::Error::from(stack);
+        assert_eq!(err.kind(), io::ErrorKind::Other);
+        assert_eq!(err.to_string(), description);
+
+        let stack = err.into_inner().unwrap().downcast::<ErrorStack>().unwrap();
+        assert!(!stack.errors().is_empty());
+    }
+}
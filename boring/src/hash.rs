@@ -1,5 +1,6 @@
 use crate::ffi;
 use std::convert::TryInto;
+use std::ffi::CString;
 use std::fmt;
 use std::io;
 use std::io::prelude::*;
@@ -40,6 +41,23 @@ impl MessageDigest {
         }
     }
 
+    /// Returns the `MessageDigest` corresponding to a short or long name, such as `"sha256"`.
+    ///
+    /// This corresponds to [`EVP_get_digestbyname`].
+    ///
+    /// [`EVP_get_digestbyname`]: https://www.openssl.org/docs/man1.1.0/crypto/EVP_DigestInit.html
+    pub fn from_name(name: &str) -> Option<MessageDigest> {
+        let name = CString::new(name).ok()?;
+        unsafe {
+            let ptr = ffi::EVP_get_digestbyname(name.as_ptr());
+            if ptr.is_null() {
+                None
+            } else {
+                Some(MessageDigest(ptr))
+            }
+        }
+    }
+
     pub fn md5() -> MessageDigest {
         unsafe { MessageDigest(ffi::EVP_md5()) }
     }
@@ -452,4 +470,15 @@ mod tests {
             MessageDigest::sha256().as_ptr()
         );
     }
+
+    #[test]
+    fn from_name() {
+        let digest = MessageDigest::from_name("sha256").unwrap();
+        assert_eq!(digest.as_ptr(), MessageDigest::sha256().as_ptr());
+        assert_eq!(digest.size(), 32);
+        assert_eq!(digest.type_(), Nid::SHA256);
+
+        assert!(MessageDigest::from_name("not-a-digest").is_none());
+        assert!(MessageDigest::from_name("sha\0256").is_none());
+    }
 }
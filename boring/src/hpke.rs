@@ -0,0 +1,438 @@
+//! Hybrid Public Key Encryption (HPKE), as defined in [RFC 9180].
+//!
+//! HPKE encrypts messages to the holder of a private key. The sender derives an encryption context
+//! from the recipient's public key and sends the resulting encapsulated key, `enc`, along with the
+//! ciphertexts; the recipient derives the same context from `enc` and its private key. Only the
+//! base mode is supported.
+//!
+//! # Examples
+//!
+//! ```
+//! use boring::hpke::{HpkeAead, HpkeCtx, HpkeKdf, HpkeKem, HpkeKey};
+//!
+//! let kem = HpkeKem::x25519_hkdf_sha256();
+//! let kdf = HpkeKdf::hkdf_sha256();
+//! let aead = HpkeAead::aes_128_gcm();
+//!
+//! let key = HpkeKey::generate(kem).unwrap();
+//! let public_key = key.public_key().unwrap();
+//!
+//! let (mut sender, enc) = HpkeCtx::setup_sender(kem, kdf, aead, &public_key, b"info").unwrap();
+//! let ciphertext = sender.seal(b"hello", b"aad").unwrap();
+//!
+//! let mut recipient = HpkeCtx::setup_recipient(&key, kdf, aead, &enc, b"info").unwrap();
+//! assert_eq!(recipient.open(&ciphertext, b"aad").unwrap(), b"hello");
+//! ```
+//!
+//! [RFC 9180]: https://www.rfc-editor.org/rfc/rfc9180.html
+use crate::ffi;
+use foreign_types::{ForeignType, ForeignTypeRef};
+
+use crate::error::ErrorStack;
+use crate::{cvt_0i, cvt_p};
+
+/// An HPKE key encapsulation mechanism.
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub struct HpkeKem(*const ffi::EVP_HPKE_KEM);
+
+impl HpkeKem {
+    /// DHKEM(X25519, HKDF-SHA256).
+    pub fn x25519_hkdf_sha256() -> HpkeKem {
+        unsafe { HpkeKem(ffi::EVP_hpke_x25519_hkdf_sha256()) }
+    }
+
+    /// Returns the KEM's identifier from the HPKE registry.
+    pub fn id(&self) -> u16 {
+        unsafe { ffi::EVP_HPKE_KEM_id(self.0) }
+    }
+
+    pub fn as_ptr(&self) -> *const ffi::EVP_HPKE_KEM {
+        self.0
+    }
+}
+
+unsafe impl Sync for HpkeKem {}
+unsafe impl Send for HpkeKem {}
+
+/// An HPKE key derivation function.
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub struct HpkeKdf(*const ffi::EVP_HPKE_KDF);
+
+impl HpkeKdf {
+    /// HKDF-SHA256.
+    pub fn hkdf_sha256() -> HpkeKdf {
+        unsafe { HpkeKdf(ffi::EVP_hpke_hkdf_sha256()) }
+    }
+
+    /// Returns the KDF's identifier from the HPKE registry.
+    pub fn id(&self) -> u16 {
+        unsafe { ffi::EVP_HPKE_KDF_id(self.0) }
+    }
+
+    pub fn as_ptr(&self) -> *const ffi::EVP_HPKE_KDF {
+        self.0
+    }
+}
+
+unsafe impl Sync for HpkeKdf {}
+unsafe impl Send for HpkeKdf {}
+
+/// An HPKE AEAD.
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub struct HpkeAead(*const ffi::EVP_HPKE_AEAD);
+
+impl HpkeAead {
+    /// AES-128-GCM.
+    pub fn aes_128_gcm() -> HpkeAead {
+        unsafe { HpkeAead(ffi::EVP_hpke_aes_128_gcm()) }
+    }
+
+    /// AES-256-GCM.
+    pub fn aes_256_gcm() -> HpkeAead {
+        unsafe { HpkeAead(ffi::EVP_hpke_aes_256_gcm()) }
+    }
+
+    /// ChaCha20-Poly1305.
+    pub fn chacha20_poly1305() -> HpkeAead {
+        unsafe { HpkeAead(ffi::EVP_hpke_chacha20_poly1305()) }
+    }
+
+    /// Returns the AEAD's identifier from the HPKE registry.
+    pub fn id(&self) -> u16 {
+        unsafe { ffi::EVP_HPKE_AEAD_id(self.0) }
+    }
+
+    pub fn as_ptr(&self) -> *const ffi::EVP_HPKE_AEAD {
+        self.0
+    }
+}
+
+unsafe impl Sync for HpkeAead {}
+unsafe impl Send for HpkeAead {}
+
+foreign_type_and_impl_send_sync! {
+    type CType = ffi::EVP_HPKE_KEY;
+    fn drop = ffi::EVP_HPKE_KEY_free;
+
+    /// An HPKE recipient's private key, along with its KEM.
+    pub struct HpkeKey;
+}
+
+impl HpkeKey {
+    /// Generates a new random key for the given KEM.
+    ///
+    /// This corresponds to [`EVP_HPKE_KEY_generate`].
+    ///
+    /// [`EVP_HPKE_KEY_generate`]: https://commondatastorage.googleapis.com/chromium-boringssl-docs/hpke.h.html#EVP_HPKE_KEY_generate
+    pub fn generate(kem: HpkeKem) -> Result<HpkeKey, ErrorStack> {
+        unsafe {
+            ffi::init();
+            let key = HpkeKey::from_ptr(cvt_p(ffi::EVP_HPKE_KEY_new())?);
+            cvt_0i(ffi::EVP_HPKE_KEY_generate(key.as_ptr(), kem.as_ptr()))?;
+            Ok(key)
+        }
+    }
+
+    /// Creates a key for the given KEM from its serialized private key.
+    ///
+    /// This corresponds to [`EVP_HPKE_KEY_init`].
+    ///
+    /// [`EVP_HPKE_KEY_init`]: https://commondatastorage.googleapis.com/chromium-boringssl-docs/hpke.h.html#EVP_HPKE_KEY_init
+    pub fn from_private_key(kem: HpkeKem, private_key: &[u8]) -> Result<HpkeKey, ErrorStack> {
+        unsafe {
+            ffi::init();
+            let key = HpkeKey::from_ptr(cvt_p(ffi::EVP_HPKE_KEY_new())?);
+            cvt_0i(ffi::EVP_HPKE_KEY_init(
+                key.as_ptr(),
+                kem.as_ptr(),
+                private_key.as_ptr(),
+                private_key.len(),
+            ))?;
+            Ok(key)
+        }
+    }
+}
+
+impl HpkeKeyRef {
+    /// Returns the key's KEM.
+    ///
+    /// This corresponds to [`EVP_HPKE_KEY_kem`].
+    ///
+    /// [`EVP_HPKE_KEY_kem`]: https://commondatastorage.googleapis.com/chromium-boringssl-docs/hpke.h.html#EVP_HPKE_KEY_kem
+    pub fn kem(&self) -> HpkeKem {
+        unsafe { HpkeKem(ffi::EVP_HPKE_KEY_kem(self.as_ptr())) }
+    }
+
+    /// Returns the serialized public key, which senders pass to [`HpkeCtx::setup_sender`].
+    ///
+    /// This corresponds to [`EVP_HPKE_KEY_public_key`].
+    ///
+    /// [`HpkeCtx::setup_sender`]: struct.HpkeCtx.html#method.setup_sender
+    /// [`EVP_HPKE_KEY_public_key`]: https://commondatastorage.googleapis.com/chromium-boringssl-docs/hpke.h.html#EVP_HPKE_KEY_public_key
+    pub fn public_key(&self) -> Result<Vec<u8>, ErrorStack> {
+        unsafe {
+            let mut buf = vec![0; ffi::EVP_HPKE_MAX_PUBLIC_KEY_LENGTH as usize];
+            let mut len = 0;
+            cvt_0i(ffi::EVP_HPKE_KEY_public_key(
+                self.as_ptr(),
+                buf.as_mut_ptr(),
+                &mut len,
+                buf.len(),
+            ))?;
+            buf.truncate(len);
+            Ok(buf)
+        }
+    }
+
+    /// Returns the serialized private key.
+    ///
+    /// This corresponds to [`EVP_HPKE_KEY_private_key`].
+    ///
+    /// [`EVP_HPKE_KEY_private_key`]: https://commondatastorage.googleapis.com/chromium-boringssl-docs/hpke.h.html#EVP_HPKE_KEY_private_key
+    pub fn private_key(&self) -> Result<Vec<u8>, ErrorStack> {
+        unsafe {
+            let mut buf = vec![0; ffi::EVP_HPKE_MAX_PRIVATE_KEY_LENGTH as usize];
+            let mut len = 0;
+            cvt_0i(ffi::EVP_HPKE_KEY_private_key(
+                self.as_ptr(),
+                buf.as_mut_ptr(),
+                &mut len,
+                buf.len(),
+            ))?;
+            buf.truncate(len);
+            Ok(buf)
+        }
+    }
+}
+
+foreign_type_and_impl_send_sync! {
+    type CType = ffi::EVP_HPKE_CTX;
+    fn drop = ffi::EVP_HPKE_CTX_free;
+
+    /// An HPKE encryption context, set up for either a sender or a recipient.
+    ///
+    /// Each message sealed or opened advances the context's sequence number, so messages must be
+    /// opened in the order they were sealed.
+    pub struct HpkeCtx;
+}
+
+impl HpkeCtx {
+    /// Sets up a sender context encrypting to `peer_public_key`.
+    ///
+    /// Returns the context and the encapsulated key, `enc`, which must be sent to the recipient.
+    ///
+    /// This corresponds to [`EVP_HPKE_CTX_setup_sender`].
+    ///
+    /// [`EVP_HPKE_CTX_setup_sender`]: https://commondatastorage.googleapis.com/chromium-boringssl-docs/hpke.h.html#EVP_HPKE_CTX_setup_sender
+    pub fn setup_sender(
+        kem: HpkeKem,
+        kdf: HpkeKdf,
+        aead: HpkeAead,
+        peer_public_key: &[u8],
+        info: &[u8],
+    ) -> Result<(HpkeCtx, Vec<u8>), ErrorStack> {
+        unsafe {
+            ffi::init();
+            let ctx = HpkeCtx::from_ptr(cvt_p(ffi::EVP_HPKE_CTX_new())?);
+            let mut enc = vec![0; ffi::EVP_HPKE_MAX_ENC_LENGTH as usize];
+            let mut enc_len = 0;
+            cvt_0i(ffi::EVP_HPKE_CTX_setup_sender(
+                ctx.as_ptr(),
+                enc.as_mut_ptr(),
+                &mut enc_len,
+                enc.len(),
+                kem.as_ptr(),
+                kdf.as_ptr(),
+                aead.as_ptr(),
+                peer_public_key.as_ptr(),
+                peer_public_key.len(),
+                info.as_ptr(),
+                info.len(),
+            ))?;
+            enc.truncate(enc_len);
+            Ok((ctx, enc))
+        }
+    }
+
+    /// Sets up a recipient context from the sender's encapsulated key, `enc`.
+    ///
+    /// This corresponds to [`EVP_HPKE_CTX_setup_recipient`].
+    ///
+    /// [`EVP_HPKE_CTX_setup_recipient`]: https://commondatastorage.googleapis.com/chromium-boringssl-docs/hpke.h.html#EVP_HPKE_CTX_setup_recipient
+    pub fn setup_recipient(
+        key: &HpkeKeyRef,
+        kdf: HpkeKdf,
+        aead: HpkeAead,
+        enc: &[u8],
+        info: &[u8],
+    ) -> Result<HpkeCtx, ErrorStack> {
+        unsafe {
+            ffi::init();
+            let ctx = HpkeCtx::from_ptr(cvt_p(ffi::EVP_HPKE_CTX_new())?);
+            cvt_0i(ffi::EVP_HPKE_CTX_setup_recipient(
+                ctx.as_ptr(),
+                key.as_ptr(),
+                kdf.as_ptr(),
+                aead.as_ptr(),
+                enc.as_ptr(),
+                enc.len(),
+                info.as_ptr(),
+                info.len(),
+            ))?;
+            Ok(ctx)
+        }
+    }
+}
+
+impl HpkeCtxRef {
+    /// Encrypts and authenticates `plaintext` and authenticates `aad`, returning the ciphertext.
+    ///
+    /// This corresponds to [`EVP_HPKE_CTX_seal`].
+    ///
+    /// [`EVP_HPKE_CTX_seal`]: https://commondatastorage.googleapis.com/chromium-boringssl-docs/hpke.h.html#EVP_HPKE_CTX_seal
+    pub fn seal(&mut self, plaintext: &[u8], aad: &[u8]) -> Result<Vec<u8>, ErrorStack> {
+        unsafe {
+            let mut out = vec![0; plaintext.len() + ffi::EVP_HPKE_CTX_max_overhead(self.as_ptr())];
+            let mut len = 0;
+            cvt_0i(ffi::EVP_HPKE_CTX_seal(
+                self.as_ptr(),
+                out.as_mut_ptr(),
+                &mut len,
+                out.len(),
+                plaintext.as_ptr(),
+                plaintext.len(),
+                aad.as_ptr(),
+                aad.len(),
+            ))?;
+            out.truncate(len);
+            Ok(out)
+        }
+    }
+
+    /// Decrypts `ciphertext` and checks it and `aad` are authentic, returning the plaintext.
+    ///
+    /// This corresponds to [`EVP_HPKE_CTX_open`].
+    ///
+    /// [`EVP_HPKE_CTX_open`]: https://commondatastorage.googleapis.com/chromium-boringssl-docs/hpke.h.html#EVP_HPKE_CTX_open
+    pub fn open(&mut self, ciphertext: &[u8], aad: &[u8]) -> Result<Vec<u8>, ErrorStack> {
+        unsafe {
+            let mut out = vec![0; ciphertext.len()];
+            let mut len = 0;
+            cvt_0i(ffi::EVP_HPKE_CTX_open(
+                self.as_ptr(),
+                out.as_mut_ptr(),
+                &mut len,
+                out.len(),
+                ciphertext.as_ptr(),
+                ciphertext.len(),
+                aad.as_ptr(),
+                aad.len(),
+            ))?;
+            out.truncate(len);
+            Ok(out)
+        }
+    }
+
+    /// Fills `out` with a secret derived from the context and `context`.
+    ///
+    /// Both ends of a context export the same secrets.
+    ///
+    /// This corresponds to [`EVP_HPKE_CTX_export`].
+    ///
+    /// [`EVP_HPKE_CTX_export`]: https://commondatastorage.googleapis.com/chromium-boringssl-docs/hpke.h.html#EVP_HPKE_CTX_export
+    pub fn export(&self, context: &[u8], out: &mut [u8]) -> Result<(), ErrorStack> {
+        unsafe {
+            cvt_0i(ffi::EVP_HPKE_CTX_export(
+                self.as_ptr(),
+                out.as_mut_ptr(),
+                out.len(),
+                context.as_ptr(),
+                context.len(),
+            ))
+            .map(|_| ())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hex::FromHex;
+
+    // RFC 9180, appendix A.1.1: DHKEM(X25519, HKDF-SHA256), HKDF-SHA256, AES-128-GCM, base mode.
+    const SK_RM: &str = "4612c550263fc8ad58375df3f557aac531d26850903e55a9f23f21d8534e8ac8";
+    const PK_RM: &str = "3948cfe0ad1ddb695d780e59077195da6c56506b027329794ab02bca80815c4d";
+    const ENC: &str = "37fda3567bdbd628e88668c3c8d7e97d1d1253b6d4ea6d44c150f741f1bf4431";
+    const INFO: &str = "4f6465206f6e2061204772656369616e2055726e";
+
+    #[test]
+    fn rfc9180_base_x25519_aes_128_gcm() {
+        let kem = HpkeKem::x25519_hkdf_sha256();
+        let key = HpkeKey::from_private_key(kem, &Vec::from_hex(SK_RM).unwrap()).unwrap();
+        assert_eq!(key.public_key().unwrap(), Vec::from_hex(PK_RM).unwrap());
+        assert_eq!(key.kem().id(), 0x0020);
+
+        let mut recipient = HpkeCtx::setup_recipient(
+            &key,
+            HpkeKdf::hkdf_sha256(),
+            HpkeAead::aes_128_gcm(),
+            &Vec::from_hex(ENC).unwrap(),
+            &Vec::from_hex(INFO).unwrap(),
+        )
+        .unwrap();
+
+        let ct =
+            "f938558b5d72f1a23810b4be2ab4f84331acc02fc97babc53a52ae8218a355a96d8770ac83d07bea87\
+                  e13c512a";
+        let pt = recipient
+            .open(&Vec::from_hex(ct).unwrap(), b"Count-0")
+            .unwrap();
+        assert_eq!(pt, b"Beauty is truth, truth beauty");
+
+        // The sequence number has advanced, so the same ciphertext no longer opens.
+        assert!(recipient
+            .open(&Vec::from_hex(ct).unwrap(), b"Count-0")
+            .is_err());
+    }
+
+    #[test]
+    fn round_trip() {
+        let kem = HpkeKem::x25519_hkdf_sha256();
+        let kdf = HpkeKdf::hkdf_sha256();
+        let key = HpkeKey::generate(kem).unwrap();
+        let key = HpkeKey::from_private_key(kem, &key.private_key().unwrap()).unwrap();
+
+        for &aead in &[
+            HpkeAead::aes_128_gcm(),
+            HpkeAead::aes_256_gcm(),
+            HpkeAead::chacha20_poly1305(),
+        ] {
+            let (mut sender, enc) =
+                HpkeCtx::setup_sender(kem, kdf, aead, &key.public_key().unwrap(), b"info").unwrap();
+            let mut recipient = HpkeCtx::setup_recipient(&key, kdf, aead, &enc, b"info").unwrap();
+
+            for msg in &[&b"first"[..], &b""[..], &b"third message"[..]] {
+                let ct = sender.seal(msg, b"aad").unwrap();
+                assert_eq!(recipient.open(&ct, b"aad").unwrap(), *msg);
+            }
+
+            let ct = sender.seal(b"wrong aad", b"aad").unwrap();
+            assert!(recipient.open(&ct, b"other").is_err());
+
+            let mut sender_secret = [0; 32];
+            let mut recipient_secret = [0; 32];
+            sender.export(b"context", &mut sender_secret).unwrap();
+            recipient.export(b"context", &mut recipient_secret).unwrap();
+            assert_eq!(sender_secret, recipient_secret);
+        }
+
+        // A recipient holding a different key cannot open the messages.
+        let aead = HpkeAead::aes_128_gcm();
+        let (mut sender, enc) =
+            HpkeCtx::setup_sender(kem, kdf, aead, &key.public_key().unwrap(), b"").unwrap();
+        let ct = sender.seal(b"msg", b"").unwrap();
+        let other = HpkeKey::generate(kem).unwrap();
+        let mut recipient = HpkeCtx::setup_recipient(&other, kdf, aead, &enc, b"").unwrap();
+        assert!(recipient.open(&ct, b"").is_err());
+    }
+}
@@ -0,0 +1,109 @@
+//! Key derivation functions.
+use crate::ffi;
+
+use crate::cvt;
+use crate::error::ErrorStack;
+use crate::hash::MessageDigest;
+
+/// The prefix prepended to every label by `hkdf_expand_label`.
+const TLS13_LABEL_PREFIX: &[u8] = b"tls13 ";
+
+/// Derives `length` bytes from `secret` with the TLS 1.3 `HKDF-Expand-Label` function.
+///
+/// The HKDF info is the `HkdfLabel` structure from [RFC 8446, section 7.1]: the output length,
+/// followed by `"tls13 " || label` and `context`, each with a one-byte length prefix. `context` is
+/// usually a transcript hash, or empty.
+///
+/// # Panics
+///
+/// Panics if `length` does not fit in 16 bits, if `label` is longer than 249 bytes, or if
+/// `context` is longer than 255 bytes.
+///
+/// # Examples
+///
+/// ```
+/// use boring::hash::MessageDigest;
+/// use boring::kdf::hkdf_expand_label;
+///
+/// let secret = [0; 32];
+/// let key = hkdf_expand_label(MessageDigest::sha256(), &secret, b"key", b"", 16).unwrap();
+/// assert_eq!(key.len(), 16);
+/// ```
+///
+/// This corresponds to [`HKDF_expand`].
+///
+/// [RFC 8446, section 7.1]: https://tools.ietf.org/html/rfc8446#section-7.1
+/// [`HKDF_expand`]: https://commondatastorage.googleapis.com/chromium-boringssl-docs/hkdf.h.html#HKDF_expand
+pub fn hkdf_expand_label(
+    digest: MessageDigest,
+    secret: &[u8],
+    label: &[u8],
+    context: &[u8],
+    length: usize,
+) -> Result<Vec<u8>, ErrorStack> {
+    assert!(length <= u16::max_value() as usize);
+    assert!(TLS13_LABEL_PREFIX.len() + label.len() <= u8::max_value() as usize);
+    assert!(context.len() <= u8::max_value() as usize);
+
+    let mut info = Vec::with_capacity(4 + TLS13_LABEL_PREFIX.len() + label.len() + context.len());
+    info.extend_from_slice(&(length as u16).to_be_bytes());
+    info.push((TLS13_LABEL_PREFIX.len() + label.len()) as u8);
+    info.extend_from_slice(TLS13_LABEL_PREFIX);
+    info.extend_from_slice(label);
+    info.push(context.len() as u8);
+    info.extend_from_slice(context);
+
+    let mut out = vec![0; length];
+    unsafe {
+        ffi::init();
+        cvt(ffi::HKDF_expand(
+            out.as_mut_ptr(),
+            out.len(),
+            digest.as_ptr(),
+            secret.as_ptr(),
+            secret.len(),
+            info.as_ptr(),
+            info.len(),
+        ))?;
+    }
+
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hash::hash;
+    use hex::{self, FromHex};
+
+    // Values from the "Simple 1-RTT Handshake" trace in RFC 8448.
+    #[test]
+    fn hkdf_expand_label_rfc8448() {
+        let early_secret =
+            Vec::from_hex("33ad0a1c607ec03b09e6cd9893680ce210adf300aa1f2660e1b22e10f170f92a")
+                .unwrap();
+        let empty_hash = hash(MessageDigest::sha256(), b"").unwrap();
+        let derived = hkdf_expand_label(
+            MessageDigest::sha256(),
+            &early_secret,
+            b"derived",
+            &empty_hash,
+            32,
+        )
+        .unwrap();
+        assert_eq!(
+            hex::encode(derived),
+            "6f2615a108c702c5678f54fc9dbab69716c076189c48250cebeac3576c3611ba"
+        );
+
+        let server_hs_secret =
+            Vec::from_hex("b67b7d690cc16c4e75e54213cb2d37b4e9c912bcded9105d42befd59d391ad38")
+                .unwrap();
+        let key =
+            hkdf_expand_label(MessageDigest::sha256(), &server_hs_secret, b"key", b"", 16).unwrap();
+        assert_eq!(hex::encode(key), "3fce516009c21727d0f2e4e86ee403bc");
+        let iv =
+            hkdf_expand_label(MessageDigest::sha256(), &server_hs_secret, b"iv", b"", 12).unwrap();
+        assert_eq!(hex::encode(iv), "5d313eb2671276ee13000b30");
+    }
+}
@@ -14,9 +14,6 @@ extern crate libc;
 #[cfg(test)]
 extern crate hex;
 
-#[doc(inline)]
-pub use crate::ffi::init;
-
 use libc::{c_int, size_t};
 
 use crate::error::ErrorStack;
@@ -41,10 +38,14 @@ pub mod error;
 pub mod ex_data;
 pub mod fips;
 pub mod hash;
+#[cfg(not(feature = "fips"))]
+pub mod hpke;
+pub mod kdf;
 pub mod memcmp;
 pub mod nid;
 pub mod pkcs12;
 pub mod pkcs5;
+pub mod pkcs7;
 pub mod pkey;
 pub mod rand;
 pub mod rsa;
@@ -58,6 +59,32 @@ pub mod symm;
 pub mod version;
 pub mod x509;
 
+/// Initializes the library.
+///
+/// BoringSSL is initialized lazily by every entry point of this crate that needs it, so calling
+/// this function is never required. Every constructor that allocates a BoringSSL object calls it;
+/// accessors that only return static tables, such as `Cipher::aes_128_gcm` or
+/// `MessageDigest::sha256`, do not touch library state and so do not need to. It is useful for embedders who want to control exactly when
+/// initialization happens, for example to run the FIPS power-on self-tests eagerly at startup
+/// rather than on the first handshake.
+///
+/// This function is idempotent and thread-safe: it may be called any number of times from any
+/// number of threads, and the underlying initialization runs exactly once.
+///
+/// # Fork safety
+///
+/// Initialization only sets up process-global, read-only state, so it is safe to call `init`
+/// before forking and to use the library in the child afterwards. BoringSSL's random number
+/// generator detects forks itself and reseeds in the child. Objects created before the fork,
+/// such as `SslContext`s or keys, should not be shared between the parent and the child.
+///
+/// This corresponds to [`CRYPTO_library_init`] and `OPENSSL_init_ssl`.
+///
+/// [`CRYPTO_library_init`]: https://commondatastorage.googleapis.com/chromium-boringssl-docs/crypto.h.html#CRYPTO_library_init
+pub fn init() {
+    ffi::init();
+}
+
 fn cvt_p<T>(r: *mut T) -> Result<*mut T, ErrorStack> {
     if r.is_null() {
         Err(ErrorStack::get())
@@ -97,3 +124,23 @@ fn cvt_n(r: c_int) -> Result<c_int, ErrorStack> {
         Ok(r)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    #[test]
+    fn init_concurrently() {
+        let threads = (0..8)
+            .map(|_| thread::spawn(super::init))
+            .collect::<Vec<_>>();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+
+        // Initialization is idempotent, and the library is usable afterwards.
+        super::init();
+        let mut buf = [0; 16];
+        crate::rand::rand_bytes(&mut buf).unwrap();
+    }
+}
@@ -6,6 +6,7 @@ macro_rules! private_key_from_pem {
         pub fn $n2(pem: &[u8], passphrase: &[u8]) -> Result<$t, crate::error::ErrorStack> {
             unsafe {
                 ffi::init();
+                crate::error::ErrorStack::clear();
                 let bio = crate::bio::MemBioSlice::new(pem)?;
                 let passphrase = ::std::ffi::CString::new(passphrase).unwrap();
                 cvt_p($f(bio.as_ptr(),
@@ -22,6 +23,7 @@ macro_rules! private_key_from_pem {
         {
             unsafe {
                 ffi::init();
+                crate::error::ErrorStack::clear();
                 let mut cb = crate::util::CallbackState::new(callback);
                 let bio = crate::bio::MemBioSlice::new(pem)?;
                 cvt_p($f(bio.as_ptr(),
@@ -108,6 +110,7 @@ macro_rules! from_der {
         pub fn $n(der: &[u8]) -> Result<$t, crate::error::ErrorStack> {
             unsafe {
                 crate::ffi::init();
+                crate::error::ErrorStack::clear();
                 let len = ::std::cmp::min(der.len(), <$len_ty>::max_value() as usize) as $len_ty;
                 crate::cvt_p($f(::std::ptr::null_mut(), &mut der.as_ptr(), len))
                     .map(|p| ::foreign_types::ForeignType::from_ptr(p))
@@ -122,6 +125,7 @@ macro_rules! from_pem {
         pub fn $n(pem: &[u8]) -> Result<$t, crate::error::ErrorStack> {
             unsafe {
                 crate::init();
+                crate::error::ErrorStack::clear();
                 let bio = crate::bio::MemBioSlice::new(pem)?;
                 cvt_p($f(bio.as_ptr(), ::std::ptr::null_mut(), None, ::std::ptr::null_mut()))
                     .map(|p| ::foreign_types::ForeignType::from_ptr(p))
@@ -2,7 +2,7 @@
 use crate::ffi;
 use libc::{c_char, c_int};
 
-use std::ffi::CStr;
+use std::ffi::{CStr, CString};
 use std::str;
 
 use crate::cvt_p;
@@ -103,6 +103,32 @@ impl Nid {
         }
     }
 
+    /// Registers a new object identifier at runtime, returning its newly allocated `Nid`.
+    ///
+    /// `oid` is the dotted numerical form of the OID, such as `"1.3.6.1.4.1.11129.2.4.2"`. Once
+    /// registered, the object can be looked up by its OID or either of its names, for example
+    /// with [`Asn1Object::from_str`].
+    ///
+    /// This corresponds to [`OBJ_create`].
+    ///
+    /// [`Asn1Object::from_str`]: ../asn1/struct.Asn1Object.html#method.from_str
+    /// [`OBJ_create`]: https://www.openssl.org/docs/man1.1.0/crypto/OBJ_create.html
+    pub fn create(oid: &str, short_name: &str, long_name: &str) -> Result<Nid, ErrorStack> {
+        let oid = CString::new(oid).unwrap();
+        let short_name = CString::new(short_name).unwrap();
+        let long_name = CString::new(long_name).unwrap();
+        unsafe {
+            ffi::init();
+            ErrorStack::clear();
+            let nid = ffi::OBJ_create(oid.as_ptr(), short_name.as_ptr(), long_name.as_ptr());
+            if nid == ffi::NID_undef {
+                Err(ErrorStack::get())
+            } else {
+                Ok(Nid(nid))
+            }
+        }
+    }
+
     pub const UNDEF: Nid = Nid(ffi::NID_undef);
     pub const ITU_T: Nid = Nid(ffi::NID_itu_t);
     pub const ISO: Nid = Nid(ffi::NID_iso);
@@ -1094,6 +1120,15 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_create() {
+        let nid = Nid::create("1.3.6.1.4.1.55555.1", "boringTest", "boring test object").unwrap();
+        assert_eq!(nid.short_name().unwrap(), "boringTest");
+        assert_eq!(nid.long_name().unwrap(), "boring test object");
+
+        assert!(Nid::create("not an oid", "boringBad", "boring bad object").is_err());
+    }
+
     #[test]
     fn test_short_name_conversion() {
         let common_name = Nid::COMMONNAME;
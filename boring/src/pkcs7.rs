@@ -0,0 +1,142 @@
+//! PKCS #7 signed data.
+//!
+//! BoringSSL only implements the subset of PKCS #7 needed to carry certificates and CRLs. A
+//! `SignedData` structure, including a detached SMIME or code-signing signature, can be parsed and
+//! its certificates extracted, but BoringSSL does not parse the `SignerInfo`s and cannot verify
+//! the signature itself.
+
+use crate::ffi;
+use foreign_types::{ForeignType, ForeignTypeRef};
+use libc::c_long;
+
+use crate::stack::StackRef;
+use crate::x509::X509;
+
+foreign_type_and_impl_send_sync! {
+    type CType = ffi::PKCS7;
+    fn drop = ffi::PKCS7_free;
+
+    /// A PKCS #7 structure.
+    pub struct Pkcs7;
+}
+
+impl Pkcs7 {
+    from_pem! {
+        /// Deserializes a PEM-encoded PKCS #7 structure.
+        ///
+        /// The input should have a header of `-----BEGIN PKCS7-----`.
+        ///
+        /// This corresponds to [`PEM_read_bio_PKCS7`].
+        ///
+        /// [`PEM_read_bio_PKCS7`]: https://commondatastorage.googleapis.com/chromium-boringssl-docs/pkcs7.h.html#PEM_read_bio_PKCS7
+        from_pem,
+        Pkcs7,
+        ffi::PEM_read_bio_PKCS7
+    }
+
+    from_der! {
+        /// Deserializes a DER-encoded PKCS #7 structure.
+        ///
+        /// This corresponds to [`d2i_PKCS7`].
+        ///
+        /// [`d2i_PKCS7`]: https://commondatastorage.googleapis.com/chromium-boringssl-docs/pkcs7.h.html#d2i_PKCS7
+        from_der,
+        Pkcs7,
+        ffi::d2i_PKCS7,
+        c_long
+    }
+}
+
+impl Pkcs7Ref {
+    to_pem! {
+        /// Serializes the structure into a PEM-encoded PKCS #7 structure.
+        ///
+        /// The output will have a header of `-----BEGIN PKCS7-----`.
+        ///
+        /// This corresponds to [`PEM_write_bio_PKCS7`].
+        ///
+        /// [`PEM_write_bio_PKCS7`]: https://commondatastorage.googleapis.com/chromium-boringssl-docs/pkcs7.h.html#PEM_write_bio_PKCS7
+        to_pem,
+        ffi::PEM_write_bio_PKCS7
+    }
+
+    to_der! {
+        /// Serializes the structure into a DER-encoded PKCS #7 structure.
+        ///
+        /// This corresponds to [`i2d_PKCS7`].
+        ///
+        /// [`i2d_PKCS7`]: https://commondatastorage.googleapis.com/chromium-boringssl-docs/pkcs7.h.html#i2d_PKCS7
+        to_der,
+        ffi::i2d_PKCS7
+    }
+
+    /// Returns whether this is a `SignedData` structure.
+    ///
+    /// This corresponds to [`PKCS7_type_is_signed`].
+    ///
+    /// [`PKCS7_type_is_signed`]: https://commondatastorage.googleapis.com/chromium-boringssl-docs/pkcs7.h.html#PKCS7_type_is_signed
+    pub fn is_signed(&self) -> bool {
+        unsafe { ffi::PKCS7_type_is_signed(self.as_ptr()) != 0 }
+    }
+
+    /// Returns the certificates carried by a `SignedData` structure.
+    ///
+    /// These normally include the signer's certificate and any intermediates needed to verify
+    /// it, in no particular order. Returns `None` if this is not a `SignedData` structure or it
+    /// carries no certificates.
+    pub fn certificates(&self) -> Option<&StackRef<X509>> {
+        unsafe {
+            if !self.is_signed() {
+                return None;
+            }
+
+            let signed = (*self.as_ptr()).d.sign;
+            if signed.is_null() || (*signed).cert.is_null() {
+                return None;
+            }
+
+            Some(StackRef::from_ptr((*signed).cert))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nid::Nid;
+
+    #[test]
+    fn parse_detached_signature() {
+        let der = include_bytes!("../test/pkcs7_detached.der");
+        let pkcs7 = Pkcs7::from_der(der).unwrap();
+        assert!(pkcs7.is_signed());
+
+        let certs = pkcs7.certificates().unwrap();
+        assert_eq!(certs.len(), 2);
+        let leaf = certs
+            .iter()
+            .find(|cert| {
+                cert.subject_name()
+                    .entries_by_nid(Nid::COMMONNAME)
+                    .next()
+                    .map_or(false, |cn| cn.data().as_slice() == b"foobar.com")
+            })
+            .unwrap();
+        let expected = X509::from_pem(include_bytes!("../test/cert.pem")).unwrap();
+        assert_eq!(leaf.to_der().unwrap(), expected.to_der().unwrap());
+    }
+
+    #[test]
+    fn pem_round_trip() {
+        let pem = include_bytes!("../test/pkcs7_detached.pem");
+        let pkcs7 = Pkcs7::from_pem(pem).unwrap();
+        let der = include_bytes!("../test/pkcs7_detached.der");
+        assert_eq!(
+            pkcs7.to_der().unwrap(),
+            Pkcs7::from_der(der).unwrap().to_der().unwrap()
+        );
+
+        let pkcs7 = Pkcs7::from_pem(&pkcs7.to_pem().unwrap()).unwrap();
+        assert_eq!(pkcs7.certificates().unwrap().len(), 2);
+    }
+}
@@ -43,16 +43,18 @@
 use crate::ffi;
 use foreign_types::{ForeignType, ForeignTypeRef};
 use libc::{c_int, c_long};
+use std::convert::TryFrom;
 use std::ffi::CString;
 use std::fmt;
 use std::mem;
 use std::ptr;
 
-use crate::bio::MemBioSlice;
+use crate::bio::{MemBio, MemBioSlice};
 use crate::dh::Dh;
 use crate::dsa::Dsa;
-use crate::ec::EcKey;
+use crate::ec::{EcGroup, EcKey};
 use crate::error::ErrorStack;
+use crate::nid::Nid;
 use crate::rsa::Rsa;
 use crate::util::{invoke_passwd_cb, CallbackState};
 use crate::{cvt, cvt_p};
@@ -246,6 +248,24 @@ where
     {
         unsafe { ffi::EVP_PKEY_cmp(self.as_ptr(), other.as_ptr()) == 1 }
     }
+
+    /// Converts the public components of the key to human readable text.
+    ///
+    /// This corresponds to [`EVP_PKEY_print_public`].
+    ///
+    /// [`EVP_PKEY_print_public`]: https://www.openssl.org/docs/manmaster/man3/EVP_PKEY_print_public.html
+    pub fn public_key_to_text(&self) -> Result<Vec<u8>, ErrorStack> {
+        let bio = MemBio::new()?;
+        unsafe {
+            cvt(ffi::EVP_PKEY_print_public(
+                bio.as_ptr(),
+                self.as_ptr(),
+                0,
+                ptr::null_mut(),
+            ))?;
+        }
+        Ok(bio.get_buf().to_owned())
+    }
 }
 
 impl<T> PKeyRef<T>
@@ -300,6 +320,17 @@ impl<T> fmt::Debug for PKey<T> {
     }
 }
 
+impl<'a> TryFrom<&'a [u8]> for PKey<Public> {
+    type Error = ErrorStack;
+
+    /// Decodes a DER-encoded SubjectPublicKeyInfo structure, like [`PKey::public_key_from_der`].
+    ///
+    /// [`PKey::public_key_from_der`]: struct.PKey.html#method.public_key_from_der
+    fn try_from(der: &'a [u8]) -> Result<PKey<Public>, ErrorStack> {
+        PKey::public_key_from_der(der)
+    }
+}
+
 impl<T> Clone for PKey<T> {
     fn clone(&self) -> PKey<T> {
         PKeyRef::to_owned(self)
@@ -314,6 +345,7 @@ impl<T> PKey<T> {
     /// [`EVP_PKEY_assign_RSA`]: https://www.openssl.org/docs/man1.1.0/crypto/EVP_PKEY_assign_RSA.html
     pub fn from_rsa(rsa: Rsa<T>) -> Result<PKey<T>, ErrorStack> {
         unsafe {
+            ffi::init();
             let evp = cvt_p(ffi::EVP_PKEY_new())?;
             let pkey = PKey::from_ptr(evp);
             cvt(ffi::EVP_PKEY_assign(
@@ -333,6 +365,7 @@ impl<T> PKey<T> {
     /// [`EVP_PKEY_assign_EC_KEY`]: https://www.openssl.org/docs/man1.1.0/crypto/EVP_PKEY_assign_EC_KEY.html
     pub fn from_ec_key(ec_key: EcKey<T>) -> Result<PKey<T>, ErrorStack> {
         unsafe {
+            ffi::init();
             let evp = cvt_p(ffi::EVP_PKEY_new())?;
             let pkey = PKey::from_ptr(evp);
             cvt(ffi::EVP_PKEY_assign(
@@ -347,6 +380,27 @@ impl<T> PKey<T> {
 }
 
 impl PKey<Private> {
+    /// Generates a new RSA key with the specified size.
+    ///
+    /// The public exponent will be 65537. See [`Rsa::generate`] for details.
+    ///
+    /// [`Rsa::generate`]: ../rsa/struct.Rsa.html#method.generate
+    pub fn generate_rsa(bits: u32) -> Result<PKey<Private>, ErrorStack> {
+        let rsa = Rsa::generate(bits)?;
+        PKey::from_rsa(rsa)
+    }
+
+    /// Generates a new elliptic curve key on the named curve.
+    ///
+    /// See [`EcKey::generate`] for details.
+    ///
+    /// [`EcKey::generate`]: ../ec/struct.EcKey.html#method.generate
+    pub fn generate_ec(curve: Nid) -> Result<PKey<Private>, ErrorStack> {
+        let group = EcGroup::from_curve_name(curve)?;
+        let ec_key = EcKey::generate(&group)?;
+        PKey::from_ec_key(ec_key)
+    }
+
     private_key_from_pem! {
         /// Deserializes a private key from a PEM-encoded key type specific format.
         ///
@@ -365,6 +419,9 @@ impl PKey<Private> {
         /// Deserializes a private key from a PEM-encoded encrypted key type specific format.
         ///
         /// The callback should fill the password into the provided buffer and return its length.
+        /// It is only invoked if the key is encrypted, so it can prompt the user interactively.
+        /// An error returned by the callback aborts decoding and is included in the returned
+        /// `ErrorStack`.
         ///
         /// This corresponds to [`PEM_read_bio_PrivateKey`].
         ///
@@ -490,9 +547,11 @@ use crate::ffi::EVP_PKEY_up_ref;
 
 #[cfg(test)]
 mod tests {
-    use crate::ec::EcKey;
+    use crate::ec::{EcGroup, EcKey};
+    use crate::hash::MessageDigest;
     use crate::nid::Nid;
     use crate::rsa::Rsa;
+    use crate::sign::{Signer, Verifier};
     use crate::symm::Cipher;
 
     use super::*;
@@ -508,6 +567,40 @@ mod tests {
         assert!(PKey::private_key_from_pem_passphrase(&pem, b"fizzbuzz").is_err());
     }
 
+    #[test]
+    fn test_aes_256_cbc_pem() {
+        let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).unwrap();
+        let pkey = PKey::from_ec_key(EcKey::generate(&group).unwrap()).unwrap();
+        let pem = pkey
+            .private_key_to_pem_pkcs8_passphrase(Cipher::aes_256_cbc(), b"hunter42")
+            .unwrap();
+        assert!(String::from_utf8_lossy(&pem).contains("ENCRYPTED PRIVATE KEY"));
+
+        let decoded = PKey::private_key_from_pem_passphrase(&pem, b"hunter42").unwrap();
+        assert!(pkey.public_eq(&decoded));
+        assert!(PKey::private_key_from_pem_passphrase(&pem, b"hunter43").is_err());
+
+        let mut password_queried = false;
+        let decoded = PKey::private_key_from_pem_callback(&pem, |password| {
+            password_queried = true;
+            password[..8].copy_from_slice(b"hunter42");
+            Ok(8)
+        })
+        .unwrap();
+        assert!(password_queried);
+        assert!(pkey.public_eq(&decoded));
+
+        assert!(PKey::private_key_from_pem_callback(&pem, |password| {
+            password[..8].copy_from_slice(b"hunter43");
+            Ok(8)
+        })
+        .is_err());
+        assert!(PKey::private_key_from_pem_callback(&pem, |_| Err(ErrorStack::get())).is_err());
+
+        let key = include_bytes!("../test/key.pem");
+        PKey::private_key_from_pem_callback(key, |_| panic!("the key is not encrypted")).unwrap();
+    }
+
     #[test]
     fn test_unencrypted_pkcs8() {
         let key = include_bytes!("../test/pkcs8-nocrypt.der");
@@ -551,6 +644,14 @@ mod tests {
         PKey::public_key_from_der(key).unwrap();
     }
 
+    #[test]
+    fn test_public_key_try_from() {
+        let key = include_bytes!("../test/key.der.pub");
+        let pkey = PKey::try_from(&key[..]).unwrap();
+        assert_eq!(pkey.public_key_to_der().unwrap(), &key[..]);
+        assert!(PKey::<Public>::try_from(&b"garbage"[..]).is_err());
+    }
+
     #[test]
     fn test_private_key_from_der() {
         let key = include_bytes!("../test/key.der");
@@ -588,4 +689,33 @@ mod tests {
         assert_eq!(pkey.id(), Id::EC);
         assert!(pkey.rsa().is_err());
     }
+
+    #[test]
+    fn test_public_key_to_text() {
+        let key = include_bytes!("../test/rsa.pem.pub");
+        let pkey = PKey::public_key_from_pem(key).unwrap();
+        let text = String::from_utf8(pkey.public_key_to_text().unwrap()).unwrap();
+        assert!(text.contains("Public-Key: (2048 bit)"));
+    }
+
+    #[test]
+    fn test_generate_rsa() {
+        let pkey = PKey::generate_rsa(2048).unwrap();
+        assert_eq!(pkey.id(), Id::RSA);
+        assert_eq!(pkey.bits(), 2048);
+    }
+
+    #[test]
+    fn test_generate_ec() {
+        let pkey = PKey::generate_ec(Nid::X9_62_PRIME256V1).unwrap();
+        assert_eq!(pkey.id(), Id::EC);
+
+        let mut signer = Signer::new(MessageDigest::sha256(), &pkey).unwrap();
+        signer.update(b"hello world").unwrap();
+        let signature = signer.sign_to_vec().unwrap();
+
+        let mut verifier = Verifier::new(MessageDigest::sha256(), &pkey).unwrap();
+        verifier.update(b"hello world").unwrap();
+        assert!(verifier.verify(&signature).unwrap());
+    }
 }
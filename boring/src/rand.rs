@@ -40,13 +40,122 @@ pub fn rand_bytes(buf: &mut [u8]) -> Result<(), ErrorStack> {
     }
 }
 
+/// Returns a cryptographically strong pseudo-random integer uniformly distributed in `[0, max)`.
+///
+/// Values are drawn from [`rand_bytes`] and rejected if they would introduce modulo bias. Use
+/// [`BigNumRef::rand_range`] for larger ranges.
+///
+/// # Panics
+///
+/// Panics if `max` is zero.
+///
+/// # Examples
+///
+/// ```
+/// use boring::rand::rand_range;
+///
+/// let jitter = rand_range(100).unwrap();
+/// assert!(jitter < 100);
+/// ```
+///
+/// [`BigNumRef::rand_range`]: ../bn/struct.BigNumRef.html#method.rand_range
+pub fn rand_range(max: u64) -> Result<u64, ErrorStack> {
+    assert!(max != 0, "max must be nonzero");
+
+    // 2^64 mod max: the number of values at the top of the u64 range which would make the lowest
+    // residues more likely than the rest.
+    let rem = (u64::max_value() % max + 1) % max;
+
+    loop {
+        let mut buf = [0; 8];
+        rand_bytes(&mut buf)?;
+        let v = u64::from_ne_bytes(buf);
+        if v <= u64::max_value() - rem {
+            return Ok(v % max);
+        }
+    }
+}
+
+/// Returns whether the random number generator has been sufficiently seeded.
+///
+/// BoringSSL seeds its generator from the operating system on first use, so this returns `true`
+/// in practice. It is provided for deployments which are required to assert the health of the
+/// generator on startup.
+///
+/// This corresponds to [`RAND_status`].
+///
+/// [`RAND_status`]: https://www.openssl.org/docs/man1.1.1/man3/RAND_status.html
+pub fn status() -> bool {
+    unsafe {
+        ffi::init();
+        ffi::RAND_status() == 1
+    }
+}
+
+/// Mixes `buf` into the random number generator's state, as an estimated `entropy` bytes of
+/// randomness.
+///
+/// This is provided for compatibility with code written against OpenSSL. BoringSSL ignores the
+/// data: its generator is seeded and periodically reseeded from the operating system and does not
+/// accept entropy from callers. Calling this is therefore harmless, but does not strengthen the
+/// generator.
+///
+/// # Panics
+///
+/// Panics if `buf.len()` does not fit in a `c_int`.
+///
+/// This corresponds to [`RAND_add`].
+///
+/// [`RAND_add`]: https://www.openssl.org/docs/man1.1.1/man3/RAND_add.html
+pub fn add(buf: &[u8], entropy: f64) {
+    unsafe {
+        ffi::init();
+        assert!(buf.len() <= c_int::max_value() as usize);
+        ffi::RAND_add(buf.as_ptr() as *const _, buf.len() as c_int, entropy);
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use super::rand_bytes;
+    use super::{add, rand_bytes, rand_range, status};
 
     #[test]
     fn test_rand_bytes() {
         let mut buf = [0; 32];
         rand_bytes(&mut buf).unwrap();
     }
+
+    #[test]
+    fn test_rand_range() {
+        let mut counts = [0; 10];
+        for _ in 0..10000 {
+            let v = rand_range(10).unwrap();
+            assert!(v < 10);
+            counts[v as usize] += 1;
+        }
+        for &count in &counts {
+            assert!(count > 800 && count < 1200, "{:?}", counts);
+        }
+
+        assert_eq!(rand_range(1).unwrap(), 0);
+        assert!(rand_range(u64::max_value()).unwrap() < u64::max_value());
+    }
+
+    #[test]
+    fn test_add() {
+        add(b"some additional entropy", 8.0);
+        add(&[], 0.0);
+
+        let mut a = [0; 32];
+        let mut b = [0; 32];
+        rand_bytes(&mut a).unwrap();
+        rand_bytes(&mut b).unwrap();
+        assert_ne!(a, b);
+    }
+
+    #[test]
+    fn test_status() {
+        crate::init();
+        assert!(status());
+    }
 }
@@ -31,7 +31,7 @@ use std::mem;
 use std::ptr;
 
 use crate::bn::{BigNum, BigNumRef};
-use crate::error::ErrorStack;
+use crate::error::{put_error, ErrorStack};
 use crate::pkey::{HasPrivate, HasPublic, Private, Public};
 use crate::{cvt, cvt_n, cvt_p};
 
@@ -458,6 +458,7 @@ impl Rsa<Public> {
     /// [`RSA_set0_key`]: https://www.openssl.org/docs/man1.1.0/crypto/RSA_set0_key.html
     pub fn from_public_components(n: BigNum, e: BigNum) -> Result<Rsa<Public>, ErrorStack> {
         unsafe {
+            ffi::init();
             let rsa = cvt_p(ffi::RSA_new())?;
             RSA_set0_key(rsa, n.as_ptr(), e.as_ptr(), ptr::null_mut());
             mem::forget((n, e));
@@ -532,6 +533,7 @@ impl RsaPrivateKeyBuilder {
     /// [`RSA_set0_key`]: https://www.openssl.org/docs/man1.1.0/crypto/RSA_set0_key.html
     pub fn new(n: BigNum, e: BigNum, d: BigNum) -> Result<RsaPrivateKeyBuilder, ErrorStack> {
         unsafe {
+            ffi::init();
             let rsa = cvt_p(ffi::RSA_new())?;
             RSA_set0_key(rsa, n.as_ptr(), e.as_ptr(), d.as_ptr());
             mem::forget((n, e, d));
@@ -628,11 +630,20 @@ impl Rsa<Private> {
     ///
     /// Unless you have specific needs and know what you're doing, use `Rsa::generate` instead.
     ///
+    /// The exponent must be odd and greater than 1.
+    ///
     /// This corresponds to [`RSA_generate_key_ex`].
     ///
     /// [`RSA_generate_key_ex`]: https://www.openssl.org/docs/man1.1.0/crypto/RSA_generate_key_ex.html
     pub fn generate_with_e(bits: u32, e: &BigNumRef) -> Result<Rsa<Private>, ErrorStack> {
         unsafe {
+            ffi::init();
+            if e.is_negative() || !e.is_bit_set(0) || e.num_bits() < 2 {
+                return Err(put_error(
+                    ffi::ERR_LIB_RSA as c_int,
+                    ffi::RSA_R_BAD_E_VALUE as c_int,
+                ));
+            }
             let rsa = Rsa::from_ptr(cvt_p(ffi::RSA_new())?);
             cvt(ffi::RSA_generate_key_ex(
                 rsa.0,
@@ -869,4 +880,19 @@ mod test {
         let e = BigNum::from_u32(0x10001).unwrap();
         Rsa::generate_with_e(2048, &e).unwrap();
     }
+
+    #[test]
+    fn generate_with_small_e() {
+        let e = BigNum::from_u32(3).unwrap();
+        let key = Rsa::generate_with_e(2048, &e).unwrap();
+        assert_eq!(key.e(), &*e);
+    }
+
+    #[test]
+    fn generate_with_invalid_e() {
+        for e in &[0, 1, 4, 0x10000] {
+            let e = BigNum::from_u32(*e).unwrap();
+            assert!(Rsa::generate_with_e(2048, &e).is_err());
+        }
+    }
 }
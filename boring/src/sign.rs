@@ -43,7 +43,7 @@ use std::ptr;
 
 use crate::error::ErrorStack;
 use crate::hash::MessageDigest;
-use crate::pkey::{HasPrivate, HasPublic, PKeyRef};
+use crate::pkey::{HasPrivate, HasPublic, Id, PKeyRef};
 use crate::rsa::Padding;
 use crate::{cvt, cvt_p};
 
@@ -72,6 +72,9 @@ impl RsaPssSaltlen {
 }
 
 /// A type which computes cryptographic signatures of data.
+///
+/// The RSA padding parameters are set on the `EVP_PKEY_CTX` owned by the digest context, so they
+/// apply however many times `update` is called and may be changed at any point before signing.
 pub struct Signer<'a> {
     md_ctx: *mut ffi::EVP_MD_CTX,
     pctx: *mut ffi::EVP_PKEY_CTX,
@@ -97,6 +100,19 @@ impl<'a> Signer<'a> {
     /// This cannot be used with Ed25519 or Ed448 keys. Please refer to
     /// `new_without_digest`.
     ///
+    /// The key must contain private components, so using a public key is rejected at compile
+    /// time:
+    ///
+    /// ```compile_fail
+    /// use boring::hash::MessageDigest;
+    /// use boring::pkey::PKey;
+    /// use boring::sign::Signer;
+    ///
+    /// let pem = include_bytes!("../test/rsa.pem.pub");
+    /// let key = PKey::public_key_from_pem(pem).unwrap();
+    /// let signer = Signer::new(MessageDigest::sha256(), &key);
+    /// ```
+    ///
     /// OpenSSL documentation at [`EVP_DigestSignInit`].
     ///
     /// [`EVP_DigestSignInit`]: https://www.openssl.org/docs/manmaster/man3/EVP_DigestSignInit.html
@@ -335,6 +351,44 @@ impl<'a> Signer<'a> {
         sig_buf.truncate(len);
         Ok(sig_buf)
     }
+
+    /// Signs a precomputed message digest, returning the signature.
+    ///
+    /// `digest` must be the output of the `MessageDigest` the `Signer` was created with; the
+    /// data fed through [`update`] is ignored. The signature can be checked with the regular
+    /// [`Verifier`] over the original message. RSA padding settings apply as for [`sign`].
+    /// This cannot be used with a `Signer` created by `new_without_digest`.
+    ///
+    /// [`update`]: #method.update
+    /// [`sign`]: #method.sign
+    /// [`Verifier`]: struct.Verifier.html
+    ///
+    /// OpenSSL documentation at [`EVP_PKEY_sign`].
+    ///
+    /// [`EVP_PKEY_sign`]: https://www.openssl.org/docs/man1.1.0/crypto/EVP_PKEY_sign.html
+    pub fn sign_raw(&self, digest: &[u8]) -> Result<Vec<u8>, ErrorStack> {
+        unsafe {
+            let mut len = 0;
+            cvt(ffi::EVP_PKEY_sign(
+                self.pctx,
+                ptr::null_mut(),
+                &mut len,
+                digest.as_ptr(),
+                digest.len(),
+            ))?;
+            let mut buf = vec![0; len];
+            cvt(ffi::EVP_PKEY_sign(
+                self.pctx,
+                buf.as_mut_ptr(),
+                &mut len,
+                digest.as_ptr(),
+                digest.len(),
+            ))?;
+            // The advertised length is not always equal to the real length for things like ECDSA
+            buf.truncate(len);
+            Ok(buf)
+        }
+    }
 }
 
 impl<'a> Write for Signer<'a> {
@@ -516,19 +570,21 @@ impl<'a> Verifier<'a> {
 
     /// Determines if the data fed into the `Verifier` matches the provided signature.
     ///
+    /// Returns `Ok(false)` only if the signature does not match the data. An `Err` is returned if
+    /// the signature could not be checked at all, such as an RSA signature which is not the size
+    /// of the modulus or an ECDSA signature which is not a DER-encoded `ECDSA-Sig-Value`.
+    ///
     /// OpenSSL documentation at [`EVP_DigestVerifyFinal`].
     ///
     /// [`EVP_DigestVerifyFinal`]: https://www.openssl.org/docs/manmaster/man3/EVP_DigestVerifyFinal.html
     pub fn verify(&self, signature: &[u8]) -> Result<bool, ErrorStack> {
+        self.check_encoding(signature)?;
         unsafe {
             let r =
                 EVP_DigestVerifyFinal(self.md_ctx, signature.as_ptr() as *mut _, signature.len());
             match r {
                 1 => Ok(true),
-                0 => {
-                    ErrorStack::get(); // discard error stack
-                    Ok(false)
-                }
+                0 => verify_failure(),
                 _ => Err(ErrorStack::get()),
             }
         }
@@ -536,10 +592,16 @@ impl<'a> Verifier<'a> {
 
     /// Determines if the data given in buf matches the provided signature.
     ///
+    /// As with [`verify`], `Ok(false)` strictly means the signature does not match, while a
+    /// signature which could not be checked at all results in an `Err`.
+    ///
+    /// [`verify`]: #method.verify
+    ///
     /// OpenSSL documentation at [`EVP_DigestVerify`].
     ///
     /// [`EVP_DigestVerify`]: https://www.openssl.org/docs/man1.1.1/man3/EVP_DigestVerify.html
     pub fn verify_oneshot(&mut self, signature: &[u8], buf: &[u8]) -> Result<bool, ErrorStack> {
+        self.check_encoding(signature)?;
         unsafe {
             let r = ffi::EVP_DigestVerify(
                 self.md_ctx,
@@ -550,14 +612,61 @@ impl<'a> Verifier<'a> {
             );
             match r {
                 1 => Ok(true),
-                0 => {
-                    ErrorStack::get();
-                    Ok(false)
-                }
+                0 => verify_failure(),
+                _ => Err(ErrorStack::get()),
+            }
+        }
+    }
+
+    /// Determines if a precomputed message digest matches the provided signature.
+    ///
+    /// `digest` must be the output of the `MessageDigest` the `Verifier` was created with; the
+    /// data fed through [`update`] is ignored. RSA padding settings apply as for [`verify`].
+    /// This cannot be used with a `Verifier` created by `new_without_digest`.
+    ///
+    /// As with [`verify`], `Ok(false)` strictly means the signature does not match.
+    ///
+    /// [`update`]: #method.update
+    /// [`verify`]: #method.verify
+    ///
+    /// OpenSSL documentation at [`EVP_PKEY_verify`].
+    ///
+    /// [`EVP_PKEY_verify`]: https://www.openssl.org/docs/man1.1.0/crypto/EVP_PKEY_verify.html
+    pub fn verify_raw(&self, signature: &[u8], digest: &[u8]) -> Result<bool, ErrorStack> {
+        self.check_encoding(signature)?;
+        unsafe {
+            let r = ffi::EVP_PKEY_verify(
+                self.pctx,
+                signature.as_ptr(),
+                signature.len(),
+                digest.as_ptr(),
+                digest.len(),
+            );
+            match r {
+                1 => Ok(true),
+                0 => verify_failure(),
                 _ => Err(ErrorStack::get()),
             }
         }
     }
+
+    /// Returns an error if `signature` is an ECDSA signature which cannot be decoded.
+    ///
+    /// BoringSSL reports such signatures with the same error as a mismatch, so they are decoded
+    /// up front, with the same strict DER parser `ECDSA_verify` uses.
+    fn check_encoding(&self, signature: &[u8]) -> Result<(), ErrorStack> {
+        unsafe {
+            let pkey = ffi::EVP_PKEY_CTX_get0_pkey(self.pctx);
+            if Id::from_raw(ffi::EVP_PKEY_id(pkey)) == Id::EC {
+                let sig = cvt_p(ffi::ECDSA_SIG_from_bytes(
+                    signature.as_ptr(),
+                    signature.len(),
+                ))?;
+                ffi::ECDSA_SIG_free(sig);
+            }
+            Ok(())
+        }
+    }
 }
 
 impl<'a> Write for Verifier<'a> {
@@ -571,6 +680,22 @@ impl<'a> Write for Verifier<'a> {
     }
 }
 
+/// Interprets a failed verification, distinguishing a signature mismatch from a signature which
+/// could not be checked.
+fn verify_failure() -> Result<bool, ErrorStack> {
+    let errors = ErrorStack::get();
+    let malformed = errors.errors().iter().any(|e| {
+        ffi::ERR_GET_LIB(e.code()) == ffi::ERR_LIB_RSA as c_int
+            && ffi::ERR_GET_REASON(e.code()) == ffi::RSA_R_DATA_LEN_NOT_EQUAL_TO_MOD_LEN as c_int
+    });
+
+    if malformed {
+        Err(errors)
+    } else {
+        Ok(false)
+    }
+}
+
 use crate::ffi::EVP_DigestVerifyFinal;
 
 #[cfg(test)]
@@ -579,7 +704,7 @@ mod test {
     use hex::{self, FromHex};
 
     use crate::ec::{EcGroup, EcKey};
-    use crate::hash::MessageDigest;
+    use crate::hash::{hash, MessageDigest};
     use crate::nid::Nid;
     use crate::pkey::PKey;
     use crate::rsa::{Padding, Rsa};
@@ -637,6 +762,63 @@ mod test {
         assert!(!verifier.verify(&Vec::from_hex(SIGNATURE).unwrap()).unwrap());
     }
 
+    #[test]
+    fn rsa_verify_oneshot() {
+        let key = include_bytes!("../test/rsa.pem");
+        let private_key = Rsa::private_key_from_pem(key).unwrap();
+        let pkey = PKey::from_rsa(private_key).unwrap();
+        let input = Vec::from_hex(INPUT).unwrap();
+        let signature = Vec::from_hex(SIGNATURE).unwrap();
+
+        let mut verifier = Verifier::new(MessageDigest::sha256(), &pkey).unwrap();
+        assert!(verifier.verify_oneshot(&signature, &input).unwrap());
+
+        let mut tampered = signature.clone();
+        *tampered.last_mut().unwrap() ^= 1;
+        let mut verifier = Verifier::new(MessageDigest::sha256(), &pkey).unwrap();
+        assert!(!verifier.verify_oneshot(&tampered, &input).unwrap());
+
+        let mut verifier = Verifier::new(MessageDigest::sha256(), &pkey).unwrap();
+        assert!(verifier
+            .verify_oneshot(&signature[..signature.len() - 1], &input)
+            .is_err());
+    }
+
+    #[test]
+    fn ec_verify_malformed() {
+        let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).unwrap();
+        let key = EcKey::generate(&group).unwrap();
+        let key = PKey::from_ec_key(key).unwrap();
+
+        let mut signer = Signer::new(MessageDigest::sha256(), &key).unwrap();
+        signer.update(b"hello world").unwrap();
+        let signature = signer.sign_to_vec().unwrap();
+
+        let verify = |signature: &[u8]| {
+            let mut verifier = Verifier::new(MessageDigest::sha256(), &key).unwrap();
+            verifier.update(b"hello world").unwrap();
+            verifier.verify(signature)
+        };
+
+        assert!(verify(&signature).unwrap());
+
+        // A well-formed signature over other data is a mismatch.
+        let mut signer = Signer::new(MessageDigest::sha256(), &key).unwrap();
+        signer.update(b"goodbye world").unwrap();
+        assert!(!verify(&signer.sign_to_vec().unwrap()).unwrap());
+
+        // Truncated DER and trailing data cannot be checked at all.
+        assert!(verify(&signature[..signature.len() - 1]).is_err());
+        let mut trailing = signature.clone();
+        trailing.push(0);
+        assert!(verify(&trailing).is_err());
+
+        let digest = hash(MessageDigest::sha256(), b"hello world").unwrap();
+        let verifier = Verifier::new(MessageDigest::sha256(), &key).unwrap();
+        assert!(verifier.verify_raw(&signature, &digest).unwrap());
+        assert!(verifier.verify_raw(&[0x30, 0x00], &digest).is_err());
+    }
+
     #[test]
     fn ec() {
         let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).unwrap();
@@ -677,4 +859,91 @@ mod test {
         verifier.update(&Vec::from_hex(INPUT).unwrap()).unwrap();
         assert!(verifier.verify(&signature).unwrap());
     }
+
+    #[test]
+    fn rsa_pss_streaming() {
+        let key = include_bytes!("../test/rsa.pem");
+        let private_key = Rsa::private_key_from_pem(key).unwrap();
+        let pkey = PKey::from_rsa(private_key).unwrap();
+        let data = Vec::from_hex(INPUT).unwrap();
+
+        let mut signer = Signer::new(MessageDigest::sha256(), &pkey).unwrap();
+        signer.set_rsa_padding(Padding::PKCS1_PSS).unwrap();
+        signer
+            .set_rsa_pss_saltlen(RsaPssSaltlen::custom(20))
+            .unwrap();
+        signer.set_rsa_mgf1_md(MessageDigest::sha1()).unwrap();
+        for chunk in data.chunks(7) {
+            signer.update(chunk).unwrap();
+        }
+        assert_eq!(signer.rsa_padding().unwrap(), Padding::PKCS1_PSS);
+        let signature = signer.sign_to_vec().unwrap();
+
+        // PSS signatures are randomized, unlike PKCS#1 v1.5 ones.
+        assert_ne!(signature, signer.sign_to_vec().unwrap());
+
+        let mut verifier = Verifier::new(MessageDigest::sha256(), &pkey).unwrap();
+        verifier.set_rsa_padding(Padding::PKCS1_PSS).unwrap();
+        verifier
+            .set_rsa_pss_saltlen(RsaPssSaltlen::custom(20))
+            .unwrap();
+        verifier.set_rsa_mgf1_md(MessageDigest::sha1()).unwrap();
+        verifier.update(&data).unwrap();
+        assert!(verifier.verify(&signature).unwrap());
+
+        let mut verifier = Verifier::new(MessageDigest::sha256(), &pkey).unwrap();
+        verifier.set_rsa_padding(Padding::PKCS1_PSS).unwrap();
+        verifier.set_rsa_mgf1_md(MessageDigest::sha256()).unwrap();
+        verifier.update(&data).unwrap();
+        assert!(!verifier.verify(&signature).unwrap());
+    }
+
+    #[test]
+    fn rsa_pss_verify_raw() {
+        let key = include_bytes!("../test/rsa.pem");
+        let private_key = Rsa::private_key_from_pem(key).unwrap();
+        let pkey = PKey::from_rsa(private_key).unwrap();
+        let data = b"hello world";
+
+        let mut signer = Signer::new(MessageDigest::sha256(), &pkey).unwrap();
+        signer.set_rsa_padding(Padding::PKCS1_PSS).unwrap();
+        signer
+            .set_rsa_pss_saltlen(RsaPssSaltlen::DIGEST_LENGTH)
+            .unwrap();
+        signer.update(data).unwrap();
+        let signature = signer.sign_to_vec().unwrap();
+
+        let digest = hash(MessageDigest::sha256(), data).unwrap();
+
+        let mut verifier = Verifier::new(MessageDigest::sha256(), &pkey).unwrap();
+        verifier.set_rsa_padding(Padding::PKCS1_PSS).unwrap();
+        verifier
+            .set_rsa_pss_saltlen(RsaPssSaltlen::DIGEST_LENGTH)
+            .unwrap();
+        assert!(verifier.verify_raw(&signature, &digest).unwrap());
+
+        let mut tampered = digest.to_vec();
+        tampered[0] ^= 1;
+        assert!(!verifier.verify_raw(&signature, &tampered).unwrap());
+    }
+
+    #[test]
+    fn sign_raw() {
+        let data = b"hello world";
+        let digest = hash(MessageDigest::sha256(), data).unwrap();
+
+        let key = include_bytes!("../test/rsa.pem");
+        let rsa = PKey::from_rsa(Rsa::private_key_from_pem(key).unwrap()).unwrap();
+        let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).unwrap();
+        let ec = PKey::from_ec_key(EcKey::generate(&group).unwrap()).unwrap();
+
+        for pkey in &[rsa, ec] {
+            let signer = Signer::new(MessageDigest::sha256(), pkey).unwrap();
+            let signature = signer.sign_raw(&digest).unwrap();
+
+            let mut verifier = Verifier::new(MessageDigest::sha256(), pkey).unwrap();
+            verifier.update(data).unwrap();
+            assert!(verifier.verify(&signature).unwrap());
+        }
+    }
 }
@@ -18,6 +18,8 @@ pub struct StreamState<S> {
     pub error: Option<io::Error>,
     pub panic: Option<Box<dyn Any + Send>>,
     pub dtls_mtu_size: c_long,
+    pub flush_on_write: bool,
+    pub record_remaining: usize,
 }
 
 /// Safe wrapper for BIO_METHOD
@@ -40,6 +42,8 @@ pub fn new<S: Read + Write>(stream: S) -> Result<(*mut BIO, BioMethod), ErrorSta
         error: None,
         panic: None,
         dtls_mtu_size: 0,
+        flush_on_write: false,
+        record_remaining: 0,
     });
 
     unsafe {
@@ -93,6 +97,10 @@ pub unsafe fn set_dtls_mtu_size<S>(bio: *mut BIO, mtu_size: usize) {
     state::<S>(bio).dtls_mtu_size = mtu_size as c_long;
 }
 
+pub unsafe fn set_flush_on_write<S>(bio: *mut BIO, flush_on_write: bool) {
+    state::<S>(bio).flush_on_write = flush_on_write;
+}
+
 unsafe fn state<'a, S: 'a>(bio: *mut BIO) -> &'a mut StreamState<S> {
     let data = BIO_get_data(bio) as *mut StreamState<S>;
 
@@ -107,7 +115,32 @@ unsafe extern "C" fn bwrite<S: Write>(bio: *mut BIO, buf: *const c_char, len: c_
     let state = state::<S>(bio);
     let buf = slice::from_raw_parts(buf as *const _, len as usize);
 
-    match catch_unwind(AssertUnwindSafe(|| state.stream.write(buf))) {
+    let result = catch_unwind(AssertUnwindSafe(|| -> io::Result<usize> {
+        if !state.flush_on_write {
+            return state.stream.write(buf);
+        }
+
+        // Write at most one record at a time, so that BoringSSL hands over the rest of the
+        // flight in further writes, and flush once the record is complete.
+        let record_len = if state.record_remaining > 0 {
+            state.record_remaining
+        } else {
+            tls_record_len(buf)
+        };
+        let len = state.stream.write(&buf[..record_len.min(buf.len())])?;
+        state.record_remaining = record_len.saturating_sub(len);
+        if state.record_remaining == 0 {
+            // The bytes have already been accepted by the stream, so a flush that would block is
+            // not reported; BoringSSL flushes again once the flight is complete.
+            match state.stream.flush() {
+                Err(ref err) if retriable_error(err) => {}
+                result => result?,
+            }
+        }
+        Ok(len)
+    }));
+
+    match result {
         Ok(Ok(len)) => len as c_int,
         Ok(Err(err)) => {
             if retriable_error(&err) {
@@ -123,6 +156,15 @@ unsafe extern "C" fn bwrite<S: Write>(bio: *mut BIO, buf: *const c_char, len: c_
     }
 }
 
+/// Returns the length of the TLS record at the start of `buf`, or the length of `buf` if it doesn't
+/// start with a TLS record header, as is the case for DTLS.
+fn tls_record_len(buf: &[u8]) -> usize {
+    if buf.len() < 5 || buf[1] != 3 {
+        return buf.len();
+    }
+    5 + ((buf[3] as usize) << 8 | buf[4] as usize)
+}
+
 unsafe extern "C" fn bread<S: Read>(bio: *mut BIO, buf: *mut c_char, len: c_int) -> c_int {
     BIO_clear_retry_flags(bio);
 
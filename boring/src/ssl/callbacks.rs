@@ -4,20 +4,22 @@ use foreign_types::ForeignTypeRef;
 use libc::c_char;
 use libc::{c_int, c_uchar, c_uint, c_void};
 use std::ffi::CStr;
+use std::io;
 use std::mem;
 use std::ptr;
 use std::slice;
 use std::str;
 use std::sync::Arc;
+use std::time::Instant;
 
 use crate::error::ErrorStack;
 use crate::ssl::AlpnError;
-use crate::ssl::{ClientHello, SelectCertError};
+use crate::ssl::{CertCallbackError, ClientHello, SelectCertError};
 use crate::ssl::{
     SniError, Ssl, SslAlert, SslContext, SslContextRef, SslRef, SslSession, SslSessionRef,
-    SESSION_CTX_INDEX,
+    SslSignatureAlgorithm, SESSION_CTX_INDEX,
 };
-use crate::x509::{X509StoreContext, X509StoreContextRef};
+use crate::x509::{X509StoreContext, X509StoreContextRef, X509VerifyResult};
 
 pub extern "C" fn raw_verify<F>(preverify_ok: c_int, x509_ctx: *mut ffi::X509_STORE_CTX) -> c_int
 where
@@ -41,6 +43,56 @@ where
     }
 }
 
+pub extern "C" fn raw_cert_verify<F>(x509_ctx: *mut ffi::X509_STORE_CTX, _arg: *mut c_void) -> c_int
+where
+    F: Fn(&mut X509StoreContextRef) -> bool + 'static + Sync + Send,
+{
+    unsafe {
+        let ctx = X509StoreContextRef::from_ptr_mut(x509_ctx);
+        let ssl_idx = X509StoreContext::ssl_idx().expect("BUG: store context ssl index missing");
+        let verify_idx = SslContext::cached_ex_index::<F>();
+
+        // raw pointer shenanigans to break the borrow of ctx
+        // the callback can't mess with its own ex_data slot so this is safe
+        let verify = ctx
+            .ex_data(ssl_idx)
+            .expect("BUG: store context missing ssl")
+            .ssl_context()
+            .ex_data(verify_idx)
+            .expect("BUG: cert verify callback missing") as *const F;
+
+        if (*verify)(ctx) {
+            1
+        } else {
+            // BoringSSL reports the store context's error as the verify result.
+            ctx.set_error(X509VerifyResult::APPLICATION_VERIFICATION);
+            0
+        }
+    }
+}
+
+pub unsafe extern "C" fn raw_custom_verify<F>(
+    ssl: *mut ffi::SSL,
+    out_alert: *mut u8,
+) -> ffi::ssl_verify_result_t
+where
+    F: Fn(&mut SslRef) -> Result<(), SslAlert> + 'static + Sync + Send,
+{
+    let ssl = SslRef::from_ptr_mut(ssl);
+    let callback = ssl
+        .ssl_context()
+        .ex_data(SslContext::cached_ex_index::<F>())
+        .expect("BUG: custom verify callback missing") as *const F;
+
+    match (*callback)(ssl) {
+        Ok(()) => ffi::ssl_verify_result_t::ssl_verify_ok,
+        Err(alert) => {
+            *out_alert = alert.0 as u8;
+            ffi::ssl_verify_result_t::ssl_verify_invalid
+        }
+    }
+}
+
 #[cfg(not(osslconf = "OPENSSL_NO_PSK"))]
 pub extern "C" fn raw_client_psk<F>(
     ssl: *mut ffi::SSL,
@@ -210,6 +262,27 @@ where
     }
 }
 
+pub unsafe extern "C" fn raw_cert<F>(ssl: *mut ffi::SSL, _arg: *mut c_void) -> c_int
+where
+    F: Fn(&mut SslRef) -> Result<(), CertCallbackError> + 'static + Sync + Send,
+{
+    let ssl = SslRef::from_ptr_mut(ssl);
+    let callback = ssl
+        .ssl_context()
+        .ex_data(SslContext::cached_ex_index::<F>())
+        .expect("BUG: cert callback missing") as *const F;
+
+    match (*callback)(ssl) {
+        Ok(()) => 1,
+        Err(CertCallbackError(ret, errors)) => {
+            if let Some(errors) = errors {
+                errors.put();
+            }
+            ret
+        }
+    }
+}
+
 pub unsafe extern "C" fn raw_tlsext_status<F>(ssl: *mut ffi::SSL, _: *mut c_void) -> c_int
 where
     F: Fn(&mut SslRef) -> Result<bool, ErrorStack> + 'static + Sync + Send,
@@ -308,6 +381,73 @@ where
     }
 }
 
+#[cfg(feature = "log")]
+pub struct InfoLogTarget(pub &'static str);
+
+/// Marks a context on which `SslContextBuilder::enable_handshake_metrics` was called.
+pub struct HandshakeMetrics;
+
+/// Handshake timestamps recorded by `SslContextBuilder::enable_handshake_metrics`.
+pub struct HandshakeTimes {
+    pub start: Option<Instant>,
+    pub end: Option<Instant>,
+}
+
+/// The preferences set by `SslContextBuilder::set_signing_algorithm_prefs`, which BoringSSL
+/// offers no way to read back.
+pub struct SigningAlgorithmPrefs(pub Vec<SslSignatureAlgorithm>);
+
+/// The context's single info callback, dispatching to every feature built on top of it.
+pub extern "C" fn raw_info(ssl: *const ffi::SSL, type_: c_int, value: c_int) {
+    unsafe {
+        let ssl = SslRef::from_ptr_mut(ssl as *mut _);
+        let metrics = ssl
+            .ssl_context()
+            .ex_data(SslContext::cached_ex_index::<HandshakeMetrics>())
+            .is_some();
+        if metrics {
+            handshake_metrics(ssl, type_);
+        }
+        #[cfg(feature = "log")]
+        info_log(ssl, type_, value);
+        #[cfg(not(feature = "log"))]
+        let _ = value;
+    }
+}
+
+#[cfg(feature = "log")]
+unsafe fn info_log(ssl: &SslRef, type_: c_int, value: c_int) {
+    let target = match ssl
+        .ssl_context()
+        .ex_data(SslContext::cached_ex_index::<InfoLogTarget>())
+    {
+        Some(target) => target.0,
+        None => return,
+    };
+    let role = if ssl.is_server() { "server" } else { "client" };
+
+    if type_ & ffi::SSL_CB_ALERT as c_int != 0 {
+        let direction = if type_ & ffi::SSL_CB_READ as c_int != 0 {
+            "received"
+        } else {
+            "sent"
+        };
+        let level = CStr::from_ptr(ffi::SSL_alert_type_string_long(value)).to_string_lossy();
+        let desc = CStr::from_ptr(ffi::SSL_alert_desc_string_long(value)).to_string_lossy();
+        log::warn!(target: target, "{} {} {} alert: {}", role, direction, level, desc);
+    } else if type_ & ffi::SSL_CB_HANDSHAKE_START as c_int != 0 {
+        log::debug!(target: target, "{} handshake started", role);
+    } else if type_ & ffi::SSL_CB_HANDSHAKE_DONE as c_int != 0 {
+        log::debug!(
+            target: target,
+            "{} handshake done: {} {}",
+            role,
+            ssl.version_str(),
+            ssl.current_cipher().map_or("(none)", |c| c.name())
+        );
+    }
+}
+
 pub unsafe extern "C" fn raw_keylog<F>(ssl: *const ffi::SSL, line: *const c_char)
 where
     F: Fn(&SslRef, &str) + 'static + Sync + Send,
@@ -322,3 +462,139 @@ where
 
     callback(ssl, line);
 }
+
+unsafe fn handshake_metrics(ssl: &mut SslRef, type_: c_int) {
+    let now = Instant::now();
+    let index = Ssl::cached_ex_index::<HandshakeTimes>();
+
+    if type_ & ffi::SSL_CB_HANDSHAKE_START as c_int != 0 {
+        let times = HandshakeTimes {
+            start: Some(now),
+            end: None,
+        };
+        // Replace in place so that a renegotiation does not leak the previous entry.
+        match ssl.ex_data_mut(index) {
+            Some(existing) => *existing = times,
+            None => ssl.set_ex_data(index, times),
+        }
+    } else if type_ & ffi::SSL_CB_HANDSHAKE_DONE as c_int != 0 {
+        if let Some(times) = ssl.ex_data_mut(index) {
+            times.end = Some(now);
+        }
+    }
+}
+
+type CertCompressFn = dyn Fn(&mut SslRef, &[u8]) -> io::Result<Vec<u8>> + 'static + Sync + Send;
+type CertDecompressFn =
+    dyn Fn(&mut SslRef, &[u8], usize) -> io::Result<Vec<u8>> + 'static + Sync + Send;
+
+/// The certificate compression callbacks registered on a context, in registration order.
+///
+/// BoringSSL does not tell the callbacks which algorithm they are invoked for, so each
+/// registration is given its own pair of trampolines, which look up their entry by position.
+pub struct CertCompressionAlgs(pub Vec<(Box<CertCompressFn>, Box<CertDecompressFn>)>);
+
+pub trait CertCompressionSlot {
+    const INDEX: usize;
+}
+
+macro_rules! cert_compression_slots {
+    ($($slot:ident = $index:tt,)*) => {
+        $(
+            pub enum $slot {}
+
+            impl CertCompressionSlot for $slot {
+                const INDEX: usize = $index;
+            }
+        )*
+
+        /// Returns the trampolines dispatching to the `index`th registered algorithm, or `None`
+        /// if there are no trampolines left.
+        pub fn cert_compression_slot(
+            index: usize,
+        ) -> Option<(
+            ffi::ssl_cert_compression_func_t,
+            ffi::ssl_cert_decompression_func_t,
+        )> {
+            match index {
+                $($index => Some((
+                    Some(raw_cert_compress::<$slot>),
+                    Some(raw_cert_decompress::<$slot>),
+                )),)*
+                _ => None,
+            }
+        }
+    };
+}
+
+cert_compression_slots! {
+    CertCompressionSlot0 = 0,
+    CertCompressionSlot1 = 1,
+    CertCompressionSlot2 = 2,
+    CertCompressionSlot3 = 3,
+    CertCompressionSlot4 = 4,
+    CertCompressionSlot5 = 5,
+    CertCompressionSlot6 = 6,
+    CertCompressionSlot7 = 7,
+}
+
+unsafe fn cert_compression_alg<'a, S>(
+    ssl: &SslRef,
+) -> &'a (Box<CertCompressFn>, Box<CertDecompressFn>)
+where
+    S: CertCompressionSlot,
+{
+    let algs = ssl
+        .ssl_context()
+        .ex_data(SslContext::cached_ex_index::<CertCompressionAlgs>())
+        .expect("BUG: cert compression callbacks missing")
+        as *const CertCompressionAlgs;
+    &(*algs).0[S::INDEX]
+}
+
+pub unsafe extern "C" fn raw_cert_compress<S>(
+    ssl: *mut ffi::SSL,
+    out: *mut ffi::CBB,
+    input: *const u8,
+    input_len: usize,
+) -> c_int
+where
+    S: CertCompressionSlot,
+{
+    let ssl = SslRef::from_ptr_mut(ssl);
+    let compress = &cert_compression_alg::<S>(ssl).0;
+    let input = slice::from_raw_parts(input, input_len);
+
+    match compress(ssl, input) {
+        Ok(compressed) => ffi::CBB_add_bytes(out, compressed.as_ptr(), compressed.len()),
+        Err(_) => 0,
+    }
+}
+
+pub unsafe extern "C" fn raw_cert_decompress<S>(
+    ssl: *mut ffi::SSL,
+    out: *mut *mut ffi::CRYPTO_BUFFER,
+    uncompressed_len: usize,
+    input: *const u8,
+    input_len: usize,
+) -> c_int
+where
+    S: CertCompressionSlot,
+{
+    let ssl = SslRef::from_ptr_mut(ssl);
+    let decompress = &cert_compression_alg::<S>(ssl).1;
+    let input = slice::from_raw_parts(input, input_len);
+
+    match decompress(ssl, input, uncompressed_len) {
+        Ok(decompressed) if decompressed.len() == uncompressed_len => {
+            let buffer =
+                ffi::CRYPTO_BUFFER_new(decompressed.as_ptr(), decompressed.len(), ptr::null_mut());
+            if buffer.is_null() {
+                return 0;
+            }
+            *out = buffer;
+            1
+        }
+        _ => 0,
+    }
+}
@@ -1,4 +1,5 @@
 use std::io::{Read, Write};
+use std::net::IpAddr;
 use std::ops::{Deref, DerefMut};
 
 use crate::dh::Dh;
@@ -77,6 +78,20 @@ impl SslConnector {
     /// Initiates a client-side TLS session on a stream.
     ///
     /// The domain is used for SNI and hostname verification.
+    ///
+    /// Once the handshake has started, the stream is not lost on error: both
+    /// `HandshakeError::Failure` and `HandshakeError::WouldBlock` carry a
+    /// [`MidHandshakeSslStream`], from which the stream can be recovered with
+    /// [`MidHandshakeSslStream::into_source_stream`]. A `WouldBlock` handshake can instead be
+    /// resumed with [`MidHandshakeSslStream::handshake`] once the stream is ready.
+    ///
+    /// `HandshakeError::SetupFailure` is returned if the session could not be configured before
+    /// the handshake started, such as when the domain cannot be used for SNI, and the stream is
+    /// dropped in that case.
+    ///
+    /// [`MidHandshakeSslStream`]: struct.MidHandshakeSslStream.html
+    /// [`MidHandshakeSslStream::into_source_stream`]: struct.MidHandshakeSslStream.html#method.into_source_stream
+    /// [`MidHandshakeSslStream::handshake`]: struct.MidHandshakeSslStream.html#method.handshake
     pub fn connect<S>(&self, domain: &str, stream: S) -> Result<SslStream<S>, HandshakeError<S>>
     where
         S: Read + Write,
@@ -128,6 +143,34 @@ impl DerefMut for SslConnectorBuilder {
     }
 }
 
+/// The name of the server a client connects to.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ServerName {
+    /// A DNS name, sent using Server Name Indication (SNI) and verified against the DNS names in
+    /// the server's certificate.
+    DnsName(String),
+    /// An IP address, verified against the IP addresses in the server's certificate.
+    ///
+    /// As required by RFC 6066, no SNI is sent for IP addresses.
+    IpAddress(IpAddr),
+}
+
+impl<'a> From<&'a str> for ServerName {
+    /// Parses IP address literals as `IpAddress`, and anything else as `DnsName`.
+    fn from(name: &'a str) -> ServerName {
+        match name.parse() {
+            Ok(ip) => ServerName::IpAddress(ip),
+            Err(_) => ServerName::DnsName(name.to_string()),
+        }
+    }
+}
+
+impl From<IpAddr> for ServerName {
+    fn from(ip: IpAddr) -> ServerName {
+        ServerName::IpAddress(ip)
+    }
+}
+
 /// A type which allows for configuration of a client-side TLS session before connection.
 pub struct ConnectConfiguration {
     ssl: Ssl,
@@ -170,17 +213,45 @@ impl ConnectConfiguration {
 
     /// Initiates a client-side TLS session on a stream.
     ///
-    /// The domain is used for SNI and hostname verification if enabled.
-    pub fn connect<S>(mut self, domain: &str, stream: S) -> Result<SslStream<S>, HandshakeError<S>>
+    /// The domain is used for SNI and hostname verification if enabled. IP address literals are
+    /// handled as described in [`connect_server_name`].
+    ///
+    /// [`connect_server_name`]: #method.connect_server_name
+    pub fn connect<S>(self, domain: &str, stream: S) -> Result<SslStream<S>, HandshakeError<S>>
     where
         S: Read + Write,
     {
-        if self.sni {
-            self.ssl.set_hostname(domain)?;
-        }
+        self.connect_server_name(&ServerName::from(domain), stream)
+    }
 
-        if self.verify_hostname {
-            setup_verify_hostname(&mut self.ssl, domain)?;
+    /// Initiates a client-side TLS session on a stream to the named server.
+    ///
+    /// A DNS name is used for SNI and hostname verification if enabled. An IP address is never
+    /// sent using SNI, but is still verified against the certificate if hostname verification is
+    /// enabled.
+    pub fn connect_server_name<S>(
+        mut self,
+        name: &ServerName,
+        stream: S,
+    ) -> Result<SslStream<S>, HandshakeError<S>>
+    where
+        S: Read + Write,
+    {
+        match name {
+            ServerName::DnsName(domain) => {
+                if self.sni {
+                    self.ssl.set_hostname(domain)?;
+                }
+
+                if self.verify_hostname {
+                    setup_verify_hostname(&mut self.ssl, domain)?;
+                }
+            }
+            ServerName::IpAddress(ip) => {
+                if self.verify_hostname {
+                    self.ssl.param_mut().set_ip(*ip)?;
+                }
+            }
         }
 
         self.ssl.connect(stream)
@@ -33,6 +33,24 @@ impl ErrorCode {
     /// An error occurred in the SSL library.
     pub const SSL: ErrorCode = ErrorCode(ffi::SSL_ERROR_SSL);
 
+    /// The certificate selection callback asked for the handshake to be suspended.
+    ///
+    /// Retry the handshake once the callback is ready to make a decision.
+    pub const PENDING_CERTIFICATE: ErrorCode = ErrorCode(ffi::SSL_ERROR_PENDING_CERTIFICATE);
+
+    /// The certificate callback asked for the handshake to be suspended.
+    ///
+    /// Retry the handshake once the certificate lookup has completed.
+    pub const WANT_X509_LOOKUP: ErrorCode = ErrorCode(ffi::SSL_ERROR_WANT_X509_LOOKUP);
+
+    /// The server rejected the client's early data.
+    ///
+    /// The connection must be reset with [`SslRef::reset_early_data_reject`] and any early data
+    /// resent once the handshake completes.
+    ///
+    /// [`SslRef::reset_early_data_reject`]: struct.SslRef.html#method.reset_early_data_reject
+    pub const EARLY_DATA_REJECTED: ErrorCode = ErrorCode(ffi::SSL_ERROR_EARLY_DATA_REJECTED);
+
     pub fn from_raw(raw: c_int) -> ErrorCode {
         ErrorCode(raw)
     }
@@ -112,6 +130,13 @@ impl fmt::Display for Error {
                 Some(e) => write!(fmt, "{}", e),
                 None => fmt.write_str("unknown BoringSSL error"),
             },
+            ErrorCode::PENDING_CERTIFICATE => {
+                fmt.write_str("the handshake is waiting on certificate selection")
+            }
+            ErrorCode::WANT_X509_LOOKUP => {
+                fmt.write_str("the handshake is waiting on a certificate lookup")
+            }
+            ErrorCode::EARLY_DATA_REJECTED => fmt.write_str("the server rejected early data"),
             ErrorCode(code) => write!(fmt, "unknown error code {}", code),
         }
     }
@@ -62,7 +62,7 @@ use foreign_types::{ForeignType, ForeignTypeRef, Opaque};
 use libc::{c_char, c_int, c_long, c_uchar, c_uint, c_void};
 use std::any::TypeId;
 use std::cmp;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::convert::TryInto;
 use std::ffi::{CStr, CString};
 use std::fmt;
@@ -77,25 +77,29 @@ use std::ptr::{self, NonNull};
 use std::slice;
 use std::str;
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 use crate::dh::DhRef;
 use crate::ec::EcKeyRef;
-use crate::error::ErrorStack;
+use crate::error::{put_error, ErrorStack};
 use crate::ex_data::Index;
+#[cfg(not(feature = "fips"))]
+use crate::hpke::HpkeKeyRef;
 use crate::nid::Nid;
-use crate::pkey::{HasPrivate, PKeyRef, Params, Private};
+use crate::pkey::{HasPrivate, PKey, PKeyRef, Params, Private};
 use crate::srtp::{SrtpProtectionProfile, SrtpProtectionProfileRef};
 use crate::ssl::bio::BioMethod;
 use crate::ssl::callbacks::*;
 use crate::ssl::error::InnerError;
-use crate::stack::{Stack, StackRef};
+use crate::stack::{Stack, StackRef, Stackable};
 use crate::x509::store::{X509Store, X509StoreBuilderRef, X509StoreRef};
-use crate::x509::verify::X509VerifyParamRef;
+use crate::x509::verify::{X509CheckFlags, X509VerifyParamRef};
 use crate::x509::{X509Name, X509Ref, X509StoreContextRef, X509VerifyResult, X509};
 use crate::{cvt, cvt_0i, cvt_n, cvt_p, init};
 
 pub use crate::ssl::connector::{
-    ConnectConfiguration, SslAcceptor, SslAcceptorBuilder, SslConnector, SslConnectorBuilder,
+    ConnectConfiguration, ServerName, SslAcceptor, SslAcceptorBuilder, SslConnector,
+    SslConnectorBuilder,
 };
 pub use crate::ssl::error::{Error, ErrorCode, HandshakeError};
 
@@ -223,6 +227,14 @@ bitflags! {
         ///
         /// Do not use this unless you know what you're doing!
         const SEND_FALLBACK_SCSV = ffi::SSL_MODE_SEND_FALLBACK_SCSV as _;
+
+        /// Enables TLS False Start on the client.
+        ///
+        /// With False Start, a client finishes a forward-secret TLS 1.2 handshake as soon as it
+        /// has sent its Finished message, and may write application data before receiving the
+        /// server's. BoringSSL only does so if ALPN was negotiated, unless
+        /// `SslContextBuilder::set_false_start_allowed_without_alpn` is enabled.
+        const ENABLE_FALSE_START = ffi::SSL_MODE_ENABLE_FALSE_START as _;
     }
 }
 
@@ -407,6 +419,19 @@ impl NameType {
     }
 }
 
+/// Whether a TLS 1.3 KeyUpdate asks the peer to update its own sending keys as well.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct KeyUpdateRequest(c_int);
+
+impl KeyUpdateRequest {
+    /// Only update the local sending keys.
+    pub const NOT_REQUESTED: KeyUpdateRequest =
+        KeyUpdateRequest(ffi::SSL_KEY_UPDATE_NOT_REQUESTED as _);
+
+    /// Update the local sending keys and request the peer to update its own.
+    pub const REQUESTED: KeyUpdateRequest = KeyUpdateRequest(ffi::SSL_KEY_UPDATE_REQUESTED as _);
+}
+
 lazy_static! {
     static ref INDEXES: Mutex<HashMap<TypeId, c_int>> = Mutex::new(HashMap::new());
     static ref SSL_INDEXES: Mutex<HashMap<TypeId, c_int>> = Mutex::new(HashMap::new());
@@ -449,6 +474,35 @@ impl SslAlert {
     pub const UNRECOGNIZED_NAME: SslAlert = SslAlert(ffi::SSL_AD_UNRECOGNIZED_NAME);
     pub const ILLEGAL_PARAMETER: SslAlert = SslAlert(ffi::SSL_AD_ILLEGAL_PARAMETER);
     pub const DECODE_ERROR: SslAlert = SslAlert(ffi::SSL_AD_DECODE_ERROR);
+    pub const INTERNAL_ERROR: SslAlert = SslAlert(ffi::SSL_AD_INTERNAL_ERROR);
+    /// Alert 42 - `bad_certificate`.
+    pub const BAD_CERTIFICATE: SslAlert = SslAlert(ffi::SSL_AD_BAD_CERTIFICATE);
+    /// Alert 43 - `unsupported_certificate`.
+    pub const UNSUPPORTED_CERTIFICATE: SslAlert = SslAlert(ffi::SSL_AD_UNSUPPORTED_CERTIFICATE);
+    /// Alert 44 - `certificate_revoked`.
+    pub const CERTIFICATE_REVOKED: SslAlert = SslAlert(ffi::SSL_AD_CERTIFICATE_REVOKED);
+    /// Alert 45 - `certificate_expired`.
+    pub const CERTIFICATE_EXPIRED: SslAlert = SslAlert(ffi::SSL_AD_CERTIFICATE_EXPIRED);
+    /// Alert 46 - `certificate_unknown`.
+    pub const CERTIFICATE_UNKNOWN: SslAlert = SslAlert(ffi::SSL_AD_CERTIFICATE_UNKNOWN);
+    /// Alert 48 - `unknown_ca`.
+    pub const UNKNOWN_CA: SslAlert = SslAlert(ffi::SSL_AD_UNKNOWN_CA);
+    /// Alert 49 - `access_denied`.
+    pub const ACCESS_DENIED: SslAlert = SslAlert(ffi::SSL_AD_ACCESS_DENIED);
+
+    /// Returns the alert which best describes a certificate verification failure, such as
+    /// [`CERTIFICATE_EXPIRED`] for an expired certificate.
+    ///
+    /// This is useful to report the outcome of a verification performed by a custom verify
+    /// callback to the peer.
+    ///
+    /// This corresponds to [`SSL_alert_from_verify_result`].
+    ///
+    /// [`CERTIFICATE_EXPIRED`]: #associatedconstant.CERTIFICATE_EXPIRED
+    /// [`SSL_alert_from_verify_result`]: https://commondatastorage.googleapis.com/chromium-boringssl-docs/ssl.h.html#SSL_alert_from_verify_result
+    pub fn from_verify_result(result: X509VerifyResult) -> SslAlert {
+        unsafe { SslAlert(ffi::SSL_alert_from_verify_result(result.as_raw() as _)) }
+    }
 }
 
 /// An error returned from an ALPN selection callback.
@@ -470,6 +524,49 @@ pub struct SelectCertError(ffi::ssl_select_cert_result_t);
 impl SelectCertError {
     /// A fatal error occured and the handshake should be terminated.
     pub const ERROR: Self = Self(ffi::ssl_select_cert_result_t::ssl_select_cert_error);
+
+    /// The operation could not be completed and should be retried later.
+    ///
+    /// The handshake is suspended and `SSL_do_handshake` returns an error with code
+    /// [`ErrorCode::PENDING_CERTIFICATE`]. Once the information needed to make a decision is
+    /// available, calling [`MidHandshakeSslStream::handshake`] will invoke the callback again.
+    ///
+    /// The handshake is not waiting on I/O at that point, so nothing else will resume it. With
+    /// `tokio-boring`, the callback should keep the waker returned by
+    /// [`tokio_boring::handshake_waker`] for [`ClientHello::ssl`], and wake it once the decision
+    /// can be made.
+    ///
+    /// [`ErrorCode::PENDING_CERTIFICATE`]: struct.ErrorCode.html#associatedconstant.PENDING_CERTIFICATE
+    /// [`MidHandshakeSslStream::handshake`]: struct.MidHandshakeSslStream.html#method.handshake
+    /// [`tokio_boring::handshake_waker`]: https://docs.rs/tokio-boring/latest/tokio_boring/fn.handshake_waker.html
+    /// [`ClientHello::ssl`]: struct.ClientHello.html#method.ssl
+    pub const RETRY: Self = Self(ffi::ssl_select_cert_result_t::ssl_select_cert_retry);
+}
+
+/// An error returned from a certificate callback.
+#[derive(Debug, Clone)]
+pub struct CertCallbackError(c_int, Option<ErrorStack>);
+
+impl CertCallbackError {
+    /// A fatal error occured and the handshake should be terminated.
+    pub const ERROR: Self = Self(0, None);
+
+    /// The certificate lookup has not completed yet and the callback should be retried later.
+    ///
+    /// The handshake is suspended and `SSL_do_handshake` returns an error with code
+    /// [`ErrorCode::WANT_X509_LOOKUP`]. Once the lookup completes, calling
+    /// [`MidHandshakeSslStream::handshake`] will invoke the callback again.
+    ///
+    /// [`ErrorCode::WANT_X509_LOOKUP`]: struct.ErrorCode.html#associatedconstant.WANT_X509_LOOKUP
+    /// [`MidHandshakeSslStream::handshake`]: struct.MidHandshakeSslStream.html#method.handshake
+    pub const RETRY: Self = Self(-1, None);
+}
+
+impl From<ErrorStack> for CertCallbackError {
+    /// A fatal error, whose errors are reported by the handshake once the callback returns.
+    fn from(e: ErrorStack) -> CertCallbackError {
+        CertCallbackError(0, Some(e))
+    }
 }
 
 /// Extension types, to be used with `ClientHello::get_extension`.
@@ -484,6 +581,11 @@ impl ExtensionType {
     pub const SRTP: Self = Self(ffi::TLSEXT_TYPE_srtp as u16);
     pub const APPLICATION_LAYER_PROTOCOL_NEGOTIATION: Self =
         Self(ffi::TLSEXT_TYPE_application_layer_protocol_negotiation as u16);
+    /// The ClientHello padding extension.
+    ///
+    /// BoringSSL has no switch for it: it always pads ClientHellos which would otherwise be
+    /// between 256 and 511 bytes long to at least 512 bytes, to work around middleboxes which fail
+    /// to handle hellos of that size.
     pub const PADDING: Self = Self(ffi::TLSEXT_TYPE_padding as u16);
     pub const EXTENDED_MASTER_SECRET: Self = Self(ffi::TLSEXT_TYPE_extended_master_secret as u16);
     #[cfg(not(feature = "fips"))]
@@ -608,6 +710,37 @@ impl SslSignatureAlgorithm {
         SslSignatureAlgorithm(ffi::SSL_SIGN_RSA_PSS_RSAE_SHA512 as _);
 
     pub const ED25519: SslSignatureAlgorithm = SslSignatureAlgorithm(ffi::SSL_SIGN_ED25519 as _);
+
+    /// Returns the curve an ECDSA algorithm is bound to in TLS 1.3, if any.
+    fn curve(self) -> Option<Nid> {
+        match self {
+            Self::ECDSA_SECP256R1_SHA256 => Some(Nid::X9_62_PRIME256V1),
+            Self::ECDSA_SECP384R1_SHA384 => Some(Nid::SECP384R1),
+            Self::ECDSA_SECP521R1_SHA512 => Some(Nid::SECP521R1),
+            _ => None,
+        }
+    }
+}
+
+/// A certificate compression algorithm, as defined in [RFC 8879].
+///
+/// [RFC 8879]: https://tools.ietf.org/html/rfc8879
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct CertCompressionAlgorithm(u16);
+
+impl CertCompressionAlgorithm {
+    pub const ZLIB: Self = Self(ffi::TLSEXT_cert_compression_zlib as u16);
+    pub const BROTLI: Self = Self(ffi::TLSEXT_cert_compression_brotli as u16);
+
+    /// Constructs a `CertCompressionAlgorithm` from a raw codepoint.
+    pub fn from_raw(raw: u16) -> Self {
+        Self(raw)
+    }
+
+    /// Returns the raw codepoint.
+    pub fn as_raw(&self) -> u16 {
+        self.0
+    }
 }
 
 /// A TLS Curve.
@@ -662,6 +795,24 @@ pub fn select_next_proto<'a>(server: &[u8], client: &'a [u8]) -> Option<&'a [u8]
     }
 }
 
+/// Encodes a list of protocol names into the ALPN wire format.
+///
+/// Each protocol must be between 1 and 255 bytes long.
+fn alpn_wire_format(protocols: &[&[u8]]) -> Result<Vec<u8>, ErrorStack> {
+    let mut wire = Vec::with_capacity(protocols.iter().map(|p| p.len() + 1).sum());
+    for protocol in protocols {
+        if protocol.is_empty() || protocol.len() > 255 {
+            return Err(put_error(
+                ffi::ERR_LIB_SSL as c_int,
+                ffi::SSL_R_INVALID_ALPN_PROTOCOL as c_int,
+            ));
+        }
+        wire.push(protocol.len() as u8);
+        wire.extend_from_slice(protocol);
+    }
+    Ok(wire)
+}
+
 /// A builder for `SslContext`s.
 pub struct SslContextBuilder(SslContext);
 
@@ -725,6 +876,86 @@ impl SslContextBuilder {
         }
     }
 
+    /// Replaces BoringSSL's certificate chain verification with a custom callback.
+    ///
+    /// Unlike the callback passed to [`set_verify_callback`], which is invoked for each
+    /// certificate while the default chain building runs, this callback is called once with a
+    /// store context holding the peer's leaf certificate (see [`X509StoreContextRef::cert`]) and
+    /// the rest of the chain it sent (see [`X509StoreContextRef::untrusted`]). No other
+    /// verification is performed: the callback returns whether the peer's certificate is
+    /// accepted. A rejection is reported as [`X509VerifyResult::APPLICATION_VERIFICATION`] by
+    /// [`SslRef::verify_result`], and the verification mode set by [`set_verify`] still decides
+    /// whether it aborts the handshake.
+    ///
+    /// This corresponds to [`SSL_CTX_set_cert_verify_callback`].
+    ///
+    /// [`set_verify_callback`]: #method.set_verify_callback
+    /// [`set_verify`]: #method.set_verify
+    /// [`X509VerifyResult::APPLICATION_VERIFICATION`]: ../x509/struct.X509VerifyResult.html#associatedconstant.APPLICATION_VERIFICATION
+    /// [`SslRef::verify_result`]: struct.SslRef.html#method.verify_result
+    /// [`X509StoreContextRef::cert`]: ../x509/struct.X509StoreContextRef.html#method.cert
+    /// [`X509StoreContextRef::untrusted`]: ../x509/struct.X509StoreContextRef.html#method.untrusted
+    /// [`SSL_CTX_set_cert_verify_callback`]: https://commondatastorage.googleapis.com/chromium-boringssl-docs/ssl.h.html#SSL_CTX_set_cert_verify_callback
+    pub fn set_cert_verify_callback<F>(&mut self, callback: F)
+    where
+        F: Fn(&mut X509StoreContextRef) -> bool + 'static + Sync + Send,
+    {
+        unsafe {
+            self.set_ex_data(SslContext::cached_ex_index::<F>(), callback);
+            ffi::SSL_CTX_set_cert_verify_callback(
+                self.as_ptr(),
+                Some(raw_cert_verify::<F>),
+                ptr::null_mut(),
+            );
+        }
+    }
+
+    /// Replaces certificate verification for new connections with a custom callback.
+    ///
+    /// The callback is invoked once the peer's certificate chain has been received, with the
+    /// connection it belongs to; the chain is available through [`SslRef::peer_cert_chain`] and
+    /// [`SslRef::peer_certificate`]. Returning an error aborts the handshake with the given alert,
+    /// such as [`SslAlert::CERTIFICATE_REVOKED`], which lets the peer tell why its certificate was
+    /// rejected. [`SslAlert::from_verify_result`] maps an X509 verification error to the matching
+    /// alert. No verification against the context's certificate store is performed.
+    ///
+    /// This corresponds to [`SSL_CTX_set_custom_verify`].
+    ///
+    /// [`SslRef::peer_cert_chain`]: struct.SslRef.html#method.peer_cert_chain
+    /// [`SslRef::peer_certificate`]: struct.SslRef.html#method.peer_certificate
+    /// [`SslAlert::CERTIFICATE_REVOKED`]: struct.SslAlert.html#associatedconstant.CERTIFICATE_REVOKED
+    /// [`SslAlert::from_verify_result`]: struct.SslAlert.html#method.from_verify_result
+    /// [`SSL_CTX_set_custom_verify`]: https://commondatastorage.googleapis.com/chromium-boringssl-docs/ssl.h.html#SSL_CTX_set_custom_verify
+    pub fn set_custom_verify_callback<F>(&mut self, mode: SslVerifyMode, callback: F)
+    where
+        F: Fn(&mut SslRef) -> Result<(), SslAlert> + 'static + Sync + Send,
+    {
+        unsafe {
+            self.set_ex_data(SslContext::cached_ex_index::<F>(), callback);
+            ffi::SSL_CTX_set_custom_verify(
+                self.as_ptr(),
+                mode.bits as c_int,
+                Some(raw_custom_verify::<F>),
+            );
+        }
+    }
+
+    /// Controls whether the peer's certificate is verified again when a session is resumed.
+    ///
+    /// By default a resumed session inherits the verification result of the handshake that
+    /// established it. When enabled, the callback configured by
+    /// [`set_custom_verify_callback`] is run on resumption as well, and the resumption fails if
+    /// it rejects the certificate. This only has an effect on clients using a custom verify
+    /// callback.
+    ///
+    /// This corresponds to [`SSL_CTX_set_reverify_on_resume`].
+    ///
+    /// [`set_custom_verify_callback`]: #method.set_custom_verify_callback
+    /// [`SSL_CTX_set_reverify_on_resume`]: https://commondatastorage.googleapis.com/chromium-boringssl-docs/ssl.h.html#SSL_CTX_set_reverify_on_resume
+    pub fn set_reverify_on_resume(&mut self, enabled: bool) {
+        unsafe { ffi::SSL_CTX_set_reverify_on_resume(self.as_ptr(), enabled as c_int) }
+    }
+
     /// Configures the server name indication (SNI) callback for new connections.
     ///
     /// SNI is used to allow a single server to handle requests for multiple domains, each of which
@@ -755,6 +986,77 @@ impl SslContextBuilder {
         }
     }
 
+    /// Selects the certificate presented to each client based on the name it sends via Server
+    /// Name Indication (SNI).
+    ///
+    /// `certificates` maps a host name to a leaf certificate, the rest of its chain and its
+    /// private key. Names are matched with [`match_servername`], so a name of the form
+    /// `*.example.com` matches any single label in place of the `*`. If the client does not send
+    /// a name, or no entry matches, the context's own certificate is used.
+    ///
+    /// An exact name takes precedence over a wildcard. As a wildcard only stands for the
+    /// left-most label, no two distinct patterns match the same name; of names which only differ
+    /// in case or by a trailing dot, the one that sorts first is used.
+    ///
+    /// This installs a servername callback and so replaces any callback set with
+    /// [`set_servername_callback`].
+    ///
+    /// [`match_servername`]: fn.match_servername.html
+    /// [`set_servername_callback`]: #method.set_servername_callback
+    pub fn set_servername_certificates(
+        &mut self,
+        certificates: HashMap<String, (X509, Stack<X509>, PKey<Private>)>,
+    ) {
+        fn normalize(name: &str) -> String {
+            let mut name = name.to_ascii_lowercase();
+            if name.ends_with('.') {
+                name.pop();
+            }
+            name
+        }
+
+        // Insert in sorted order so that the same entry wins every time names collide.
+        let entries = certificates.into_iter().collect::<BTreeMap<_, _>>();
+        let mut certificates = HashMap::new();
+        for (name, entry) in entries {
+            certificates.entry(normalize(&name)).or_insert(entry);
+        }
+
+        self.set_servername_callback(move |ssl, alert| {
+            let host = match ssl.servername(NameType::HOST_NAME) {
+                Some(host) => normalize(host),
+                None => return Ok(()),
+            };
+
+            // A `*` in the name itself must not match a pattern literally.
+            if host.contains('*') {
+                return Ok(());
+            }
+
+            let entry = certificates.get(&host).or_else(|| {
+                let pattern = format!("*{}", &host[host.find('.')?..]);
+                certificates
+                    .get(&pattern)
+                    .filter(|_| match_servername(&pattern, &host))
+            });
+            let (cert, chain, key) = match entry {
+                Some(entry) => entry,
+                None => return Ok(()),
+            };
+
+            let r = ssl
+                .set_certificate(cert)
+                .and_then(|()| ssl.set_chain(chain))
+                .and_then(|()| ssl.set_private_key(key));
+            if r.is_err() {
+                *alert = SslAlert::INTERNAL_ERROR;
+                return Err(SniError::ALERT_FATAL);
+            }
+
+            Ok(())
+        });
+    }
+
     /// Sets the certificate verification depth.
     ///
     /// If the peer's certificate chain is longer than this value, verification will fail.
@@ -834,7 +1136,13 @@ impl SslContextBuilder {
 
     /// Sets the parameters to be used during ephemeral elliptic curve Diffie-Hellman key exchange.
     ///
+    /// In BoringSSL this restricts the supported groups to the key's curve, pinning the ECDHE
+    /// group of TLS 1.2 handshakes. Prefer [`set_curves`], which accepts a preference list and
+    /// also covers X25519 and TLS 1.3.
+    ///
     /// This corresponds to `SSL_CTX_set_tmp_ecdh`.
+    ///
+    /// [`set_curves`]: #method.set_curves
     pub fn set_tmp_ecdh(&mut self, key: &EcKeyRef<Params>) -> Result<(), ErrorStack> {
         unsafe { cvt(ffi::SSL_CTX_set_tmp_ecdh(self.as_ptr(), key.as_ptr()) as c_int).map(|_| ()) }
     }
@@ -971,8 +1279,13 @@ impl SslContextBuilder {
     ///
     /// Use `add_extra_chain_cert` to add the remainder of the certificate chain.
     ///
+    /// Unlike OpenSSL, BoringSSL does not keep a certificate per key type, so this replaces any
+    /// previously set certificate. See [`set_cert_callback`] for serving several certificates.
+    ///
     /// This corresponds to [`SSL_CTX_use_certificate`].
     ///
+    /// [`set_cert_callback`]: #method.set_cert_callback
+    ///
     /// [`SSL_CTX_use_certificate`]: https://www.openssl.org/docs/man1.0.2/ssl/SSL_CTX_use_certificate_file.html
     pub fn set_certificate(&mut self, cert: &X509Ref) -> Result<(), ErrorStack> {
         unsafe { cvt(ffi::SSL_CTX_use_certificate(self.as_ptr(), cert.as_ptr())).map(|_| ()) }
@@ -1106,8 +1419,18 @@ impl SslContextBuilder {
     /// A value of `None` will enable protocol versions down the the highest version supported by
     /// OpenSSL.
     ///
+    /// Regardless of this setting, BoringSSL always implements the TLS 1.3 downgrade protection of
+    /// [RFC 8446 section 4.1.3]: a client which supports TLS 1.3 and sees the downgrade sentinel
+    /// in a TLS 1.2 ServerHello fails the handshake with a `TLS13_DOWNGRADE` error. There is no way
+    /// to select TLS 1.3 draft variants or disable this check: `SSL_CTX_set_ignore_tls13_downgrade`
+    /// has no effect in BoringSSL, so it is not exposed. When testing against a middlebox which
+    /// breaks TLS 1.3, cap the client's maximum version at TLS 1.2 instead, since the sentinel is
+    /// only checked by clients which offered TLS 1.3.
+    ///
     /// This corresponds to [`SSL_CTX_set_max_proto_version`].
     ///
+    /// [RFC 8446 section 4.1.3]: https://tools.ietf.org/html/rfc8446#section-4.1.3
+    ///
     /// [`SSL_CTX_set_max_proto_version`]: https://www.openssl.org/docs/man1.1.0/ssl/SSL_set_min_proto_version.html
     pub fn set_max_proto_version(&mut self, version: Option<SslVersion>) -> Result<(), ErrorStack> {
         unsafe {
@@ -1184,6 +1507,21 @@ impl SslContextBuilder {
         }
     }
 
+    /// Sets the protocols to sent to the server for Application Layer Protocol Negotiation (ALPN).
+    ///
+    /// Unlike [`set_alpn_protos`], this takes a list of protocol names and encodes the wire format
+    /// itself. Each protocol must be between 1 and 255 bytes long. The protocols are ordered by
+    /// preference.
+    ///
+    /// This corresponds to [`SSL_CTX_set_alpn_protos`].
+    ///
+    /// [`set_alpn_protos`]: #method.set_alpn_protos
+    /// [`SSL_CTX_set_alpn_protos`]: https://www.openssl.org/docs/man1.1.0/ssl/SSL_CTX_set_alpn_protos.html
+    pub fn set_alpn_protocols(&mut self, protocols: &[&[u8]]) -> Result<(), ErrorStack> {
+        let wire = alpn_wire_format(protocols)?;
+        self.set_alpn_protos(&wire)
+    }
+
     /// Enables the DTLS extension "use_srtp" as defined in RFC5764.
     ///
     /// This corresponds to [`SSL_CTX_set_tlsext_use_srtp`].
@@ -1203,6 +1541,22 @@ impl SslContextBuilder {
         }
     }
 
+    /// Configures whether a client accepts ALPN protocols it did not offer.
+    ///
+    /// By default, a client aborts the handshake if the server selects a protocol which is not in
+    /// the list configured with [`set_alpn_protos`]. With this enabled, the server's selection is
+    /// accepted and reported by [`SslRef::selected_alpn_protocol`] as is. This is useful for
+    /// proxies which forward the protocol negotiated with the origin server. Servers are unaffected.
+    ///
+    /// This corresponds to [`SSL_CTX_set_allow_unknown_alpn_protos`].
+    ///
+    /// [`set_alpn_protos`]: #method.set_alpn_protos
+    /// [`SslRef::selected_alpn_protocol`]: struct.SslRef.html#method.selected_alpn_protocol
+    /// [`SSL_CTX_set_allow_unknown_alpn_protos`]: https://commondatastorage.googleapis.com/chromium-boringssl-docs/ssl.h.html#SSL_CTX_set_allow_unknown_alpn_protos
+    pub fn set_allow_unknown_alpn_protos(&mut self, allow: bool) {
+        unsafe { ffi::SSL_CTX_set_allow_unknown_alpn_protos(self.as_ptr(), allow as c_int) }
+    }
+
     /// Sets the callback used by a server to select a protocol for Application Layer Protocol
     /// Negotiation (ALPN).
     ///
@@ -1229,12 +1583,25 @@ impl SslContextBuilder {
             );
         }
     }
+
     /// Sets a callback that is called before most ClientHello processing and before the decision whether
     /// to resume a session is made. The callback may inspect the ClientHello and configure the
     /// connection.
     ///
+    /// This is BoringSSL's equivalent of OpenSSL's `SSL_CTX_set_client_hello_cb`: it is the
+    /// earliest point at which a server can act on a ClientHello, and runs before the servername
+    /// callback and any certificate selection. Returning `Ok(())` continues the handshake,
+    /// [`SelectCertError::ERROR`] rejects the connection, and [`SelectCertError::RETRY`] suspends
+    /// the handshake so that the decision can be made asynchronously; see its documentation for
+    /// how to resume it.
+    ///
+    /// BoringSSL has no ClientHello callback separate from this one, so there is no
+    /// `set_client_hello_callback`: a second wrapper could only replace this callback.
+    ///
     /// This corresponds to [`SSL_CTX_set_select_certificate_cb`].
     ///
+    /// [`SelectCertError::ERROR`]: struct.SelectCertError.html#associatedconstant.ERROR
+    /// [`SelectCertError::RETRY`]: struct.SelectCertError.html#associatedconstant.RETRY
     /// [`SSL_CTX_set_select_certificate_cb`]: https://www.openssl.org/docs/man1.1.0/ssl/SSL_CTX_set_select_certificate_cb.html
     pub fn set_select_certificate_callback<F>(&mut self, callback: F)
     where
@@ -1249,6 +1616,43 @@ impl SslContextBuilder {
         }
     }
 
+    /// Sets a callback that is called on servers to configure the certificate of a connection.
+    ///
+    /// BoringSSL holds a single certificate and private key per context or connection, so
+    /// setting a second one with [`set_certificate`] replaces the first instead of adding
+    /// another one for a different key type. Servers which offer both an ECDSA and an RSA
+    /// certificate should instead install this callback and select one with
+    /// [`SslRef::set_certificate`] and [`SslRef::set_private_key`], based on the signature
+    /// algorithms reported by [`SslRef::peer_verify_algorithms`].
+    ///
+    /// The callback runs after the ClientHello has been processed, before the certificate is
+    /// used. Returning [`CertCallbackError::ERROR`], or an `ErrorStack` through `?`, aborts the
+    /// handshake. Returning [`CertCallbackError::RETRY`] suspends it so that the certificate can
+    /// be looked up asynchronously.
+    ///
+    /// This corresponds to [`SSL_CTX_set_cert_cb`].
+    ///
+    /// [`CertCallbackError::ERROR`]: struct.CertCallbackError.html#associatedconstant.ERROR
+    /// [`CertCallbackError::RETRY`]: struct.CertCallbackError.html#associatedconstant.RETRY
+    /// [`set_certificate`]: #method.set_certificate
+    /// [`SslRef::set_certificate`]: struct.SslRef.html#method.set_certificate
+    /// [`SslRef::set_private_key`]: struct.SslRef.html#method.set_private_key
+    /// [`SslRef::peer_verify_algorithms`]: struct.SslRef.html#method.peer_verify_algorithms
+    /// [`SSL_CTX_set_cert_cb`]: https://commondatastorage.googleapis.com/chromium-boringssl-docs/ssl.h.html#SSL_CTX_set_cert_cb
+    pub fn set_cert_callback<F>(&mut self, callback: F)
+    where
+        F: Fn(&mut SslRef) -> Result<(), CertCallbackError> + 'static + Sync + Send,
+    {
+        unsafe {
+            self.set_ex_data(SslContext::cached_ex_index::<F>(), callback);
+            ffi::SSL_CTX_set_cert_cb(
+                self.as_ptr(),
+                Some(callbacks::raw_cert::<F>),
+                ptr::null_mut(),
+            );
+        }
+    }
+
     /// Checks for consistency between the private key and certificate.
     ///
     /// This corresponds to [`SSL_CTX_check_private_key`].
@@ -1306,6 +1710,38 @@ impl SslContextBuilder {
         }
     }
 
+    /// Sets a server-side callback producing the OCSP response stapled to the handshake.
+    ///
+    /// The callback is invoked when a client requests certificate status, after the server name
+    /// and certificate have been selected, and returns the DER-encoded OCSP response to send.
+    /// Returning `Ok(None)` sends no staple, and returning an error aborts the handshake.
+    ///
+    /// This is a convenience over [`set_status_callback`] and replaces any callback set by it.
+    ///
+    /// This corresponds to [`SSL_CTX_set_tlsext_status_cb`].
+    ///
+    /// [`set_status_callback`]: #method.set_status_callback
+    /// [`SSL_CTX_set_tlsext_status_cb`]: https://www.openssl.org/docs/man1.0.2/ssl/SSL_CTX_set_tlsext_status_cb.html
+    pub fn set_ocsp_callback<F>(&mut self, callback: F) -> Result<(), ErrorStack>
+    where
+        F: FnMut(&mut SslRef) -> Result<Option<Vec<u8>>, ErrorStack> + 'static + Send,
+    {
+        let callback = Mutex::new(callback);
+        self.set_status_callback(move |ssl| {
+            let mut callback = match callback.lock() {
+                Ok(callback) => callback,
+                Err(poisoned) => poisoned.into_inner(),
+            };
+            match (*callback)(ssl)? {
+                Some(response) => {
+                    ssl.set_ocsp_status(&response)?;
+                    Ok(true)
+                }
+                None => Ok(false),
+            }
+        })
+    }
+
     /// Sets the callback for providing an identity and pre-shared key for a TLS-PSK client.
     ///
     /// The callback will be called with the SSL context, an identity hint if one was provided
@@ -1364,6 +1800,32 @@ impl SslContextBuilder {
         }
     }
 
+    /// Sets the PSK identity hint sent by a TLS-PSK server.
+    ///
+    /// The hint is passed to the client's callback configured with
+    /// [`set_psk_client_callback`], and can be used to select the identity and key to use. It is
+    /// only sent for TLS 1.2 PSK cipher suites.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `hint` contains a NUL byte.
+    ///
+    /// This corresponds to [`SSL_CTX_use_psk_identity_hint`].
+    ///
+    /// [`set_psk_client_callback`]: #method.set_psk_client_callback
+    /// [`SSL_CTX_use_psk_identity_hint`]: https://commondatastorage.googleapis.com/chromium-boringssl-docs/ssl.h.html#SSL_CTX_use_psk_identity_hint
+    #[cfg(not(osslconf = "OPENSSL_NO_PSK"))]
+    pub fn set_psk_identity_hint(&mut self, hint: &[u8]) -> Result<(), ErrorStack> {
+        let hint = CString::new(hint).unwrap();
+        unsafe {
+            cvt(ffi::SSL_CTX_use_psk_identity_hint(
+                self.as_ptr(),
+                hint.as_ptr(),
+            ))
+            .map(|_| ())
+        }
+    }
+
     /// Sets the callback which is called when new sessions are negotiated.
     ///
     /// This can be used by clients to implement session caching. While in TLSv1.2 the session is
@@ -1417,12 +1879,19 @@ impl SslContextBuilder {
     /// return the session corresponding to that ID if available. This is only used for servers, not
     /// clients.
     ///
+    /// Ownership of the returned session is transferred to BoringSSL. External caches should
+    /// enable [`SslSessionCacheMode::NO_INTERNAL`] and store sessions from the callbacks set by
+    /// [`set_new_session_callback`] and [`set_remove_session_callback`].
+    ///
     /// This corresponds to [`SSL_CTX_sess_set_get_cb`].
     ///
     /// # Safety
     ///
     /// The returned `SslSession` must not be associated with a different `SslContext`.
     ///
+    /// [`SslSessionCacheMode::NO_INTERNAL`]: struct.SslSessionCacheMode.html#associatedconstant.NO_INTERNAL
+    /// [`set_new_session_callback`]: #method.set_new_session_callback
+    /// [`set_remove_session_callback`]: #method.set_remove_session_callback
     /// [`SSL_CTX_sess_set_get_cb`]: https://www.openssl.org/docs/manmaster/man3/SSL_CTX_sess_set_new_cb.html
     pub unsafe fn set_get_session_callback<F>(&mut self, callback: F)
     where
@@ -1451,6 +1920,107 @@ impl SslContextBuilder {
         }
     }
 
+    /// Forwards BoringSSL's connection state notifications to the `log` crate.
+    ///
+    /// Alerts sent or received on connections created from this context are logged at the `warn`
+    /// level, and the start and completion of handshakes at the `debug` level, all with the given
+    /// log target. This requires the `log` feature.
+    ///
+    /// This is implemented with the context's info callback, which is shared with
+    /// [`SslContextBuilder::enable_handshake_metrics`], so both can be used together.
+    ///
+    /// [`SslContextBuilder::enable_handshake_metrics`]: #method.enable_handshake_metrics
+    ///
+    /// This corresponds to [`SSL_CTX_set_info_callback`].
+    ///
+    /// [`SSL_CTX_set_info_callback`]: https://commondatastorage.googleapis.com/chromium-boringssl-docs/ssl.h.html#SSL_CTX_set_info_callback
+    #[cfg(feature = "log")]
+    pub fn set_info_log_target(&mut self, target: &'static str) {
+        unsafe {
+            self.set_ex_data(
+                SslContext::cached_ex_index::<callbacks::InfoLogTarget>(),
+                callbacks::InfoLogTarget(target),
+            );
+            ffi::SSL_CTX_set_info_callback(self.as_ptr(), Some(callbacks::raw_info));
+        }
+    }
+
+    /// Records the start and end time of each handshake on connections created from this context.
+    ///
+    /// The duration of the most recently completed handshake can then be retrieved with
+    /// [`SslRef::handshake_duration`]. This is implemented with the context's info callback, which
+    /// is shared with `set_info_log_target`, so both can be used together.
+    ///
+    /// This corresponds to [`SSL_CTX_set_info_callback`].
+    ///
+    /// [`SslRef::handshake_duration`]: struct.SslRef.html#method.handshake_duration
+    /// [`SSL_CTX_set_info_callback`]: https://commondatastorage.googleapis.com/chromium-boringssl-docs/ssl.h.html#SSL_CTX_set_info_callback
+    pub fn enable_handshake_metrics(&mut self) {
+        unsafe {
+            self.set_ex_data(
+                SslContext::cached_ex_index::<callbacks::HandshakeMetrics>(),
+                callbacks::HandshakeMetrics,
+            );
+            ffi::SSL_CTX_set_info_callback(self.as_ptr(), Some(callbacks::raw_info));
+        }
+    }
+
+    /// Registers a certificate compression algorithm for TLS 1.3.
+    ///
+    /// `compress` is called by servers (or clients sending a certificate) with the encoded
+    /// Certificate message, and should return its compressed form. `decompress` is called with
+    /// compressed data and the uncompressed length the peer claims, and must return exactly that
+    /// many bytes. Algorithms are offered to the peer in the order they were registered.
+    ///
+    /// BoringSSL does not enable any algorithm by default, so certificate compression is disabled
+    /// unless this method is called. At most 8 algorithms can be registered on a context, and
+    /// registering more returns an error.
+    ///
+    /// This corresponds to [`SSL_CTX_add_cert_compression_alg`].
+    ///
+    /// [`SSL_CTX_add_cert_compression_alg`]: https://commondatastorage.googleapis.com/chromium-boringssl-docs/ssl.h.html#SSL_CTX_add_cert_compression_alg
+    pub fn add_cert_compression_alg<C, D>(
+        &mut self,
+        algorithm: CertCompressionAlgorithm,
+        compress: C,
+        decompress: D,
+    ) -> Result<(), ErrorStack>
+    where
+        C: Fn(&mut SslRef, &[u8]) -> io::Result<Vec<u8>> + 'static + Sync + Send,
+        D: Fn(&mut SslRef, &[u8], usize) -> io::Result<Vec<u8>> + 'static + Sync + Send,
+    {
+        unsafe {
+            // The callbacks are kept in a single list, as keying them by type would let two
+            // algorithms with the same callback types overwrite each other.
+            let index = SslContext::cached_ex_index::<callbacks::CertCompressionAlgs>();
+            let mut algs = ffi::SSL_CTX_get_ex_data(self.as_ptr(), index.as_raw())
+                as *mut callbacks::CertCompressionAlgs;
+            if algs.is_null() {
+                algs =
+                    self.set_ex_data_inner(index, callbacks::CertCompressionAlgs(vec![])) as *mut _;
+            }
+
+            let (raw_compress, raw_decompress) =
+                match callbacks::cert_compression_slot((*algs).0.len()) {
+                    Some(slot) => slot,
+                    None => {
+                        return Err(put_error(
+                            ffi::ERR_LIB_SSL as c_int,
+                            ffi::ERR_R_OVERFLOW as c_int,
+                        ))
+                    }
+                };
+            cvt(ffi::SSL_CTX_add_cert_compression_alg(
+                self.as_ptr(),
+                algorithm.0,
+                raw_compress,
+                raw_decompress,
+            ))?;
+            (*algs).0.push((Box::new(compress), Box::new(decompress)));
+            Ok(())
+        }
+    }
+
     /// Sets the session caching mode use for connections made with the context.
     ///
     /// Returns the previous session caching mode.
@@ -1497,6 +2067,38 @@ impl SslContextBuilder {
         unsafe { ffi::SSL_CTX_sess_set_cache_size(self.as_ptr(), size.into()).into() }
     }
 
+    /// Sets the lifetime of sessions established by the context, returning the previous lifetime.
+    ///
+    /// Sessions older than this are not resumed. The lifetime has a granularity of one second and
+    /// defaults to two hours.
+    ///
+    /// This only applies to TLS 1.2 and earlier. TLS 1.3 sessions use a separate lifetime set by
+    /// `SSL_CTX_set_session_psk_dhe_timeout`, which also determines the lifetime advertised in
+    /// TLS 1.3 session tickets.
+    ///
+    /// This corresponds to [`SSL_CTX_set_timeout`].
+    ///
+    /// [`SSL_CTX_set_timeout`]: https://commondatastorage.googleapis.com/chromium-boringssl-docs/ssl.h.html#SSL_CTX_set_timeout
+    pub fn set_session_timeout(&mut self, timeout: Duration) -> Duration {
+        let secs = cmp::min(timeout.as_secs(), u64::from(u32::max_value())) as u32;
+        let prev = unsafe { ffi::SSL_CTX_set_timeout(self.as_ptr(), secs) };
+        Duration::from_secs(prev.into())
+    }
+
+    /// Configures whether connections created from this context shut down quietly.
+    ///
+    /// With quiet shutdown enabled, `shutdown` marks the session as shut down in both directions
+    /// without sending a close_notify alert to the peer. The peer then cannot distinguish the end
+    /// of the connection from a truncation attack, so this should only be used when the
+    /// application protocol delimits its own messages.
+    ///
+    /// This corresponds to [`SSL_CTX_set_quiet_shutdown`].
+    ///
+    /// [`SSL_CTX_set_quiet_shutdown`]: https://commondatastorage.googleapis.com/chromium-boringssl-docs/ssl.h.html#SSL_CTX_set_quiet_shutdown
+    pub fn set_quiet_shutdown(&mut self, quiet: bool) {
+        unsafe { ffi::SSL_CTX_set_quiet_shutdown(self.as_ptr(), quiet as c_int) }
+    }
+
     /// Sets the context's supported signature algorithms.
     ///
     /// This corresponds to [`SSL_CTX_set1_sigalgs_list`].
@@ -1519,8 +2121,44 @@ impl SslContextBuilder {
         unsafe { ffi::SSL_CTX_set_grease_enabled(self.as_ptr(), enabled as _) }
     }
 
+    /// Sets whether False Start may be used when ALPN was not negotiated.
+    ///
+    /// By default, a client with [`SslMode::ENABLE_FALSE_START`] only uses False Start if ALPN
+    /// was negotiated, which excludes servers that are known to break with it. Enabling this
+    /// removes that requirement.
+    ///
+    /// This corresponds to [`SSL_CTX_set_false_start_allowed_without_alpn`].
+    ///
+    /// [`SslMode::ENABLE_FALSE_START`]: struct.SslMode.html#associatedconstant.ENABLE_FALSE_START
+    /// [`SSL_CTX_set_false_start_allowed_without_alpn`]: https://commondatastorage.googleapis.com/chromium-boringssl-docs/ssl.h.html#SSL_CTX_set_false_start_allowed_without_alpn
+    pub fn set_false_start_allowed_without_alpn(&mut self, allowed: bool) {
+        unsafe { ffi::SSL_CTX_set_false_start_allowed_without_alpn(self.as_ptr(), allowed as _) }
+    }
+
+    /// Sets whether the context should enable TLS 1.3 early data (0-RTT).
+    ///
+    /// Clients will offer early data when resuming a session that permits it, and servers will
+    /// accept it when resuming. Early data may be replayed by an attacker, so it must only be used
+    /// for idempotent requests.
+    ///
+    /// BoringSSL does not allow the amount of early data to be configured for TLS. Servers
+    /// advertise a fixed limit of 14336 bytes in their tickets, and reject early data beyond it.
+    ///
+    /// This corresponds to [`SSL_CTX_set_early_data_enabled`].
+    ///
+    /// [`SSL_CTX_set_early_data_enabled`]: https://commondatastorage.googleapis.com/chromium-boringssl-docs/ssl.h.html#SSL_CTX_set_early_data_enabled
+    pub fn set_early_data_enabled(&mut self, enabled: bool) {
+        unsafe { ffi::SSL_CTX_set_early_data_enabled(self.as_ptr(), enabled as _) }
+    }
+
     /// Sets the context's supported signature verification algorithms.
     ///
+    /// These are the algorithms the peer may use to sign the handshake, and are advertised to
+    /// it; a client uses this to refuse servers signing with weak algorithms. This is distinct
+    /// from [`set_sigalgs_list`], which also configures the algorithms used for signing.
+    ///
+    /// [`set_sigalgs_list`]: #method.set_sigalgs_list
+    ///
     /// This corresponds to [`SSL_CTX_set_verify_algorithm_prefs`]
     ///
     /// [`SSL_CTX_set_verify_algorithm_prefs`]: https://commondatastorage.googleapis.com/chromium-boringssl-docs/ssl.h.html#SSL_CTX_set_verify_algorithm_prefs
@@ -1538,6 +2176,75 @@ impl SslContextBuilder {
         }
     }
 
+    /// Sets the context's preferred signature algorithms for signing the handshake.
+    ///
+    /// Algorithms which the loaded private key cannot produce are skipped at handshake time, so
+    /// a list with none usable for the key fails every handshake. Use
+    /// [`check_signing_algorithm_prefs`] once the key is loaded to catch this early.
+    ///
+    /// This corresponds to [`SSL_CTX_set_signing_algorithm_prefs`].
+    ///
+    /// [`check_signing_algorithm_prefs`]: #method.check_signing_algorithm_prefs
+    /// [`SSL_CTX_set_signing_algorithm_prefs`]: https://commondatastorage.googleapis.com/chromium-boringssl-docs/ssl.h.html#SSL_CTX_set_signing_algorithm_prefs
+    pub fn set_signing_algorithm_prefs(
+        &mut self,
+        prefs: &[SslSignatureAlgorithm],
+    ) -> Result<(), ErrorStack> {
+        unsafe {
+            cvt_0i(ffi::SSL_CTX_set_signing_algorithm_prefs(
+                self.as_ptr(),
+                prefs.as_ptr() as *const _,
+                prefs.len(),
+            ))?;
+            // Replace in place so that setting the preferences again does not leak the old list.
+            let index = SslContext::cached_ex_index::<callbacks::SigningAlgorithmPrefs>();
+            let existing = ffi::SSL_CTX_get_ex_data(self.as_ptr(), index.as_raw())
+                as *mut callbacks::SigningAlgorithmPrefs;
+            if existing.is_null() {
+                self.set_ex_data(index, callbacks::SigningAlgorithmPrefs(prefs.to_vec()));
+            } else {
+                (*existing).0 = prefs.to_vec();
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks that the private key can sign with at least one of the algorithms configured by
+    /// [`set_signing_algorithm_prefs`].
+    ///
+    /// Returns an error with the `NO_COMMON_SIGNATURE_ALGORITHMS` reason otherwise, such as for
+    /// ECDSA-only preferences with an RSA key. The curve of an EC key must also match, as TLS 1.3
+    /// requires, so `ECDSA_SECP256R1_SHA256` alone is rejected for a P-384 key even though TLS 1.2
+    /// would accept it. It succeeds if no preferences or no private key have been set. Keys
+    /// configured per connection or from a certificate callback are not checked.
+    ///
+    /// [`set_signing_algorithm_prefs`]: #method.set_signing_algorithm_prefs
+    pub fn check_signing_algorithm_prefs(&self) -> Result<(), ErrorStack> {
+        let index = SslContext::cached_ex_index::<callbacks::SigningAlgorithmPrefs>();
+        let prefs = match self.0.ex_data(index) {
+            Some(prefs) => prefs,
+            None => return Ok(()),
+        };
+        let key = match self.0.private_key() {
+            Some(key) => key,
+            None => return Ok(()),
+        };
+
+        let key_type = key.id().as_raw();
+        let curve = key.ec_key().ok().and_then(|key| key.group().curve_name());
+        let usable = prefs.0.iter().any(|&alg| {
+            let key_ok = unsafe { ffi::SSL_get_signature_algorithm_key_type(alg.0) == key_type };
+            key_ok && alg.curve().map_or(true, |nid| Some(nid) == curve)
+        });
+        if !usable {
+            return Err(put_error(
+                ffi::ERR_LIB_SSL as c_int,
+                ffi::SSL_R_NO_COMMON_SIGNATURE_ALGORITHMS as c_int,
+            ));
+        }
+        Ok(())
+    }
+
     /// Enables SCT requests on all client SSL handshakes.
     ///
     /// This corresponds to [`SSL_CTX_enable_signed_cert_timestamps`]
@@ -1572,6 +2279,20 @@ impl SslContextBuilder {
         }
     }
 
+    /// Sets the Encrypted ClientHello (ECH) configurations and keys a server uses to decrypt
+    /// ClientHellos, replacing any previously set.
+    ///
+    /// Replacing the set, for example to rotate keys, does not affect connections already
+    /// created from the context.
+    ///
+    /// This corresponds to [`SSL_CTX_set1_ech_keys`].
+    ///
+    /// [`SSL_CTX_set1_ech_keys`]: https://commondatastorage.googleapis.com/chromium-boringssl-docs/ssl.h.html#SSL_CTX_set1_ech_keys
+    #[cfg(not(feature = "fips"))]
+    pub fn set_ech_keys(&mut self, keys: &SslEchKeysRef) -> Result<(), ErrorStack> {
+        unsafe { cvt(ffi::SSL_CTX_set1_ech_keys(self.as_ptr(), keys.as_ptr())).map(|_| ()) }
+    }
+
     /// Consumes the builder, returning a new `SslContext`.
     pub fn build(self) -> SslContext {
         self.0
@@ -1760,20 +2481,84 @@ impl SslContextRef {
         unsafe { ffi::SSL_CTX_sess_get_cache_size(self.as_ptr()).into() }
     }
 
-    /// Returns the verify mode that was set on this context from [`SslContextBuilder::set_verify`].
+    /// Returns the number of sessions currently stored in the context's internal session cache.
     ///
-    /// This corresponds to [`SSL_CTX_get_verify_mode`].
+    /// BoringSSL does not maintain OpenSSL's other session cache statistics, such as the number of
+    /// hits and misses; `SSL_CTX_sess_hits` and friends always return zero. Resumption rates can
+    /// be tracked with [`SslRef::session_reused`] instead.
     ///
-    /// [`SslContextBuilder::set_verify`]: struct.SslContextBuilder.html#method.set_verify
-    /// [`SSL_CTX_get_verify_mode`]: https://www.openssl.org/docs/man1.1.1/man3/SSL_CTX_get_verify_mode.html
-    pub fn verify_mode(&self) -> SslVerifyMode {
-        let mode = unsafe { ffi::SSL_CTX_get_verify_mode(self.as_ptr()) };
-        SslVerifyMode::from_bits(mode).expect("SSL_CTX_get_verify_mode returned invalid mode")
+    /// This corresponds to [`SSL_CTX_sess_number`].
+    ///
+    /// [`SslRef::session_reused`]: struct.SslRef.html#method.session_reused
+    /// [`SSL_CTX_sess_number`]: https://commondatastorage.googleapis.com/chromium-boringssl-docs/ssl.h.html#SSL_CTX_sess_number
+    pub fn session_cache_len(&self) -> usize {
+        unsafe { ffi::SSL_CTX_sess_number(self.as_ptr()) as usize }
     }
-}
 
-/// Information about the state of a cipher.
-pub struct CipherBits {
+    /// Returns the cipher suites the context enables for `version`, in order of preference.
+    ///
+    /// For TLS 1.2 and earlier, these are the suites of the cipher list configured with
+    /// [`set_cipher_list`] which can be used with `version`. BoringSSL does not allow the TLS 1.3
+    /// cipher suites to be configured, so all of them are returned for [`SslVersion::TLS1_3`].
+    ///
+    /// This corresponds to [`SSL_CTX_get_ciphers`].
+    ///
+    /// [`set_cipher_list`]: struct.SslContextBuilder.html#method.set_cipher_list
+    /// [`SslVersion::TLS1_3`]: struct.SslVersion.html#associatedconstant.TLS1_3
+    /// [`SSL_CTX_get_ciphers`]: https://commondatastorage.googleapis.com/chromium-boringssl-docs/ssl.h.html#SSL_CTX_get_ciphers
+    pub fn available_ciphers(&self, version: SslVersion) -> Vec<&SslCipherRef> {
+        let supports = |cipher: &&SslCipherRef| {
+            cipher.min_version().0 <= version.0 && version.0 <= cipher.max_version().0
+        };
+
+        if version == SslVersion::TLS1_3 {
+            return all_ciphers().into_iter().filter(supports).collect();
+        }
+
+        unsafe {
+            let ciphers = ffi::SSL_CTX_get_ciphers(self.as_ptr());
+            if ciphers.is_null() {
+                return vec![];
+            }
+            StackRef::<SslCipher>::from_ptr(ciphers)
+                .iter()
+                .filter(supports)
+                .collect()
+        }
+    }
+
+    /// Returns the lifetime of sessions established by the context.
+    ///
+    /// This corresponds to [`SSL_CTX_get_timeout`].
+    ///
+    /// [`SSL_CTX_get_timeout`]: https://commondatastorage.googleapis.com/chromium-boringssl-docs/ssl.h.html#SSL_CTX_get_timeout
+    pub fn session_timeout(&self) -> Duration {
+        unsafe { Duration::from_secs(ffi::SSL_CTX_get_timeout(self.as_ptr()).into()) }
+    }
+
+    /// Returns whether connections created from this context shut down quietly.
+    ///
+    /// This corresponds to [`SSL_CTX_get_quiet_shutdown`].
+    ///
+    /// [`SSL_CTX_get_quiet_shutdown`]: https://commondatastorage.googleapis.com/chromium-boringssl-docs/ssl.h.html#SSL_CTX_get_quiet_shutdown
+    pub fn quiet_shutdown(&self) -> bool {
+        unsafe { ffi::SSL_CTX_get_quiet_shutdown(self.as_ptr()) != 0 }
+    }
+
+    /// Returns the verify mode that was set on this context from [`SslContextBuilder::set_verify`].
+    ///
+    /// This corresponds to [`SSL_CTX_get_verify_mode`].
+    ///
+    /// [`SslContextBuilder::set_verify`]: struct.SslContextBuilder.html#method.set_verify
+    /// [`SSL_CTX_get_verify_mode`]: https://www.openssl.org/docs/man1.1.1/man3/SSL_CTX_get_verify_mode.html
+    pub fn verify_mode(&self) -> SslVerifyMode {
+        let mode = unsafe { ffi::SSL_CTX_get_verify_mode(self.as_ptr()) };
+        SslVerifyMode::from_bits(mode).expect("SSL_CTX_get_verify_mode returned invalid mode")
+    }
+}
+
+/// Information about the state of a cipher.
+pub struct CipherBits {
     /// The number of secret bits used for the cipher.
     pub secret: i32,
 
@@ -1803,7 +2588,8 @@ impl ClientHello {
         }
     }
 
-    fn ssl(&self) -> &SslRef {
+    /// Returns the connection on which the ClientHello was received.
+    pub fn ssl(&self) -> &SslRef {
         unsafe { SslRef::from_ptr(self.0.ssl) }
     }
 
@@ -1855,6 +2641,10 @@ impl DerefMut for SslCipher {
     }
 }
 
+impl Stackable for SslCipher {
+    type StackType = ffi::stack_st_SSL_CIPHER;
+}
+
 /// Reference to an [`SslCipher`].
 ///
 /// [`SslCipher`]: struct.SslCipher.html
@@ -1951,6 +2741,46 @@ impl SslCipherRef {
             Some(Nid::from_raw(n))
         }
     }
+
+    /// Returns the minimum protocol version the cipher can be used with.
+    ///
+    /// This corresponds to [`SSL_CIPHER_get_min_version`].
+    ///
+    /// [`SSL_CIPHER_get_min_version`]: https://commondatastorage.googleapis.com/chromium-boringssl-docs/ssl.h.html#SSL_CIPHER_get_min_version
+    pub fn min_version(&self) -> SslVersion {
+        unsafe { SslVersion(ffi::SSL_CIPHER_get_min_version(self.as_ptr())) }
+    }
+
+    /// Returns the maximum protocol version the cipher can be used with.
+    ///
+    /// This corresponds to [`SSL_CIPHER_get_max_version`].
+    ///
+    /// [`SSL_CIPHER_get_max_version`]: https://commondatastorage.googleapis.com/chromium-boringssl-docs/ssl.h.html#SSL_CIPHER_get_max_version
+    pub fn max_version(&self) -> SslVersion {
+        unsafe { SslVersion(ffi::SSL_CIPHER_get_max_version(self.as_ptr())) }
+    }
+}
+
+/// Returns every cipher suite supported by BoringSSL, ordered by their IANA value.
+///
+/// Whether a suite is enabled on a given context is reported by
+/// [`SslContextRef::available_ciphers`].
+///
+/// This corresponds to [`SSL_get_cipher_by_value`].
+///
+/// [`SslContextRef::available_ciphers`]: struct.SslContextRef.html#method.available_ciphers
+/// [`SSL_get_cipher_by_value`]: https://commondatastorage.googleapis.com/chromium-boringssl-docs/ssl.h.html#SSL_get_cipher_by_value
+pub fn all_ciphers() -> Vec<&'static SslCipherRef> {
+    (0..=u16::max_value())
+        .filter_map(|value| unsafe {
+            let ptr = ffi::SSL_get_cipher_by_value(value);
+            if ptr.is_null() {
+                None
+            } else {
+                Some(SslCipherRef::from_ptr(ptr as *mut _))
+            }
+        })
+        .collect()
 }
 
 foreign_type_and_impl_send_sync! {
@@ -1970,16 +2800,33 @@ impl Clone for SslSession {
 }
 
 impl SslSession {
-    from_der! {
-        /// Deserializes a DER-encoded session structure.
-        ///
-        /// This corresponds to [`d2i_SSL_SESSION`].
-        ///
-        /// [`d2i_SSL_SESSION`]: https://www.openssl.org/docs/man1.0.2/ssl/d2i_SSL_SESSION.html
-        from_der,
-        SslSession,
-        ffi::d2i_SSL_SESSION,
-        ::libc::c_long
+    /// Deserializes a DER-encoded session structure.
+    ///
+    /// The encoding is specific to BoringSSL and may change between BoringSSL versions.
+    /// BoringSSL checks the encoding's version and contents, so a session serialized by an
+    /// incompatible version is rejected with an error rather than misinterpreted. Data following
+    /// the encoded session is rejected as well.
+    ///
+    /// This corresponds to [`d2i_SSL_SESSION`].
+    ///
+    /// [`d2i_SSL_SESSION`]: https://www.openssl.org/docs/man1.0.2/ssl/d2i_SSL_SESSION.html
+    pub fn from_der(der: &[u8]) -> Result<SslSession, ErrorStack> {
+        unsafe {
+            ffi::init();
+            ErrorStack::clear();
+            let len = cmp::min(der.len(), c_long::max_value() as usize) as c_long;
+            let mut ptr = der.as_ptr();
+            let session = cvt_p(ffi::d2i_SSL_SESSION(ptr::null_mut(), &mut ptr, len))
+                .map(|p| SslSession::from_ptr(p))?;
+            // `d2i_SSL_SESSION` stops after the session, so check that nothing follows it.
+            if ptr != der.as_ptr().add(der.len()) {
+                return Err(put_error(
+                    ffi::ERR_LIB_SSL as c_int,
+                    ffi::SSL_R_INVALID_SSL_SESSION as c_int,
+                ));
+            }
+            Ok(session)
+        }
     }
 }
 
@@ -2062,17 +2909,181 @@ impl SslSessionRef {
         }
     }
 
+    /// Returns the maximum amount of early data, in bytes, the server accepts when this session
+    /// is resumed.
+    ///
+    /// This is zero unless the session was issued by a TLS 1.3 server with early data enabled.
+    /// BoringSSL servers do not make the limit configurable and always advertise 14336 bytes.
+    ///
+    /// This corresponds to [`SSL_SESSION_get_max_early_data`].
+    ///
+    /// [`SSL_SESSION_get_max_early_data`]: https://commondatastorage.googleapis.com/chromium-boringssl-docs/ssl.h.html#SSL_SESSION_get_max_early_data
+    pub fn max_early_data(&self) -> u32 {
+        unsafe { ffi::SSL_SESSION_get_max_early_data(self.as_ptr()) }
+    }
+
     to_der! {
         /// Serializes the session into a DER-encoded structure.
         ///
+        /// The encoding is specific to BoringSSL, so sessions stored externally should be
+        /// discarded when they fail to deserialize with [`SslSession::from_der`].
+        ///
         /// This corresponds to [`i2d_SSL_SESSION`].
         ///
+        /// [`SslSession::from_der`]: struct.SslSession.html#method.from_der
         /// [`i2d_SSL_SESSION`]: https://www.openssl.org/docs/man1.0.2/ssl/i2d_SSL_SESSION.html
         to_der,
         ffi::i2d_SSL_SESSION
     }
 }
 
+#[cfg(not(feature = "fips"))]
+foreign_type_and_impl_send_sync! {
+    type CType = ffi::SSL_ECH_KEYS;
+    fn drop = ffi::SSL_ECH_KEYS_free;
+
+    /// A builder used to construct an `SslEchKeys`.
+    pub struct SslEchKeysBuilder;
+}
+
+#[cfg(not(feature = "fips"))]
+impl SslEchKeysBuilder {
+    /// Returns a builder for an initially empty set of ECH keys.
+    ///
+    /// This corresponds to [`SSL_ECH_KEYS_new`].
+    ///
+    /// [`SSL_ECH_KEYS_new`]: https://commondatastorage.googleapis.com/chromium-boringssl-docs/ssl.h.html#SSL_ECH_KEYS_new
+    pub fn new() -> Result<SslEchKeysBuilder, ErrorStack> {
+        unsafe {
+            ffi::init();
+            cvt_p(ffi::SSL_ECH_KEYS_new()).map(|p| SslEchKeysBuilder::from_ptr(p))
+        }
+    }
+
+    /// Constructs the `SslEchKeys`.
+    pub fn build(self) -> SslEchKeys {
+        let keys = SslEchKeys(self.0);
+        mem::forget(self);
+        keys
+    }
+}
+
+#[cfg(not(feature = "fips"))]
+impl SslEchKeysBuilderRef {
+    /// Adds a serialized `ECHConfig` and the private key it was created for.
+    ///
+    /// A server may hold several configurations at once, for example while rotating keys, and
+    /// decrypts ClientHellos encrypted to any of them. Configurations added with
+    /// `is_retry_config` are sent to clients whose ClientHello could not be decrypted, so they
+    /// can retry; at least one configuration should be a retry configuration.
+    ///
+    /// This corresponds to [`SSL_ECH_KEYS_add`].
+    ///
+    /// [`SSL_ECH_KEYS_add`]: https://commondatastorage.googleapis.com/chromium-boringssl-docs/ssl.h.html#SSL_ECH_KEYS_add
+    pub fn add_key(
+        &mut self,
+        is_retry_config: bool,
+        ech_config: &[u8],
+        key: &HpkeKeyRef,
+    ) -> Result<(), ErrorStack> {
+        unsafe {
+            cvt(ffi::SSL_ECH_KEYS_add(
+                self.as_ptr(),
+                is_retry_config as c_int,
+                ech_config.as_ptr(),
+                ech_config.len(),
+                key.as_ptr(),
+            ))
+            .map(|_| ())
+        }
+    }
+}
+
+#[cfg(not(feature = "fips"))]
+foreign_type_and_impl_send_sync! {
+    type CType = ffi::SSL_ECH_KEYS;
+    fn drop = ffi::SSL_ECH_KEYS_free;
+
+    /// An immutable set of Encrypted ClientHello (ECH) configurations and their private keys,
+    /// used by a server to decrypt ClientHellos.
+    ///
+    /// The set is built with an [`SslEchKeysBuilder`] and installed with
+    /// [`SslContextBuilder::set_ech_keys`].
+    ///
+    /// [`SslEchKeysBuilder`]: struct.SslEchKeysBuilder.html
+    /// [`SslContextBuilder::set_ech_keys`]: struct.SslContextBuilder.html#method.set_ech_keys
+    pub struct SslEchKeys;
+}
+
+#[cfg(not(feature = "fips"))]
+impl SslEchKeys {
+    /// Returns a builder for a set of ECH keys.
+    pub fn builder() -> Result<SslEchKeysBuilder, ErrorStack> {
+        SslEchKeysBuilder::new()
+    }
+}
+
+#[cfg(not(feature = "fips"))]
+impl SslEchKeysRef {
+    /// Returns the `ECHConfigList` of the retry configurations, which is also the value to
+    /// publish in DNS for clients.
+    ///
+    /// This corresponds to [`SSL_ECH_KEYS_marshal_retry_configs`].
+    ///
+    /// [`SSL_ECH_KEYS_marshal_retry_configs`]: https://commondatastorage.googleapis.com/chromium-boringssl-docs/ssl.h.html#SSL_ECH_KEYS_marshal_retry_configs
+    pub fn marshal_retry_configs(&self) -> Result<Vec<u8>, ErrorStack> {
+        unsafe {
+            let mut out = ptr::null_mut();
+            let mut out_len = 0;
+            cvt(ffi::SSL_ECH_KEYS_marshal_retry_configs(
+                self.as_ptr(),
+                &mut out,
+                &mut out_len,
+            ))?;
+            let list = slice::from_raw_parts(out, out_len).to_vec();
+            ffi::OPENSSL_free(out as *mut _);
+            Ok(list)
+        }
+    }
+}
+
+/// Serializes an `ECHConfig` for `key`, to be added to an [`SslEchKeysBuilder`].
+///
+/// `config_id` identifies the configuration to the server and should differ between the
+/// configurations it holds. `public_name` is the name sent in the outer ClientHello, for which
+/// the server must be able to authenticate when ECH is rejected. `max_name_len` is the length of
+/// the longest name the server expects clients to encrypt, or zero if unknown, and is used to pad
+/// ClientHellos so their length does not reveal the name.
+///
+/// This corresponds to [`SSL_marshal_ech_config`].
+///
+/// [`SslEchKeysBuilder`]: struct.SslEchKeysBuilder.html
+/// [`SSL_marshal_ech_config`]: https://commondatastorage.googleapis.com/chromium-boringssl-docs/ssl.h.html#SSL_marshal_ech_config
+#[cfg(not(feature = "fips"))]
+pub fn marshal_ech_config(
+    config_id: u8,
+    key: &HpkeKeyRef,
+    public_name: &str,
+    max_name_len: usize,
+) -> Result<Vec<u8>, ErrorStack> {
+    let public_name = CString::new(public_name).unwrap();
+    unsafe {
+        let mut out = ptr::null_mut();
+        let mut out_len = 0;
+        cvt(ffi::SSL_marshal_ech_config(
+            &mut out,
+            &mut out_len,
+            config_id,
+            key.as_ptr(),
+            public_name.as_ptr(),
+            max_name_len,
+        ))?;
+        let config = slice::from_raw_parts(out, out_len).to_vec();
+        ffi::OPENSSL_free(out as *mut _);
+        Ok(config)
+    }
+}
+
 foreign_type_and_impl_send_sync! {
     type CType = ffi::SSL;
     fn drop = ffi::SSL_free;
@@ -2193,11 +3204,19 @@ impl SslRef {
     }
 
     fn read(&mut self, buf: &mut [u8]) -> c_int {
+        ErrorStack::clear();
         let len = cmp::min(c_int::max_value() as usize, buf.len()) as c_int;
         unsafe { ffi::SSL_read(self.as_ptr(), buf.as_ptr() as *mut c_void, len) }
     }
 
+    fn peek(&mut self, buf: &mut [u8]) -> c_int {
+        ErrorStack::clear();
+        let len = cmp::min(c_int::max_value() as usize, buf.len()) as c_int;
+        unsafe { ffi::SSL_peek(self.as_ptr(), buf.as_ptr() as *mut c_void, len) }
+    }
+
     fn write(&mut self, buf: &[u8]) -> c_int {
+        ErrorStack::clear();
         let len = cmp::min(c_int::max_value() as usize, buf.len()) as c_int;
         unsafe { ffi::SSL_write(self.as_ptr(), buf.as_ptr() as *const c_void, len) }
     }
@@ -2226,6 +3245,58 @@ impl SslRef {
         SslVerifyMode::from_bits(mode).expect("SSL_get_verify_mode returned invalid mode")
     }
 
+    /// Like [`SslContextBuilder::set_verify_cert_store`].
+    ///
+    /// This overrides the context's store for this connection only, which allows trust roots to
+    /// be rotated without rebuilding the context.
+    ///
+    /// This corresponds to [`SSL_set0_verify_cert_store`].
+    ///
+    /// [`SslContextBuilder::set_verify_cert_store`]: struct.SslContextBuilder.html#method.set_verify_cert_store
+    /// [`SSL_set0_verify_cert_store`]: https://www.openssl.org/docs/man1.0.2/ssl/SSL_set0_verify_cert_store.html
+    pub fn set_verify_cert_store(&mut self, cert_store: X509Store) -> Result<(), ErrorStack> {
+        unsafe {
+            let ptr = cert_store.as_ptr();
+            cvt(ffi::SSL_set0_verify_cert_store(self.as_ptr(), ptr) as c_int)?;
+            mem::forget(cert_store);
+
+            Ok(())
+        }
+    }
+
+    /// Like [`SslContextBuilder::set_options`].
+    ///
+    /// This corresponds to [`SSL_set_options`].
+    ///
+    /// [`SslContextBuilder::set_options`]: struct.SslContextBuilder.html#method.set_options
+    /// [`SSL_set_options`]: https://www.openssl.org/docs/manmaster/man3/SSL_set_options.html
+    pub fn set_options(&mut self, option: SslOptions) -> SslOptions {
+        let bits = unsafe { ffi::SSL_set_options(self.as_ptr(), option.bits()) };
+        SslOptions { bits }
+    }
+
+    /// Like [`SslContextBuilder::options`].
+    ///
+    /// This corresponds to [`SSL_get_options`].
+    ///
+    /// [`SslContextBuilder::options`]: struct.SslContextBuilder.html#method.options
+    /// [`SSL_get_options`]: https://www.openssl.org/docs/manmaster/man3/SSL_get_options.html
+    pub fn options(&self) -> SslOptions {
+        let bits = unsafe { ffi::SSL_get_options(self.as_ptr()) };
+        SslOptions { bits }
+    }
+
+    /// Like [`SslContextBuilder::clear_options`].
+    ///
+    /// This corresponds to [`SSL_clear_options`].
+    ///
+    /// [`SslContextBuilder::clear_options`]: struct.SslContextBuilder.html#method.clear_options
+    /// [`SSL_clear_options`]: https://www.openssl.org/docs/manmaster/man3/SSL_clear_options.html
+    pub fn clear_options(&mut self, option: SslOptions) -> SslOptions {
+        let bits = unsafe { ffi::SSL_clear_options(self.as_ptr(), option.bits()) };
+        SslOptions { bits }
+    }
+
     /// Like [`SslContextBuilder::set_verify_callback`].
     ///
     /// This corresponds to [`SSL_set_verify`].
@@ -2285,6 +3356,40 @@ impl SslRef {
         }
     }
 
+    /// Like [`SslContextBuilder::set_alpn_protocols`].
+    ///
+    /// This corresponds to [`SSL_set_alpn_protos`].
+    ///
+    /// [`SslContextBuilder::set_alpn_protocols`]: struct.SslContextBuilder.html#method.set_alpn_protocols
+    /// [`SSL_set_alpn_protos`]: https://www.openssl.org/docs/man1.1.0/ssl/SSL_set_alpn_protos.html
+    pub fn set_alpn_protocols(&mut self, protocols: &[&[u8]]) -> Result<(), ErrorStack> {
+        let wire = alpn_wire_format(protocols)?;
+        self.set_alpn_protos(&wire)
+    }
+
+    /// Returns the name of the group negotiated for the key exchange, such as `"P-256"` or
+    /// `"X25519"`, if any.
+    ///
+    /// This corresponds to [`SSL_get_curve_id`] and [`SSL_get_curve_name`].
+    ///
+    /// [`SSL_get_curve_id`]: https://commondatastorage.googleapis.com/chromium-boringssl-docs/ssl.h.html#SSL_get_curve_id
+    /// [`SSL_get_curve_name`]: https://commondatastorage.googleapis.com/chromium-boringssl-docs/ssl.h.html#SSL_get_curve_name
+    pub fn curve_name(&self) -> Option<&'static str> {
+        unsafe {
+            let id = ffi::SSL_get_curve_id(self.as_ptr());
+            if id == 0 {
+                return None;
+            }
+
+            let name = ffi::SSL_get_curve_name(id);
+            if name.is_null() {
+                None
+            } else {
+                CStr::from_ptr(name).to_str().ok()
+            }
+        }
+    }
+
     /// Returns the current cipher if the session is active.
     ///
     /// This corresponds to [`SSL_get_current_cipher`].
@@ -2304,8 +3409,13 @@ impl SslRef {
 
     /// Returns a short string describing the state of the session.
     ///
+    /// BoringSSL always returns the placeholder `"!!!!!!"`. Use [`state_string_long`] to find
+    /// where a handshake is stuck.
+    ///
     /// This corresponds to [`SSL_state_string`].
     ///
+    /// [`state_string_long`]: #method.state_string_long
+    ///
     /// [`SSL_state_string`]: https://www.openssl.org/docs/man1.0.2/ssl/SSL_state_string.html
     pub fn state_string(&self) -> &'static str {
         let state = unsafe {
@@ -2330,6 +3440,78 @@ impl SslRef {
         str::from_utf8(state.to_bytes()).unwrap()
     }
 
+    /// Returns how long the most recently completed handshake took.
+    ///
+    /// Returns `None` unless [`SslContextBuilder::enable_handshake_metrics`] was called on the
+    /// context, or if no handshake has completed yet.
+    ///
+    /// [`SslContextBuilder::enable_handshake_metrics`]: struct.SslContextBuilder.html#method.enable_handshake_metrics
+    pub fn handshake_duration(&self) -> Option<Duration> {
+        let times = self.ex_data(Ssl::cached_ex_index::<HandshakeTimes>())?;
+        match (times.start, times.end) {
+            (Some(start), Some(end)) => Some(end.duration_since(start)),
+            _ => None,
+        }
+    }
+
+    /// Returns the number of bytes read from the connection's read `BIO`, including handshake
+    /// messages and record overhead.
+    ///
+    /// Returns 0 if no `BIO` is attached.
+    ///
+    /// This corresponds to [`BIO_number_read`] on [`SSL_get_rbio`].
+    ///
+    /// [`BIO_number_read`]: https://commondatastorage.googleapis.com/chromium-boringssl-docs/bio.h.html#BIO_number_read
+    /// [`SSL_get_rbio`]: https://commondatastorage.googleapis.com/chromium-boringssl-docs/ssl.h.html#SSL_get_rbio
+    pub fn record_bytes_read(&self) -> u64 {
+        unsafe {
+            let bio = ffi::SSL_get_rbio(self.as_ptr());
+            if bio.is_null() {
+                0
+            } else {
+                ffi::BIO_number_read(bio) as u64
+            }
+        }
+    }
+
+    /// Returns the number of bytes written to the connection's write `BIO`, including handshake
+    /// messages and record overhead.
+    ///
+    /// Returns 0 if no `BIO` is attached.
+    ///
+    /// This corresponds to [`BIO_number_written`] on [`SSL_get_wbio`].
+    ///
+    /// [`BIO_number_written`]: https://commondatastorage.googleapis.com/chromium-boringssl-docs/bio.h.html#BIO_number_written
+    /// [`SSL_get_wbio`]: https://commondatastorage.googleapis.com/chromium-boringssl-docs/ssl.h.html#SSL_get_wbio
+    pub fn record_bytes_written(&self) -> u64 {
+        unsafe {
+            let bio = ffi::SSL_get_wbio(self.as_ptr());
+            if bio.is_null() {
+                0
+            } else {
+                ffi::BIO_number_written(bio) as u64
+            }
+        }
+    }
+
+    /// Sets the PSK identity hint sent by a TLS-PSK server on this connection.
+    ///
+    /// See [`SslContextBuilder::set_psk_identity_hint`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `hint` contains a NUL byte.
+    ///
+    /// This corresponds to [`SSL_use_psk_identity_hint`].
+    ///
+    /// [`SslContextBuilder::set_psk_identity_hint`]: struct.SslContextBuilder.html#method.set_psk_identity_hint
+    /// [`SSL_use_psk_identity_hint`]: https://commondatastorage.googleapis.com/chromium-boringssl-docs/ssl.h.html#SSL_use_psk_identity_hint
+    #[cfg(not(osslconf = "OPENSSL_NO_PSK"))]
+    pub fn set_psk_identity_hint(&mut self, hint: &[u8]) -> Result<(), ErrorStack> {
+        let hint = CString::new(hint).unwrap();
+        unsafe { cvt(ffi::SSL_use_psk_identity_hint(self.as_ptr(), hint.as_ptr())).map(|_| ()) }
+    }
+
     /// Sets the host name to be sent to the server for Server Name Indication (SNI).
     ///
     /// It has no effect for a server-side connection.
@@ -2345,10 +3527,65 @@ impl SslRef {
         }
     }
 
+    /// Sets the leaf certificate for this connection.
+    ///
+    /// This corresponds to [`SSL_use_certificate`].
+    ///
+    /// [`SSL_use_certificate`]: https://commondatastorage.googleapis.com/chromium-boringssl-docs/ssl.h.html#SSL_use_certificate
+    pub fn set_certificate(&mut self, cert: &X509Ref) -> Result<(), ErrorStack> {
+        unsafe { cvt(ffi::SSL_use_certificate(self.as_ptr(), cert.as_ptr())).map(|_| ()) }
+    }
+
+    /// Sets the certificates following the leaf in the chain sent for this connection.
+    ///
+    /// This corresponds to [`SSL_set1_chain`].
+    ///
+    /// [`SSL_set1_chain`]: https://www.openssl.org/docs/man1.1.0/ssl/SSL_set1_chain.html
+    pub fn set_chain(&mut self, chain: &StackRef<X509>) -> Result<(), ErrorStack> {
+        unsafe { cvt(ffi::SSL_set1_chain(self.as_ptr(), chain.as_ptr()) as c_int).map(|_| ()) }
+    }
+
+    /// Sets the private key for this connection.
+    ///
+    /// This corresponds to [`SSL_use_PrivateKey`].
+    ///
+    /// [`SSL_use_PrivateKey`]: https://commondatastorage.googleapis.com/chromium-boringssl-docs/ssl.h.html#SSL_use_PrivateKey
+    pub fn set_private_key<T>(&mut self, key: &PKeyRef<T>) -> Result<(), ErrorStack>
+    where
+        T: HasPrivate,
+    {
+        unsafe { cvt(ffi::SSL_use_PrivateKey(self.as_ptr(), key.as_ptr())).map(|_| ()) }
+    }
+
+    /// Returns the signature algorithms the peer is able to verify.
+    ///
+    /// On a server, these are the algorithms offered in the ClientHello, which makes this useful
+    /// to choose a certificate from a callback set with
+    /// [`SslContextBuilder::set_cert_callback`]. The list is empty outside of the handshake.
+    ///
+    /// This corresponds to [`SSL_get0_peer_verify_algorithms`].
+    ///
+    /// [`SslContextBuilder::set_cert_callback`]: struct.SslContextBuilder.html#method.set_cert_callback
+    /// [`SSL_get0_peer_verify_algorithms`]: https://commondatastorage.googleapis.com/chromium-boringssl-docs/ssl.h.html#SSL_get0_peer_verify_algorithms
+    pub fn peer_verify_algorithms(&self) -> &[SslSignatureAlgorithm] {
+        unsafe {
+            let mut ptr = ptr::null();
+            let len = ffi::SSL_get0_peer_verify_algorithms(self.as_ptr(), &mut ptr);
+            if ptr.is_null() || len == 0 {
+                return &[];
+            }
+            slice::from_raw_parts(ptr as *const SslSignatureAlgorithm, len)
+        }
+    }
+
     /// Returns the peer's certificate, if present.
     ///
+    /// This is the peer's end-entity certificate on both the client and the server side, unlike
+    /// [`peer_cert_chain`].
+    ///
     /// This corresponds to [`SSL_get_peer_certificate`].
     ///
+    /// [`peer_cert_chain`]: #method.peer_cert_chain
     /// [`SSL_get_peer_certificate`]: https://www.openssl.org/docs/man1.1.0/ssl/SSL_get_peer_certificate.html
     pub fn peer_certificate(&self) -> Option<X509> {
         unsafe {
@@ -2364,10 +3601,11 @@ impl SslRef {
     /// Returns the certificate chain of the peer, if present.
     ///
     /// On the client side, the chain includes the leaf certificate, but on the server side it does
-    /// not. Fun!
+    /// not. Fun! Use [`peer_certificate`] to get the leaf consistently.
     ///
     /// This corresponds to [`SSL_get_peer_cert_chain`].
     ///
+    /// [`peer_certificate`]: #method.peer_certificate
     /// [`SSL_get_peer_cert_chain`]: https://www.openssl.org/docs/man1.1.0/ssl/SSL_get_peer_cert_chain.html
     pub fn peer_cert_chain(&self) -> Option<&StackRef<X509>> {
         unsafe {
@@ -2531,10 +3769,13 @@ impl SslRef {
     /// Returns the number of bytes remaining in the currently processed TLS record.
     ///
     /// If this is greater than 0, the next call to `read` will not call down to the underlying
-    /// stream.
+    /// stream. Data already moved into an `SslStream`'s [`BufRead`] buffer is not included; use
+    /// [`SslStream::pending`] to account for it.
     ///
     /// This corresponds to [`SSL_pending`].
     ///
+    /// [`BufRead`]: https://doc.rust-lang.org/std/io/trait.BufRead.html
+    /// [`SslStream::pending`]: struct.SslStream.html#method.pending
     /// [`SSL_pending`]: https://www.openssl.org/docs/man1.1.0/ssl/SSL_pending.html
     pub fn pending(&self) -> usize {
         unsafe { ffi::SSL_pending(self.as_ptr()) as usize }
@@ -2612,6 +3853,44 @@ impl SslRef {
         unsafe { X509VerifyParamRef::from_ptr_mut(ffi::SSL_get0_param(self.as_ptr())) }
     }
 
+    /// Sets the expected DNS hostname of the peer's certificate, replacing any previously set.
+    ///
+    /// BoringSSL has no `SSL_set1_host`; like OpenSSL's function of that name, this sets the host
+    /// on the connection's [`param_mut`].
+    ///
+    /// This corresponds to [`X509_VERIFY_PARAM_set1_host`].
+    ///
+    /// [`param_mut`]: #method.param_mut
+    /// [`X509_VERIFY_PARAM_set1_host`]: https://www.openssl.org/docs/man1.1.0/crypto/X509_VERIFY_PARAM_set1_host.html
+    pub fn set_host(&mut self, host: &str) -> Result<(), ErrorStack> {
+        self.param_mut().set_host(host)
+    }
+
+    /// Adds an additional expected DNS hostname of the peer's certificate.
+    ///
+    /// The certificate is accepted if it matches any of the names set by [`set_host`] and
+    /// `add_host`, which is useful for a server known by several names, such as a short name and
+    /// its fully qualified form.
+    ///
+    /// This corresponds to [`X509_VERIFY_PARAM_add1_host`].
+    ///
+    /// [`set_host`]: #method.set_host
+    /// [`X509_VERIFY_PARAM_add1_host`]: https://www.openssl.org/docs/man1.1.0/crypto/X509_VERIFY_PARAM_set1_host.html
+    pub fn add_host(&mut self, host: &str) -> Result<(), ErrorStack> {
+        self.param_mut().add_host(host)
+    }
+
+    /// Sets the flags controlling how the names set by [`set_host`] and [`add_host`] are matched.
+    ///
+    /// This corresponds to [`X509_VERIFY_PARAM_set_hostflags`].
+    ///
+    /// [`set_host`]: #method.set_host
+    /// [`add_host`]: #method.add_host
+    /// [`X509_VERIFY_PARAM_set_hostflags`]: https://www.openssl.org/docs/man1.1.0/crypto/X509_VERIFY_PARAM_set_hostflags.html
+    pub fn set_hostflags(&mut self, hostflags: X509CheckFlags) {
+        self.param_mut().set_hostflags(hostflags)
+    }
+
     /// Returns the certificate verification result.
     ///
     /// This corresponds to [`SSL_get_verify_result`].
@@ -2699,7 +3978,7 @@ impl SslRef {
     ///
     /// This should be called before the handshake to attempt to reuse a previously established
     /// session. If the server is not willing to reuse the session, a new one will be transparently
-    /// negotiated.
+    /// negotiated. Together with [`session`], this allows clients to keep their own session store.
     ///
     /// This corresponds to [`SSL_set_session`].
     ///
@@ -2708,6 +3987,7 @@ impl SslRef {
     /// The caller of this method is responsible for ensuring that the session is associated
     /// with the same `SslContext` as this `Ssl`.
     ///
+    /// [`session`]: #method.session
     /// [`SSL_set_session`]: https://www.openssl.org/docs/manmaster/man3/SSL_set_session.html
     pub unsafe fn set_session(&mut self, session: &SslSessionRef) -> Result<(), ErrorStack> {
         cvt(ffi::SSL_set_session(self.as_ptr(), session.as_ptr())).map(|_| ())
@@ -2722,6 +4002,172 @@ impl SslRef {
         unsafe { ffi::SSL_session_reused(self.as_ptr()) != 0 }
     }
 
+    /// Like [`SslContextBuilder::set_early_data_enabled`].
+    ///
+    /// This corresponds to [`SSL_set_early_data_enabled`].
+    ///
+    /// [`SslContextBuilder::set_early_data_enabled`]: struct.SslContextBuilder.html#method.set_early_data_enabled
+    /// [`SSL_set_early_data_enabled`]: https://commondatastorage.googleapis.com/chromium-boringssl-docs/ssl.h.html#SSL_set_early_data_enabled
+    pub fn set_early_data_enabled(&mut self, enabled: bool) {
+        unsafe { ffi::SSL_set_early_data_enabled(self.as_ptr(), enabled as _) }
+    }
+
+    /// Like [`SslContextBuilder::set_session_id_context`].
+    ///
+    /// Sessions, including TLS 1.3 tickets, only resume on a connection with the same session ID
+    /// context as the one they were issued on. A server using early data under several
+    /// application contexts should set a distinct value for each, so a ticket issued under one
+    /// context cannot carry early data into another: resumption across contexts, and with it the
+    /// early data, is rejected and a full handshake is performed instead.
+    ///
+    /// Contexts longer than 32 bytes are rejected with an error, as for the context-wide setter.
+    ///
+    /// This corresponds to [`SSL_set_session_id_context`].
+    ///
+    /// [`SslContextBuilder::set_session_id_context`]: struct.SslContextBuilder.html#method.set_session_id_context
+    /// [`SSL_set_session_id_context`]: https://www.openssl.org/docs/manmaster/man3/SSL_set_session_id_context.html
+    pub fn set_session_id_context(&mut self, sid_ctx: &[u8]) -> Result<(), ErrorStack> {
+        unsafe {
+            cvt(ffi::SSL_set_session_id_context(
+                self.as_ptr(),
+                sid_ctx.as_ptr(),
+                sid_ctx.len(),
+            ))
+            .map(|_| ())
+        }
+    }
+
+    /// Sets the context which scopes early data on a QUIC server.
+    ///
+    /// The context is stored in the tickets the server issues, and a QUIC connection only accepts
+    /// early data when resuming a ticket whose context matches. BoringSSL only compares it for
+    /// QUIC connections, so it has no effect on TLS over a byte stream; use
+    /// [`set_session_id_context`] to isolate early data there.
+    ///
+    /// This corresponds to [`SSL_set_quic_early_data_context`].
+    ///
+    /// [`set_session_id_context`]: #method.set_session_id_context
+    /// [`SSL_set_quic_early_data_context`]: https://commondatastorage.googleapis.com/chromium-boringssl-docs/ssl.h.html#SSL_set_quic_early_data_context
+    pub fn set_quic_early_data_context(&mut self, context: &[u8]) -> Result<(), ErrorStack> {
+        unsafe {
+            cvt(ffi::SSL_set_quic_early_data_context(
+                self.as_ptr(),
+                context.as_ptr(),
+                context.len(),
+            ))
+            .map(|_| ())
+        }
+    }
+
+    /// Determines if the client is in False Start, having sent its Finished message but not yet
+    /// received the server's.
+    ///
+    /// The handshake is completed by the next read.
+    ///
+    /// This corresponds to [`SSL_in_false_start`].
+    ///
+    /// [`SSL_in_false_start`]: https://commondatastorage.googleapis.com/chromium-boringssl-docs/ssl.h.html#SSL_in_false_start
+    pub fn in_false_start(&self) -> bool {
+        unsafe { ffi::SSL_in_false_start(self.as_ptr()) != 0 }
+    }
+
+    /// Determines if the handshake is in the early data state.
+    ///
+    /// A client in this state may write early data, and a server may read it. The handshake is
+    /// completed by the next read or write.
+    ///
+    /// This corresponds to [`SSL_in_early_data`].
+    ///
+    /// [`SSL_in_early_data`]: https://commondatastorage.googleapis.com/chromium-boringssl-docs/ssl.h.html#SSL_in_early_data
+    pub fn in_early_data(&self) -> bool {
+        unsafe { ffi::SSL_in_early_data(self.as_ptr()) != 0 }
+    }
+
+    /// Determines if early data was accepted by the server.
+    ///
+    /// This corresponds to [`SSL_early_data_accepted`].
+    ///
+    /// [`SSL_early_data_accepted`]: https://commondatastorage.googleapis.com/chromium-boringssl-docs/ssl.h.html#SSL_early_data_accepted
+    pub fn early_data_accepted(&self) -> bool {
+        unsafe { ffi::SSL_early_data_accepted(self.as_ptr()) != 0 }
+    }
+
+    /// Resets the connection after the server rejected early data, so the handshake can be
+    /// resumed without it.
+    ///
+    /// This corresponds to [`SSL_reset_early_data_reject`].
+    ///
+    /// [`SSL_reset_early_data_reject`]: https://commondatastorage.googleapis.com/chromium-boringssl-docs/ssl.h.html#SSL_reset_early_data_reject
+    pub fn reset_early_data_reject(&mut self) {
+        unsafe { ffi::SSL_reset_early_data_reject(self.as_ptr()) }
+    }
+
+    /// Configures the client to offer Encrypted ClientHello (ECH) to the server.
+    ///
+    /// `ech_config_list` is a serialized `ECHConfigList`, typically published by the server in a
+    /// DNS HTTPS record. The real ClientHello, including the name set with [`set_hostname`], is
+    /// encrypted to the server, and the outer ClientHello sent in the clear only carries the public
+    /// name of the selected configuration. If the server rejects ECH, the server is authenticated
+    /// for the public name instead and the handshake fails with the `ECH_REJECTED` reason;
+    /// [`ech_retry_configs`] then returns the configurations to retry with, if the server sent any.
+    ///
+    /// BoringSSL only supports this per connection, so clients using an [`SslConnector`] set it
+    /// on the [`ConnectConfiguration`].
+    ///
+    /// This corresponds to [`SSL_set1_ech_config_list`].
+    ///
+    /// [`set_hostname`]: #method.set_hostname
+    /// [`ech_retry_configs`]: #method.ech_retry_configs
+    /// [`SslConnector`]: struct.SslConnector.html
+    /// [`ConnectConfiguration`]: struct.ConnectConfiguration.html
+    /// [`SSL_set1_ech_config_list`]: https://commondatastorage.googleapis.com/chromium-boringssl-docs/ssl.h.html#SSL_set1_ech_config_list
+    #[cfg(not(feature = "fips"))]
+    pub fn set_ech_config_list(&mut self, ech_config_list: &[u8]) -> Result<(), ErrorStack> {
+        unsafe {
+            cvt(ffi::SSL_set1_ech_config_list(
+                self.as_ptr(),
+                ech_config_list.as_ptr(),
+                ech_config_list.len(),
+            ))
+            .map(|_| ())
+        }
+    }
+
+    /// Determines if Encrypted ClientHello was accepted, so the encrypted ClientHello was used
+    /// for the handshake.
+    ///
+    /// This corresponds to [`SSL_ech_accepted`].
+    ///
+    /// [`SSL_ech_accepted`]: https://commondatastorage.googleapis.com/chromium-boringssl-docs/ssl.h.html#SSL_ech_accepted
+    #[cfg(not(feature = "fips"))]
+    pub fn ech_accepted(&self) -> bool {
+        unsafe { ffi::SSL_ech_accepted(self.as_ptr()) != 0 }
+    }
+
+    /// Returns the `ECHConfigList` the server sent when it rejected Encrypted ClientHello.
+    ///
+    /// A client may retry the connection with [`set_ech_config_list`] and these configurations.
+    /// Returns `None` if the server did not send any, in which case the client should retry
+    /// without ECH.
+    ///
+    /// This corresponds to [`SSL_get0_ech_retry_configs`].
+    ///
+    /// [`set_ech_config_list`]: #method.set_ech_config_list
+    /// [`SSL_get0_ech_retry_configs`]: https://commondatastorage.googleapis.com/chromium-boringssl-docs/ssl.h.html#SSL_get0_ech_retry_configs
+    #[cfg(not(feature = "fips"))]
+    pub fn ech_retry_configs(&self) -> Option<&[u8]> {
+        unsafe {
+            let mut ptr = ptr::null();
+            let mut len = 0;
+            ffi::SSL_get0_ech_retry_configs(self.as_ptr(), &mut ptr, &mut len);
+            if len == 0 {
+                None
+            } else {
+                Some(slice::from_raw_parts(ptr, len))
+            }
+        }
+    }
+
     /// Sets the status response a client wishes the server to reply with.
     ///
     /// This corresponds to [`SSL_set_tlsext_status_type`].
@@ -2770,6 +4216,41 @@ impl SslRef {
         }
     }
 
+    /// Queues a TLS 1.3 KeyUpdate message, rotating the keys used to send data.
+    ///
+    /// The message is sent with the next write to the connection. KeyUpdates received from the
+    /// peer, including responses to `KeyUpdateRequest::REQUESTED`, are processed transparently by
+    /// reads. This fails if the connection did not negotiate TLS 1.3 or the handshake has not
+    /// completed.
+    ///
+    /// This corresponds to [`SSL_key_update`].
+    ///
+    /// [`SSL_key_update`]: https://commondatastorage.googleapis.com/chromium-boringssl-docs/ssl.h.html#SSL_key_update
+    pub fn key_update(&mut self, request: KeyUpdateRequest) -> Result<(), ErrorStack> {
+        unsafe { cvt(ffi::SSL_key_update(self.as_ptr(), request.0)).map(|_| ()) }
+    }
+
+    /// Configures whether this connection shuts down quietly.
+    ///
+    /// See [`SslContextBuilder::set_quiet_shutdown`] for the security implications.
+    ///
+    /// This corresponds to [`SSL_set_quiet_shutdown`].
+    ///
+    /// [`SslContextBuilder::set_quiet_shutdown`]: struct.SslContextBuilder.html#method.set_quiet_shutdown
+    /// [`SSL_set_quiet_shutdown`]: https://commondatastorage.googleapis.com/chromium-boringssl-docs/ssl.h.html#SSL_set_quiet_shutdown
+    pub fn set_quiet_shutdown(&mut self, quiet: bool) {
+        unsafe { ffi::SSL_set_quiet_shutdown(self.as_ptr(), quiet as c_int) }
+    }
+
+    /// Returns whether this connection shuts down quietly.
+    ///
+    /// This corresponds to [`SSL_get_quiet_shutdown`].
+    ///
+    /// [`SSL_get_quiet_shutdown`]: https://commondatastorage.googleapis.com/chromium-boringssl-docs/ssl.h.html#SSL_get_quiet_shutdown
+    pub fn quiet_shutdown(&self) -> bool {
+        unsafe { ffi::SSL_get_quiet_shutdown(self.as_ptr()) != 0 }
+    }
+
     /// Determines if this `Ssl` is configured for server-side or client-side use.
     ///
     /// This corresponds to [`SSL_is_server`].
@@ -2835,7 +4316,15 @@ impl SslRef {
     /// The total size of the message is returned, so this can be used to determine the size of the
     /// buffer required.
     ///
+    /// In a full TLS 1.2 handshake, the client's Finished message is the `tls-unique` channel
+    /// binding defined in [RFC 5929]. `tls-unique` is undefined for TLS 1.3; use
+    /// [`export_keying_material`] to derive a `tls-exporter` binding ([RFC 9266]) instead.
+    ///
     /// This corresponds to `SSL_get_finished`.
+    ///
+    /// [RFC 5929]: https://tools.ietf.org/html/rfc5929
+    /// [RFC 9266]: https://tools.ietf.org/html/rfc9266
+    /// [`export_keying_material`]: #method.export_keying_material
     pub fn finished(&self, buf: &mut [u8]) -> usize {
         unsafe { ffi::SSL_get_finished(self.as_ptr(), buf.as_mut_ptr() as *mut c_void, buf.len()) }
     }
@@ -2846,7 +4335,11 @@ impl SslRef {
     /// The total size of the message is returned, so this can be used to determine the size of the
     /// buffer required.
     ///
+    /// See [`finished`] for notes on `tls-unique` channel binding.
+    ///
     /// This corresponds to `SSL_get_peer_finished`.
+    ///
+    /// [`finished`]: #method.finished
     pub fn peer_finished(&self, buf: &mut [u8]) -> usize {
         unsafe {
             ffi::SSL_get_peer_finished(self.as_ptr(), buf.as_mut_ptr() as *mut c_void, buf.len())
@@ -2868,6 +4361,94 @@ impl SslRef {
     pub fn set_mtu(&mut self, mtu: u32) -> Result<(), ErrorStack> {
         unsafe { cvt(ffi::SSL_set_mtu(self.as_ptr(), mtu as c_uint) as c_int).map(|_| ()) }
     }
+
+    /// Returns the time remaining until the DTLS retransmission timer expires.
+    ///
+    /// DTLS runs over unreliable transports, so the handshake retransmits its last flight if no
+    /// response arrives in time. Event loops should wait for readability no longer than the
+    /// returned duration, then call [`dtls_handle_timeout`]. Returns `None` if no timer is
+    /// running, and a zero duration if the timer has already expired.
+    ///
+    /// This corresponds to [`DTLSv1_get_timeout`].
+    ///
+    /// [`dtls_handle_timeout`]: #method.dtls_handle_timeout
+    /// [`DTLSv1_get_timeout`]: https://commondatastorage.googleapis.com/chromium-boringssl-docs/ssl.h.html#DTLSv1_get_timeout
+    pub fn dtls_get_timeout(&self) -> Option<Duration> {
+        unsafe {
+            let mut timeout = mem::zeroed::<ffi::timeval>();
+            if ffi::DTLSv1_get_timeout(self.as_ptr(), &mut timeout) == 0 {
+                return None;
+            }
+
+            Some(
+                Duration::from_secs(timeout.tv_sec as u64)
+                    + Duration::from_micros(timeout.tv_usec as u64),
+            )
+        }
+    }
+
+    /// Retransmits the last DTLS flight if the retransmission timer has expired.
+    ///
+    /// Returns `true` if a flight was retransmitted and `false` if the timer had not expired yet.
+    /// The timeout doubles with each retransmission.
+    ///
+    /// This corresponds to [`DTLSv1_handle_timeout`].
+    ///
+    /// [`DTLSv1_handle_timeout`]: https://commondatastorage.googleapis.com/chromium-boringssl-docs/ssl.h.html#DTLSv1_handle_timeout
+    pub fn dtls_handle_timeout(&mut self) -> Result<bool, ErrorStack> {
+        unsafe { cvt_n(ffi::DTLSv1_handle_timeout(self.as_ptr())).map(|r| r != 0) }
+    }
+}
+
+/// Returns whether the server name `host` matches `pattern`, following the wildcard rules of
+/// [RFC 6125, section 6.4.3].
+///
+/// Names are compared case-insensitively and a trailing dot is ignored. The pattern may
+/// contain a single `*`, which must make up the entire left-most label and matches exactly one
+/// non-empty label of `host`. Wildcards are not allowed directly above a top-level domain, so
+/// `*.com` matches nothing.
+///
+/// # Examples
+///
+/// ```
+/// use boring::ssl::match_servername;
+///
+/// assert!(match_servername("*.example.com", "a.example.com"));
+/// assert!(!match_servername("*.example.com", "a.b.example.com"));
+/// assert!(!match_servername("*.example.com", "example.com"));
+/// ```
+///
+/// [RFC 6125, section 6.4.3]: https://tools.ietf.org/html/rfc6125#section-6.4.3
+pub fn match_servername(pattern: &str, host: &str) -> bool {
+    fn strip_root(name: &str) -> &str {
+        if name.ends_with('.') {
+            &name[..name.len() - 1]
+        } else {
+            name
+        }
+    }
+
+    let pattern = strip_root(pattern);
+    let host = strip_root(host);
+
+    if pattern.is_empty() || host.is_empty() {
+        return false;
+    }
+
+    if !pattern.starts_with("*.") {
+        return !pattern.contains('*') && pattern.eq_ignore_ascii_case(host);
+    }
+
+    let suffix = &pattern[1..];
+    // The wildcard must be the only one, and must be followed by at least two labels.
+    if suffix.contains('*') || suffix[1..].find('.').is_none() {
+        return false;
+    }
+
+    match host.find('.') {
+        Some(dot) => dot > 0 && host[dot..].eq_ignore_ascii_case(suffix),
+        None => false,
+    }
 }
 
 /// An SSL stream midway through the handshake process.
@@ -2893,6 +4474,11 @@ impl<S> MidHandshakeSslStream<S> {
         self.stream.ssl()
     }
 
+    /// Returns a mutable reference to the `Ssl` of the stream.
+    pub fn ssl_mut(&mut self) -> &mut SslRef {
+        self.stream.ssl_mut()
+    }
+
     /// Returns the underlying error which interrupted this handshake.
     pub fn error(&self) -> &Error {
         &self.error
@@ -2919,15 +4505,17 @@ impl<S> MidHandshakeSslStream<S> {
     ///
     /// [`SSL_do_handshake`]: https://www.openssl.org/docs/manmaster/man3/SSL_do_handshake.html
     pub fn handshake(mut self) -> Result<SslStream<S>, HandshakeError<S>> {
+        ErrorStack::clear();
         let ret = unsafe { ffi::SSL_do_handshake(self.stream.ssl.as_ptr()) };
         if ret > 0 {
             Ok(self.stream)
         } else {
             self.error = self.stream.make_error(ret);
             match self.error.code() {
-                ErrorCode::WANT_READ | ErrorCode::WANT_WRITE => {
-                    Err(HandshakeError::WouldBlock(self))
-                }
+                ErrorCode::WANT_READ
+                | ErrorCode::WANT_WRITE
+                | ErrorCode::PENDING_CERTIFICATE
+                | ErrorCode::WANT_X509_LOOKUP => Err(HandshakeError::WouldBlock(self)),
                 _ => Err(HandshakeError::Failure(self)),
             }
         }
@@ -2935,9 +4523,26 @@ impl<S> MidHandshakeSslStream<S> {
 }
 
 /// A TLS session over a stream.
+///
+/// BoringSSL reads from and writes to `S` directly through its own record buffers, which it
+/// releases while the connection is idle. Reads from `S` may return any number of bytes, so a
+/// stream with a small internal buffer still completes the handshake.
+///
+/// The only buffer on the Rust side belongs to the [`BufRead`] implementation: BoringSSL does not
+/// expose its decrypted record buffer, so the stream allocates one the first time `fill_buf` is
+/// called. It holds one TLS record by default, and its size can be changed with
+/// [`set_read_buffer_capacity`]. Plain reads are unaffected.
+///
+/// [`BufRead`]: https://doc.rust-lang.org/std/io/trait.BufRead.html
+/// [`set_read_buffer_capacity`]: #method.set_read_buffer_capacity
 pub struct SslStream<S> {
     ssl: ManuallyDrop<Ssl>,
     method: ManuallyDrop<BioMethod>,
+    read_buf: Vec<u8>,
+    read_pos: usize,
+    read_buf_capacity: usize,
+    bytes_read: u64,
+    bytes_written: u64,
     _p: PhantomData<S>,
 }
 
@@ -2972,6 +4577,11 @@ impl<S: Read + Write> SslStream<S> {
             SslStream {
                 ssl: ManuallyDrop::new(ssl),
                 method: ManuallyDrop::new(method),
+                read_buf: Vec::new(),
+                read_pos: 0,
+                read_buf_capacity: MAX_PLAINTEXT_LENGTH,
+                bytes_read: 0,
+                bytes_written: 0,
                 _p: PhantomData,
             }
         }
@@ -3007,7 +4617,47 @@ impl<S: Read + Write> SslStream<S> {
             return Ok(0);
         }
 
+        // Serve data buffered by `fill_buf` before reading more records.
+        let buffered = &self.read_buf[self.read_pos..];
+        if !buffered.is_empty() {
+            let len = cmp::min(buf.len(), buffered.len());
+            buf[..len].copy_from_slice(&buffered[..len]);
+            self.read_pos += len;
+            self.bytes_read += len as u64;
+            return Ok(len);
+        }
+
         let ret = self.ssl.read(buf);
+        if ret > 0 {
+            self.bytes_read += ret as u64;
+            Ok(ret as usize)
+        } else {
+            Err(self.make_error(ret))
+        }
+    }
+
+    /// Like `ssl_read`, but leaves the returned data in the stream.
+    ///
+    /// A subsequent read returns the same bytes again. This can be used to sniff the application
+    /// protocol before deciding how to handle the connection.
+    ///
+    /// This corresponds to [`SSL_peek`].
+    ///
+    /// [`SSL_peek`]: https://www.openssl.org/docs/manmaster/man3/SSL_peek.html
+    pub fn ssl_peek(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        // See `ssl_read` for why zero-length reads are short-circuited.
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        let buffered = &self.read_buf[self.read_pos..];
+        if !buffered.is_empty() {
+            let len = cmp::min(buf.len(), buffered.len());
+            buf[..len].copy_from_slice(&buffered[..len]);
+            return Ok(len);
+        }
+
+        let ret = self.ssl.peek(buf);
         if ret > 0 {
             Ok(ret as usize)
         } else {
@@ -3031,12 +4681,51 @@ impl<S: Read + Write> SslStream<S> {
 
         let ret = self.ssl.write(buf);
         if ret > 0 {
+            self.bytes_written += ret as u64;
             Ok(ret as usize)
         } else {
             Err(self.make_error(ret))
         }
     }
 
+    /// Reads early data sent by the client in a server-side 0-RTT handshake.
+    ///
+    /// Early data is only available if the context enabled it with
+    /// [`SslContextBuilder::set_early_data_enabled`] and the client resumed a session that
+    /// permits it, in which case `accept` returns while the handshake is still in the early data
+    /// state. This returns `ReadEarlyDataResult::Finished` once the handshake has left that state;
+    /// the rest of the handshake is completed by the next read or write.
+    ///
+    /// The early data may end during this call, in which case the handshake is completed and
+    /// `ReadEarlyDataResult::Finished` is returned. Any data the client sent after the handshake
+    /// is not placed in `buf`, but returned by the next read.
+    ///
+    /// This corresponds to [`SSL_read`] while [`SSL_in_early_data`] is true.
+    ///
+    /// [`SslContextBuilder::set_early_data_enabled`]: struct.SslContextBuilder.html#method.set_early_data_enabled
+    /// [`SSL_read`]: https://www.openssl.org/docs/manmaster/man3/SSL_read.html
+    /// [`SSL_in_early_data`]: https://commondatastorage.googleapis.com/chromium-boringssl-docs/ssl.h.html#SSL_in_early_data
+    pub fn read_early_data(&mut self, buf: &mut [u8]) -> Result<ReadEarlyDataResult, Error> {
+        if !self.ssl.in_early_data() {
+            return Ok(ReadEarlyDataResult::Finished);
+        }
+
+        let len = self.ssl_read(buf)?;
+        if self.ssl.in_early_data() {
+            return Ok(ReadEarlyDataResult::Success(len));
+        }
+
+        // BoringSSL completed the handshake once the early data ended and went on to read the
+        // data that followed it, which must not be mistaken for early data. Nothing was buffered
+        // by `fill_buf`, or it would have been returned without reading, so the data can be kept
+        // there for the next read.
+        self.read_buf.clear();
+        self.read_buf.extend_from_slice(&buf[..len]);
+        self.read_pos = 0;
+        self.bytes_read -= len as u64;
+        Ok(ReadEarlyDataResult::Finished)
+    }
+
     /// Shuts down the session.
     ///
     /// The shutdown process consists of two steps. The first step sends a close notify message to
@@ -3051,6 +4740,7 @@ impl<S: Read + Write> SslStream<S> {
     ///
     /// [`SSL_shutdown`]: https://www.openssl.org/docs/man1.0.2/ssl/SSL_shutdown.html
     pub fn shutdown(&mut self) -> Result<ShutdownResult, Error> {
+        ErrorStack::clear();
         match unsafe { ffi::SSL_shutdown(self.ssl.as_ptr()) } {
             0 => Ok(ShutdownResult::Sent),
             1 => Ok(ShutdownResult::Received),
@@ -3125,6 +4815,19 @@ impl<S> SslStream<S> {
     }
 
     /// Returns a shared reference to the underlying stream.
+    ///
+    /// This is a cheap pointer lookup, and can be used to reach information about the underlying
+    /// connection without unwrapping the stream:
+    ///
+    /// ```no_run
+    /// use boring::ssl::{SslConnector, SslMethod};
+    /// use std::net::TcpStream;
+    ///
+    /// let connector = SslConnector::builder(SslMethod::tls()).unwrap().build();
+    /// let stream = TcpStream::connect("google.com:443").unwrap();
+    /// let stream = connector.connect("google.com", stream).unwrap();
+    /// println!("connected to {}", stream.get_ref().peer_addr().unwrap());
+    /// ```
     pub fn get_ref(&self) -> &S {
         unsafe {
             let bio = self.ssl.get_raw_rbio();
@@ -3149,6 +4852,65 @@ impl<S> SslStream<S> {
     pub fn ssl(&self) -> &SslRef {
         &self.ssl
     }
+
+    /// Returns a mutable reference to the `Ssl` object associated with this stream.
+    pub fn ssl_mut(&mut self) -> &mut SslRef {
+        &mut self.ssl
+    }
+
+    /// Configures whether every record is written and flushed to the underlying stream on its own.
+    ///
+    /// See [`SslStreamBuilder::set_flush_on_write`] for details.
+    ///
+    /// [`SslStreamBuilder::set_flush_on_write`]: struct.SslStreamBuilder.html#method.set_flush_on_write
+    pub fn set_flush_on_write(&mut self, flush_on_write: bool) {
+        unsafe {
+            let bio = self.ssl.get_raw_rbio();
+            bio::set_flush_on_write::<S>(bio, flush_on_write);
+        }
+    }
+
+    /// Sets the size of the buffer used by the [`BufRead`] implementation.
+    ///
+    /// Defaults to the maximum plaintext size of a TLS record, 16384 bytes. Smaller buffers save
+    /// memory when many connections are open at once; records that do not fit stay buffered in
+    /// BoringSSL until they are read. A capacity of 0 is treated as 1. The new size applies the
+    /// next time the buffer is refilled.
+    ///
+    /// [`BufRead`]: https://doc.rust-lang.org/std/io/trait.BufRead.html
+    pub fn set_read_buffer_capacity(&mut self, capacity: usize) {
+        self.read_buf_capacity = cmp::max(capacity, 1);
+    }
+
+    /// Returns the number of decrypted bytes that can be read without calling down to the
+    /// underlying stream.
+    ///
+    /// Unlike [`SslRef::pending`], this includes data buffered by `fill_buf`.
+    ///
+    /// [`SslRef::pending`]: struct.SslRef.html#method.pending
+    pub fn pending(&self) -> usize {
+        self.read_buf.len() - self.read_pos + self.ssl.pending()
+    }
+
+    /// Returns the number of application data bytes read from the stream so far.
+    ///
+    /// This counts decrypted plaintext, not the records received from the underlying stream.
+    /// See [`SslRef::record_bytes_read`] for the latter.
+    ///
+    /// [`SslRef::record_bytes_read`]: struct.SslRef.html#method.record_bytes_read
+    pub fn bytes_read(&self) -> u64 {
+        self.bytes_read
+    }
+
+    /// Returns the number of application data bytes written to the stream so far.
+    ///
+    /// This counts plaintext accepted by `SSL_write`, not the records sent to the underlying
+    /// stream. See [`SslRef::record_bytes_written`] for the latter.
+    ///
+    /// [`SslRef::record_bytes_written`]: struct.SslRef.html#method.record_bytes_written
+    pub fn bytes_written(&self) -> u64 {
+        self.bytes_written
+    }
 }
 
 impl<S: Read + Write> Read for SslStream<S> {
@@ -3171,6 +4933,34 @@ impl<S: Read + Write> Read for SslStream<S> {
     }
 }
 
+/// The maximum size of a TLS record's plaintext.
+const MAX_PLAINTEXT_LENGTH: usize = 16384;
+
+impl<S: Read + Write> BufRead for SslStream<S> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        if self.read_pos >= self.read_buf.len() {
+            let mut buf = mem::take(&mut self.read_buf);
+            buf.resize(self.read_buf_capacity, 0);
+            self.read_pos = 0;
+            // Buffered bytes are only counted as read once they are consumed.
+            let bytes_read = self.bytes_read;
+            let r = self.read(&mut buf);
+            self.bytes_read = bytes_read;
+            buf.truncate(*r.as_ref().unwrap_or(&0));
+            self.read_buf = buf;
+            r?;
+        }
+
+        Ok(&self.read_buf[self.read_pos..])
+    }
+
+    fn consume(&mut self, amt: usize) {
+        let read_pos = cmp::min(self.read_pos + amt, self.read_buf.len());
+        self.bytes_read += (read_pos - self.read_pos) as u64;
+        self.read_pos = read_pos;
+    }
+}
+
 impl<S: Read + Write> Write for SslStream<S> {
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
         loop {
@@ -3228,13 +5018,17 @@ where
     /// See `Ssl::connect`
     pub fn connect(self) -> Result<SslStream<S>, HandshakeError<S>> {
         let mut stream = self.inner;
+        ErrorStack::clear();
         let ret = unsafe { ffi::SSL_connect(stream.ssl.as_ptr()) };
         if ret > 0 {
             Ok(stream)
         } else {
             let error = stream.make_error(ret);
             match error.code() {
-                ErrorCode::WANT_READ | ErrorCode::WANT_WRITE => {
+                ErrorCode::WANT_READ
+                | ErrorCode::WANT_WRITE
+                | ErrorCode::PENDING_CERTIFICATE
+                | ErrorCode::WANT_X509_LOOKUP => {
                     Err(HandshakeError::WouldBlock(MidHandshakeSslStream {
                         stream,
                         error,
@@ -3251,13 +5045,17 @@ where
     /// See `Ssl::accept`
     pub fn accept(self) -> Result<SslStream<S>, HandshakeError<S>> {
         let mut stream = self.inner;
+        ErrorStack::clear();
         let ret = unsafe { ffi::SSL_accept(stream.ssl.as_ptr()) };
         if ret > 0 {
             Ok(stream)
         } else {
             let error = stream.make_error(ret);
             match error.code() {
-                ErrorCode::WANT_READ | ErrorCode::WANT_WRITE => {
+                ErrorCode::WANT_READ
+                | ErrorCode::WANT_WRITE
+                | ErrorCode::PENDING_CERTIFICATE
+                | ErrorCode::WANT_X509_LOOKUP => {
                     Err(HandshakeError::WouldBlock(MidHandshakeSslStream {
                         stream,
                         error,
@@ -3280,13 +5078,17 @@ where
     /// [`SSL_do_handshake`]: https://www.openssl.org/docs/manmaster/man3/SSL_do_handshake.html
     pub fn handshake(self) -> Result<SslStream<S>, HandshakeError<S>> {
         let mut stream = self.inner;
+        ErrorStack::clear();
         let ret = unsafe { ffi::SSL_do_handshake(stream.ssl.as_ptr()) };
         if ret > 0 {
             Ok(stream)
         } else {
             let error = stream.make_error(ret);
             match error.code() {
-                ErrorCode::WANT_READ | ErrorCode::WANT_WRITE => {
+                ErrorCode::WANT_READ
+                | ErrorCode::WANT_WRITE
+                | ErrorCode::PENDING_CERTIFICATE
+                | ErrorCode::WANT_X509_LOOKUP => {
                     Err(HandshakeError::WouldBlock(MidHandshakeSslStream {
                         stream,
                         error,
@@ -3328,6 +5130,29 @@ impl<S> SslStreamBuilder<S> {
         &self.inner.ssl
     }
 
+    /// Configures whether every record is written and flushed to the underlying stream on its own.
+    ///
+    /// BoringSSL buffers the records of a handshake flight and writes them to the stream at once
+    /// when the flight is complete. Enabling this mode splits such writes so that every TLS
+    /// record is written and flushed on its own, letting each one reach the peer as early as
+    /// possible. This lowers latency at the cost of more, smaller writes and therefore more
+    /// syscalls. It is disabled by default, and has no effect on DTLS.
+    pub fn set_flush_on_write(&mut self, flush_on_write: bool) {
+        unsafe {
+            let bio = self.inner.ssl.get_raw_rbio();
+            bio::set_flush_on_write::<S>(bio, flush_on_write);
+        }
+    }
+
+    /// Sets the size of the buffer used by the stream's `BufRead` implementation.
+    ///
+    /// See [`SslStream::set_read_buffer_capacity`] for details.
+    ///
+    /// [`SslStream::set_read_buffer_capacity`]: struct.SslStream.html#method.set_read_buffer_capacity
+    pub fn set_read_buffer_capacity(&mut self, capacity: usize) {
+        self.inner.set_read_buffer_capacity(capacity);
+    }
+
     /// Set the DTLS MTU size.
     ///
     /// It will be ignored if the value is smaller than the minimum packet size
@@ -3344,6 +5169,16 @@ impl<S> SslStreamBuilder<S> {
     }
 }
 
+/// The result of a `read_early_data` call.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ReadEarlyDataResult {
+    /// The number of bytes of early data read.
+    Success(usize),
+
+    /// The handshake is no longer accepting early data.
+    Finished,
+}
+
 /// The result of a shutdown request.
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum ShutdownResult {
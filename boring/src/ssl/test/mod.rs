@@ -2,6 +2,7 @@
 
 use hex;
 use std::cell::Cell;
+use std::collections::{HashMap, VecDeque};
 use std::env;
 use std::fs::File;
 use std::io::prelude::*;
@@ -12,24 +13,35 @@ use std::net::UdpSocket;
 use std::net::{SocketAddr, TcpListener, TcpStream};
 use std::path::Path;
 use std::process::{Child, ChildStdin, Command, Stdio};
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
 use std::thread;
 use std::time::Duration;
 
+use crate::asn1::Asn1Time;
 use crate::dh::Dh;
+use crate::ec::EcKey;
 use crate::error::ErrorStack;
 use crate::hash::MessageDigest;
-use crate::pkey::PKey;
+#[cfg(not(feature = "fips"))]
+use crate::hpke::{HpkeKem, HpkeKey, HpkeKeyRef};
+use crate::nid::Nid;
+use crate::pkey::{Id, PKey, Private};
 use crate::srtp::SrtpProfileId;
 use crate::ssl;
 use crate::ssl::test::server::Server;
+#[cfg(not(feature = "fips"))]
+use crate::ssl::SslEchKeys;
 use crate::ssl::SslVersion;
 use crate::ssl::{
-    Error, ExtensionType, HandshakeError, MidHandshakeSslStream, ShutdownResult, ShutdownState,
-    Ssl, SslAcceptor, SslAcceptorBuilder, SslConnector, SslContext, SslContextBuilder, SslFiletype,
-    SslMethod, SslOptions, SslSessionCacheMode, SslStream, SslStreamBuilder, SslVerifyMode,
-    StatusType,
+    CertCompressionAlgorithm, Error, ErrorCode, ExtensionType, HandshakeError, KeyUpdateRequest,
+    MidHandshakeSslStream, ReadEarlyDataResult, ServerName, ShutdownResult, ShutdownState, Ssl,
+    SslAcceptor, SslAcceptorBuilder, SslAlert, SslCipherRef, SslConnector, SslContext,
+    SslContextBuilder, SslFiletype, SslMethod, SslMode, SslOptions, SslRef, SslSession,
+    SslSessionCacheMode, SslSessionRef, SslSignatureAlgorithm, SslStream, SslStreamBuilder,
+    SslVerifyMode, StatusType,
 };
+use crate::stack::Stack;
 use crate::x509::store::X509StoreBuilder;
 use crate::x509::verify::X509CheckFlags;
 use crate::x509::{X509Name, X509StoreContext, X509VerifyResult, X509};
@@ -52,6 +64,71 @@ fn verify_untrusted() {
     client.connect_err();
 }
 
+#[test]
+#[cfg(feature = "log")]
+fn info_log_target() {
+    lazy_static! {
+        static ref LINES: Mutex<Vec<(log::Level, String)>> = Mutex::new(vec![]);
+    }
+
+    struct Capture;
+
+    impl log::Log for Capture {
+        fn enabled(&self, metadata: &log::Metadata) -> bool {
+            metadata.target() == "boring-test"
+        }
+
+        fn log(&self, record: &log::Record) {
+            if self.enabled(record.metadata()) {
+                let line = record.args().to_string();
+                LINES.lock().unwrap().push((record.level(), line));
+            }
+        }
+
+        fn flush(&self) {}
+    }
+
+    static CAPTURE: Capture = Capture;
+    log::set_logger(&CAPTURE).unwrap();
+    log::set_max_level(log::LevelFilter::Debug);
+
+    let mut server = Server::builder();
+    server.should_error();
+    let server = server.build();
+
+    let mut client = server.client();
+    client.ctx().set_verify(SslVerifyMode::PEER);
+    client.ctx().set_info_log_target("boring-test");
+    client.connect_err();
+
+    {
+        let lines = LINES.lock().unwrap();
+        assert!(
+            lines
+                .iter()
+                .any(|(level, line)| *level == log::Level::Debug
+                    && line == "client handshake started")
+        );
+        assert!(lines.iter().any(|(level, line)| *level == log::Level::Warn
+            && line.starts_with("client sent fatal alert")));
+    }
+
+    // Both share the info callback, so neither may disable the other.
+    let server = Server::builder().build();
+
+    let mut client = server.client();
+    client.ctx().set_info_log_target("boring-test");
+    client.ctx().enable_handshake_metrics();
+    let s = client.connect();
+    assert!(s.ssl().handshake_duration().is_some());
+
+    let lines = LINES.lock().unwrap();
+    assert!(lines
+        .iter()
+        .any(|(level, line)| *level == log::Level::Debug
+            && line.starts_with("client handshake done")));
+}
+
 #[test]
 fn verify_trusted() {
     let server = Server::builder().build();
@@ -251,730 +328,2510 @@ fn clear_ctx_options() {
 }
 
 #[test]
-fn zero_length_buffers() {
-    let server = Server::builder().build();
+fn ssl_options() {
+    let ctx = SslContext::builder(SslMethod::tls()).unwrap().build();
+    let mut ssl = Ssl::new(&ctx).unwrap();
+    assert!(!ssl.options().contains(SslOptions::NO_TICKET));
 
-    let mut s = server.client().connect();
-    assert_eq!(s.write(&[]).unwrap(), 0);
-    assert_eq!(s.read(&mut []).unwrap(), 0);
+    let opts = ssl.set_options(SslOptions::NO_TICKET);
+    assert!(opts.contains(SslOptions::NO_TICKET));
+    assert!(ssl.options().contains(SslOptions::NO_TICKET));
+
+    let opts = ssl.clear_options(SslOptions::NO_TICKET);
+    assert!(!opts.contains(SslOptions::NO_TICKET));
 }
 
 #[test]
-fn peer_certificate() {
-    let server = Server::builder().build();
+fn ssl_options_no_ticket() {
+    static TICKETS: AtomicUsize = AtomicUsize::new(0);
+
+    let connect = |no_ticket: bool| {
+        let mut server = Server::builder();
+        if no_ticket {
+            server.ssl_cb(|ssl| {
+                ssl.set_options(SslOptions::NO_TICKET);
+            });
+        }
+        let server = server.build();
+
+        let mut client = server.client();
+        client
+            .ctx()
+            .set_session_cache_mode(SslSessionCacheMode::CLIENT | SslSessionCacheMode::NO_INTERNAL);
+        client.ctx().set_new_session_callback(|_, _| {
+            TICKETS.fetch_add(1, Ordering::SeqCst);
+        });
+        client.connect();
+    };
 
-    let s = server.client().connect();
-    let cert = s.ssl().peer_certificate().unwrap();
-    let fingerprint = cert.digest(MessageDigest::sha1()).unwrap();
-    assert_eq!(
-        hex::encode(fingerprint),
-        "59172d9313e84459bcff27f967e79e6e9217e584"
-    );
+    connect(false);
+    let issued = TICKETS.load(Ordering::SeqCst);
+    assert!(issued > 0);
+
+    connect(true);
+    assert_eq!(TICKETS.load(Ordering::SeqCst), issued);
 }
 
 #[test]
-fn pending() {
+fn tls_unique_channel_binding() {
     let mut server = Server::builder();
-    server.io_cb(|mut s| s.write_all(&[0; 10]).unwrap());
+    server
+        .ctx()
+        .set_max_proto_version(Some(SslVersion::TLS1_2))
+        .unwrap();
+    server.io_cb(|mut s| {
+        let mut buf = [0; 64];
+        let len = s.ssl().finished(&mut buf);
+        s.write_all(&[len as u8]).unwrap();
+        s.write_all(&buf[..len]).unwrap();
+        let len = s.ssl().peer_finished(&mut buf);
+        s.write_all(&[len as u8]).unwrap();
+        s.write_all(&buf[..len]).unwrap();
+    });
     let server = server.build();
 
     let mut s = server.client().connect();
-    s.read_exact(&mut [0]).unwrap();
+    assert_eq!(s.ssl().version2(), Some(SslVersion::TLS1_2));
 
-    assert_eq!(s.ssl().pending(), 9);
-    assert_eq!(s.read(&mut [0; 10]).unwrap(), 9);
-}
+    let mut read_finished = || {
+        let mut len = [0];
+        s.read_exact(&mut len).unwrap();
+        let mut buf = vec![0; len[0] as usize];
+        s.read_exact(&mut buf).unwrap();
+        buf
+    };
+    let server_finished = read_finished();
+    let server_peer_finished = read_finished();
 
-#[test]
-fn state() {
-    let server = Server::builder().build();
+    let mut buf = [0; 64];
+    let len = s.ssl().finished(&mut buf);
+    assert!(len > 0);
+    assert_eq!(&buf[..len], &server_peer_finished[..]);
 
-    let s = server.client().connect();
-    // NOTE: Boring returs a placeholder string for state_string
-    assert_eq!(s.ssl().state_string(), "!!!!!!");
-    assert_eq!(
-        s.ssl().state_string_long(),
-        "SSL negotiation finished successfully"
-    );
+    let len = s.ssl().peer_finished(&mut buf);
+    assert!(len > 0);
+    assert_eq!(&buf[..len], &server_finished[..]);
 }
 
-/// Tests that when both the client as well as the server use SRTP and their
-/// lists of supported protocols have an overlap -- with only ONE protocol
-/// being valid for both.
 #[test]
-fn test_connect_with_srtp_ctx() {
-    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
-    let addr = listener.local_addr().unwrap();
-
-    let guard = thread::spawn(move || {
-        let stream = listener.accept().unwrap().0;
-        let mut ctx = SslContext::builder(SslMethod::dtls()).unwrap();
-        ctx.set_tlsext_use_srtp("SRTP_AES128_CM_SHA1_80:SRTP_AES128_CM_SHA1_32")
-            .unwrap();
-        ctx.set_certificate_file(Path::new("test/cert.pem"), SslFiletype::PEM)
-            .unwrap();
-        ctx.set_private_key_file(Path::new("test/key.pem"), SslFiletype::PEM)
-            .unwrap();
-        let mut ssl = Ssl::new(&ctx.build()).unwrap();
-        ssl.set_mtu(1500).unwrap();
-        let mut stream = ssl.accept(stream).unwrap();
+fn handshake_with_small_reads() {
+    struct SmallReads(TcpStream);
 
-        let mut buf = [0; 60];
-        stream
-            .ssl()
-            .export_keying_material(&mut buf, "EXTRACTOR-dtls_srtp", None)
-            .unwrap();
-
-        stream.write_all(&[0]).unwrap();
-
-        buf
-    });
+    impl Read for SmallReads {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let len = buf.len().min(16);
+            self.0.read(&mut buf[..len])
+        }
+    }
 
-    let stream = TcpStream::connect(addr).unwrap();
-    let mut ctx = SslContext::builder(SslMethod::dtls()).unwrap();
-    ctx.set_tlsext_use_srtp("SRTP_AES128_CM_SHA1_80:SRTP_AES128_CM_SHA1_32")
-        .unwrap();
-    let mut ssl = Ssl::new(&ctx.build()).unwrap();
-    ssl.set_mtu(1500).unwrap();
-    let mut stream = ssl.connect(stream).unwrap();
+    impl Write for SmallReads {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.write(buf)
+        }
 
-    let mut buf = [1; 60];
-    {
-        let srtp_profile = stream.ssl().selected_srtp_profile().unwrap();
-        assert_eq!("SRTP_AES128_CM_SHA1_80", srtp_profile.name());
-        assert_eq!(SrtpProfileId::SRTP_AES128_CM_SHA1_80, srtp_profile.id());
+        fn flush(&mut self) -> io::Result<()> {
+            self.0.flush()
+        }
     }
-    stream
-        .ssl()
-        .export_keying_material(&mut buf, "EXTRACTOR-dtls_srtp", None)
-        .expect("extract");
-
-    stream.read_exact(&mut [0]).unwrap();
 
-    let buf2 = guard.join().unwrap();
+    let mut server = Server::builder();
+    server
+        .ctx()
+        .set_certificate_chain_file("test/certs.pem")
+        .unwrap();
+    let server = server.build();
 
-    assert_eq!(buf[..], buf2[..]);
+    let stream = SmallReads(server.connect_tcp());
+    let ctx = SslContext::builder(SslMethod::tls()).unwrap().build();
+    let mut s = Ssl::new(&ctx).unwrap().connect(stream).unwrap();
+    s.read_exact(&mut [0]).unwrap();
+    assert!(s.ssl().peer_cert_chain().unwrap().len() > 1);
 }
 
-/// Tests that when both the client as well as the server use SRTP and their
-/// lists of supported protocols have an overlap -- with only ONE protocol
-/// being valid for both.
 #[test]
-fn test_connect_with_srtp_ssl() {
-    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
-    let addr = listener.local_addr().unwrap();
+fn client_hello_padding() {
+    struct Capture(Vec<u8>);
 
-    let guard = thread::spawn(move || {
-        let stream = listener.accept().unwrap().0;
-        let mut ctx = SslContext::builder(SslMethod::dtls()).unwrap();
-        ctx.set_certificate_file(Path::new("test/cert.pem"), SslFiletype::PEM)
-            .unwrap();
-        ctx.set_private_key_file(Path::new("test/key.pem"), SslFiletype::PEM)
-            .unwrap();
-        let mut ssl = Ssl::new(&ctx.build()).unwrap();
-        ssl.set_tlsext_use_srtp("SRTP_AES128_CM_SHA1_80:SRTP_AES128_CM_SHA1_32")
-            .unwrap();
-        let mut profilenames = String::new();
-        for profile in ssl.srtp_profiles().unwrap() {
-            if !profilenames.is_empty() {
-                profilenames.push(':');
-            }
-            profilenames += profile.name();
+    impl Read for Capture {
+        fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+            Err(io::ErrorKind::WouldBlock.into())
         }
-        assert_eq!(
-            "SRTP_AES128_CM_SHA1_80:SRTP_AES128_CM_SHA1_32",
-            profilenames
-        );
-        ssl.set_mtu(1500).unwrap();
-        let mut stream = ssl.accept(stream).unwrap();
-
-        let mut buf = [0; 60];
-        stream
-            .ssl()
-            .export_keying_material(&mut buf, "EXTRACTOR-dtls_srtp", None)
-            .unwrap();
-
-        stream.write_all(&[0]).unwrap();
-
-        buf
-    });
+    }
 
-    let stream = TcpStream::connect(addr).unwrap();
-    let ctx = SslContext::builder(SslMethod::dtls()).unwrap();
-    let mut ssl = Ssl::new(&ctx.build()).unwrap();
-    ssl.set_tlsext_use_srtp("SRTP_AES128_CM_SHA1_80:SRTP_AES128_CM_SHA1_32")
-        .unwrap();
-    ssl.set_mtu(1500).unwrap();
-    let mut stream = ssl.connect(stream).unwrap();
+    impl Write for Capture {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.extend_from_slice(buf);
+            Ok(buf.len())
+        }
 
-    let mut buf = [1; 60];
-    {
-        let srtp_profile = stream.ssl().selected_srtp_profile().unwrap();
-        assert_eq!("SRTP_AES128_CM_SHA1_80", srtp_profile.name());
-        assert_eq!(SrtpProfileId::SRTP_AES128_CM_SHA1_80, srtp_profile.id());
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
     }
-    stream
-        .ssl()
-        .export_keying_material(&mut buf, "EXTRACTOR-dtls_srtp", None)
-        .expect("extract");
 
-    stream.read_exact(&mut [0]).unwrap();
-
-    let buf2 = guard.join().unwrap();
+    let ctx = SslContext::builder(SslMethod::tls()).unwrap().build();
+    let mut lens = vec![];
+    for name_len in 1..=250 {
+        let mut ssl = Ssl::new(&ctx).unwrap();
+        ssl.set_hostname(&"a".repeat(name_len)).unwrap();
+        let mid = match ssl.connect(Capture(vec![])) {
+            Err(HandshakeError::WouldBlock(mid)) => mid,
+            _ => panic!("expected the handshake to wait for the server"),
+        };
+
+        // A handshake record holding the ClientHello, with a 24-bit message length.
+        let hello = &mid.get_ref().0;
+        assert_eq!(hello[0], 22);
+        assert_eq!(hello[5], 1);
+        let len = (hello[6] as usize) << 16 | (hello[7] as usize) << 8 | hello[8] as usize;
+        lens.push(len + 4);
+    }
 
-    assert_eq!(buf[..], buf2[..]);
+    assert!(lens.iter().all(|&len| len < 0x100 || len >= 0x200));
+    assert!(lens.iter().any(|&len| len >= 0x200));
 }
 
-/// Tests that when the `SslStream` is created as a server stream, the protocols
-/// are correctly advertised to the client.
 #[test]
-fn test_alpn_server_advertise_multiple() {
+fn buf_read_lines() {
     let mut server = Server::builder();
-    server.ctx().set_alpn_select_callback(|_, client| {
-        ssl::select_next_proto(b"\x08http/1.1\x08spdy/3.1", client).ok_or(ssl::AlpnError::NOACK)
+    server.io_cb(|mut s| {
+        s.write_all(b"220 foobar.com ESMTP\r\n250-PIPELINING\r\n250 SMTPUTF8\r\n")
+            .unwrap();
     });
     let server = server.build();
 
-    let mut client = server.client();
-    client.ctx().set_alpn_protos(b"\x08spdy/3.1").unwrap();
-    let s = client.connect();
-    assert_eq!(s.ssl().selected_alpn_protocol(), Some(&b"spdy/3.1"[..]));
+    let s = server.client().connect();
+    let lines = s.lines().collect::<Result<Vec<_>, _>>().unwrap();
+    assert_eq!(
+        lines,
+        ["220 foobar.com ESMTP", "250-PIPELINING", "250 SMTPUTF8"]
+    );
 }
 
 #[test]
-fn test_alpn_server_select_none_fatal() {
+fn buf_read_small_capacity() {
     let mut server = Server::builder();
-    server.ctx().set_alpn_select_callback(|_, client| {
-        ssl::select_next_proto(b"\x08http/1.1\x08spdy/3.1", client)
-            .ok_or(ssl::AlpnError::ALERT_FATAL)
+    server.io_cb(|mut s| {
+        s.write_all(b"220 foobar.com ESMTP\r\n250 SMTPUTF8\r\n")
+            .unwrap();
     });
-    #[cfg(not(feature = "fips"))]
-    server.should_error();
     let server = server.build();
 
-    let mut client = server.client();
-    client.ctx().set_alpn_protos(b"\x06http/2").unwrap();
+    let mut s = server.client().connect();
+    s.set_read_buffer_capacity(4);
+    assert_eq!(s.fill_buf().unwrap().len(), 4);
+    assert_eq!(s.pending(), 36);
 
-    if cfg!(feature = "fips") {
-        let s = client.connect();
-        assert_eq!(None, s.ssl().selected_alpn_protocol());
-    } else {
-        client.connect_err();
-    }
+    let lines = s.lines().collect::<Result<Vec<_>, _>>().unwrap();
+    assert_eq!(lines, ["220 foobar.com ESMTP", "250 SMTPUTF8"]);
 }
 
 #[test]
-fn test_alpn_server_select_none() {
-    let mut server = Server::builder();
-    server.ctx().set_alpn_select_callback(|_, client| {
-        ssl::select_next_proto(b"\x08http/1.1\x08spdy/3.1", client).ok_or(ssl::AlpnError::NOACK)
-    });
-    let server = server.build();
+fn cert_verify_callback_pin() {
+    let pin = X509::from_pem(CERT)
+        .unwrap()
+        .public_key()
+        .unwrap()
+        .public_key_to_der()
+        .unwrap();
 
-    let mut client = server.client();
-    client.ctx().set_alpn_protos(b"\x06http/2").unwrap();
-    let s = client.connect();
-    assert_eq!(None, s.ssl().selected_alpn_protocol());
-}
+    let server = Server::builder().build();
 
-#[test]
-fn test_alpn_server_unilateral() {
+    // No trusted roots are configured, so the default verification would fail.
+    let mut client = server.client();
+    let expected = pin.clone();
+    client.ctx().set_verify(SslVerifyMode::PEER);
+    client.ctx().set_cert_verify_callback(move |ctx| {
+        ctx.cert()
+            .and_then(|cert| cert.public_key().ok())
+            .and_then(|key| key.public_key_to_der().ok())
+            .map_or(false, |spki| spki == expected)
+    });
+    client.connect();
+
+    let other_pin = X509::from_pem(ROOT_CERT)
+        .unwrap()
+        .public_key()
+        .unwrap()
+        .public_key_to_der()
+        .unwrap();
+    assert_ne!(pin, other_pin);
+
+    let mut server = Server::builder();
+    server.should_error();
+    let server = server.build();
+
+    let mut client = server.client();
+    client.ctx().set_verify(SslVerifyMode::PEER);
+    client.ctx().set_cert_verify_callback(move |ctx| {
+        ctx.cert()
+            .and_then(|cert| cert.public_key().ok())
+            .and_then(|key| key.public_key_to_der().ok())
+            .map_or(false, |spki| spki == other_pin)
+    });
+    client.connect_err();
+
+    // Without enforced verification, the rejection only shows in the verify result.
     let server = Server::builder().build();
 
     let mut client = server.client();
-    client.ctx().set_alpn_protos(b"\x06http/2").unwrap();
+    client.ctx().set_verify(SslVerifyMode::NONE);
+    client.ctx().set_cert_verify_callback(|_| false);
     let s = client.connect();
-    assert_eq!(None, s.ssl().selected_alpn_protocol());
+    assert_eq!(
+        s.ssl().verify_result(),
+        X509VerifyResult::APPLICATION_VERIFICATION
+    );
 }
 
 #[test]
-fn test_select_cert_ok() {
+fn peer_certificate_is_leaf() {
+    let expected = X509::from_pem(CERT).unwrap().to_der().unwrap();
+    let expected_server = expected.clone();
+
     let mut server = Server::builder();
     server
         .ctx()
-        .set_select_certificate_callback(|_client_hello| Ok(()));
+        .set_verify_callback(SslVerifyMode::PEER, |_, _| true);
+    server.io_cb(move |s| {
+        let leaf = s.ssl().peer_certificate().unwrap();
+        assert_eq!(leaf.to_der().unwrap(), expected_server);
+    });
     let server = server.build();
 
-    let client = server.client();
-    client.connect();
+    let mut client = server.client();
+    client
+        .ctx()
+        .set_certificate_file("test/cert.pem", SslFiletype::PEM)
+        .unwrap();
+    client
+        .ctx()
+        .set_private_key_file("test/key.pem", SslFiletype::PEM)
+        .unwrap();
+    let s = client.connect();
+
+    let leaf = s.ssl().peer_certificate().unwrap();
+    assert_eq!(leaf.to_der().unwrap(), expected);
+    let chain = s.ssl().peer_cert_chain().unwrap();
+    assert_eq!(chain[0].to_der().unwrap(), expected);
 }
 
 #[test]
-fn test_select_cert_error() {
+fn handshake_metrics() {
     let mut server = Server::builder();
-    server.should_error();
-    server
-        .ctx()
-        .set_select_certificate_callback(|_client_hello| Err(ssl::SelectCertError::ERROR));
+    server.expected_connections_count(2);
     let server = server.build();
 
     let client = server.client();
-    client.connect_err();
+    let s = client.connect();
+    assert_eq!(s.ssl().handshake_duration(), None);
+
+    let mut client = server.client();
+    client.ctx().enable_handshake_metrics();
+    let s = client.connect();
+    let duration = s.ssl().handshake_duration().unwrap();
+    assert!(duration > Duration::from_secs(0));
+    assert!(duration < Duration::from_secs(10));
 }
 
 #[test]
-fn test_select_cert_unknown_extension() {
-    let mut server = Server::builder();
-    let unknown_extension = std::sync::Arc::new(std::sync::Mutex::new(Some(vec![])));
+fn get_ref_peer_addr() {
+    let server = Server::builder().build();
 
-    server.ctx().set_select_certificate_callback({
-        let unknown = unknown_extension.clone();
-        move |client_hello| {
-            let ext = client_hello
-                .get_extension(ExtensionType::SERVER_NAME)
-                .map(ToOwned::to_owned);
-            assert!(ext.is_none());
-            *unknown.lock().unwrap() = ext;
-            Ok(())
-        }
-    });
+    let tcp = server.connect_tcp();
+    let expected = tcp.peer_addr().unwrap();
+    let ctx = SslContext::builder(SslMethod::tls()).unwrap().build();
+    let s = Ssl::new(&ctx).unwrap().connect(tcp).unwrap();
+    assert_eq!(s.get_ref().peer_addr().unwrap(), expected);
+}
 
+#[test]
+fn byte_counters() {
+    let mut server = Server::builder();
+    server.io_cb(|mut s| {
+        let mut buf = [0; 1000];
+        s.read_exact(&mut buf).unwrap();
+        assert_eq!(s.bytes_read(), 1000);
+        s.write_all(&[1; 10]).unwrap();
+    });
     let server = server.build();
-    let client = server.client();
 
-    client.connect();
-    assert_eq!(unknown_extension.lock().unwrap().as_deref(), None);
+    // The client harness reads the single byte the server writes after the handshake.
+    let mut s = server.client().connect();
+    assert_eq!(s.bytes_read(), 1);
+    assert_eq!(s.bytes_written(), 0);
+    s.write_all(&[0; 1000]).unwrap();
+    assert_eq!(s.bytes_written(), 1000);
+
+    // Data buffered by `fill_buf` only counts once it is consumed.
+    assert_eq!(s.fill_buf().unwrap().len(), 10);
+    assert_eq!(s.bytes_read(), 1);
+    s.consume(4);
+    assert_eq!(s.bytes_read(), 5);
+
+    let mut buf = vec![];
+    s.read_to_end(&mut buf).unwrap();
+    assert_eq!(buf.len(), 6);
+    assert_eq!(s.bytes_read(), 11);
+
+    assert!(s.ssl().record_bytes_written() > 1000);
+    assert!(s.ssl().record_bytes_read() > 11);
 }
 
 #[test]
-fn test_select_cert_alpn_extension() {
+fn buf_read_then_read() {
     let mut server = Server::builder();
-    let alpn_extension = std::sync::Arc::new(std::sync::Mutex::new(None));
-    server.ctx().set_select_certificate_callback({
-        let alpn = alpn_extension.clone();
-        move |client_hello| {
-            *alpn.lock().unwrap() = Some(
-                client_hello
-                    .get_extension(ExtensionType::APPLICATION_LAYER_PROTOCOL_NEGOTIATION)
-                    .unwrap()
-                    .to_owned(),
-            );
-            Ok(())
-        }
+    server.io_cb(|mut s| {
+        s.write_all(b"HELLO\r\nworld").unwrap();
     });
     let server = server.build();
 
-    let mut client = server.client();
-    client.ctx().set_alpn_protos(b"\x06http/2").unwrap();
-    client.connect();
-    assert_eq!(
-        alpn_extension.lock().unwrap().as_deref(),
-        Some(&b"\x00\x07\x06http/2"[..]),
-    );
+    let mut s = server.client().connect();
+    let mut line = String::new();
+    s.read_line(&mut line).unwrap();
+    assert_eq!(line, "HELLO\r\n");
+    assert_eq!(s.pending(), 5);
+    assert_eq!(s.ssl().pending(), 0);
+
+    let mut rest = vec![];
+    s.read_to_end(&mut rest).unwrap();
+    assert_eq!(rest, b"world");
 }
 
 #[test]
-#[should_panic(expected = "blammo")]
-fn write_panic() {
-    struct ExplodingStream(TcpStream);
-
-    impl Read for ExplodingStream {
-        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
-            self.0.read(buf)
-        }
+fn cert_compression() {
+    use flate2::read::ZlibDecoder;
+    use flate2::write::ZlibEncoder;
+    use flate2::Compression;
+
+    static COMPRESSED: AtomicUsize = AtomicUsize::new(0);
+    static DECOMPRESSED: AtomicUsize = AtomicUsize::new(0);
+    static SAVED: AtomicUsize = AtomicUsize::new(0);
+
+    fn compress(_: &mut SslRef, input: &[u8]) -> io::Result<Vec<u8>> {
+        let mut encoder = ZlibEncoder::new(vec![], Compression::best());
+        encoder.write_all(input)?;
+        let output = encoder.finish()?;
+        COMPRESSED.fetch_add(1, Ordering::SeqCst);
+        SAVED.store(input.len().saturating_sub(output.len()), Ordering::SeqCst);
+        Ok(output)
     }
 
-    impl Write for ExplodingStream {
-        fn write(&mut self, _: &[u8]) -> io::Result<usize> {
-            panic!("blammo");
-        }
-
-        fn flush(&mut self) -> io::Result<()> {
-            self.0.flush()
-        }
+    fn decompress(_: &mut SslRef, input: &[u8], len: usize) -> io::Result<Vec<u8>> {
+        let mut output = Vec::with_capacity(len);
+        ZlibDecoder::new(input).read_to_end(&mut output)?;
+        DECOMPRESSED.fetch_add(1, Ordering::SeqCst);
+        Ok(output)
     }
 
+    let algorithm = CertCompressionAlgorithm::ZLIB;
+
     let mut server = Server::builder();
-    server.should_error();
+    server
+        .ctx()
+        .set_certificate_chain_file("test/certs.pem")
+        .unwrap();
+    server
+        .ctx()
+        .add_cert_compression_alg(algorithm, compress, decompress)
+        .unwrap();
     let server = server.build();
 
-    let stream = ExplodingStream(server.connect_tcp());
+    let mut client = server.client();
+    client
+        .ctx()
+        .add_cert_compression_alg(algorithm, compress, decompress)
+        .unwrap();
+    let s = client.connect();
 
-    let ctx = SslContext::builder(SslMethod::tls()).unwrap();
-    let _ = Ssl::new(&ctx.build()).unwrap().connect(stream);
+    assert_eq!(s.ssl().version2(), Some(SslVersion::TLS1_3));
+    assert_eq!(s.ssl().peer_cert_chain().unwrap().len(), 2);
+    assert_eq!(COMPRESSED.load(Ordering::SeqCst), 1);
+    assert_eq!(DECOMPRESSED.load(Ordering::SeqCst), 1);
+    // The chain shares its issuer names, so zlib makes the message smaller.
+    assert!(SAVED.load(Ordering::SeqCst) > 0);
 }
 
 #[test]
-#[should_panic(expected = "blammo")]
-fn read_panic() {
-    struct ExplodingStream(TcpStream);
-
-    impl Read for ExplodingStream {
-        fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
-            panic!("blammo");
-        }
+fn cert_compression_too_many_algorithms() {
+    fn identity(_: &mut SslRef, input: &[u8]) -> io::Result<Vec<u8>> {
+        Ok(input.to_vec())
     }
 
-    impl Write for ExplodingStream {
-        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-            self.0.write(buf)
-        }
-
-        fn flush(&mut self) -> io::Result<()> {
-            self.0.flush()
-        }
+    fn identity_decompress(_: &mut SslRef, input: &[u8], _: usize) -> io::Result<Vec<u8>> {
+        Ok(input.to_vec())
     }
 
-    let mut server = Server::builder();
-    server.should_error();
-    let server = server.build();
-
-    let stream = ExplodingStream(server.connect_tcp());
-
-    let ctx = SslContext::builder(SslMethod::tls()).unwrap();
-    let _ = Ssl::new(&ctx.build()).unwrap().connect(stream);
+    let mut ctx = SslContext::builder(SslMethod::tls()).unwrap();
+    for raw in 0xff01..0xff09 {
+        ctx.add_cert_compression_alg(
+            CertCompressionAlgorithm::from_raw(raw),
+            identity,
+            identity_decompress,
+        )
+        .unwrap();
+    }
+    assert!(ctx
+        .add_cert_compression_alg(
+            CertCompressionAlgorithm::from_raw(0xff09),
+            identity,
+            identity_decompress,
+        )
+        .is_err());
 }
 
 #[test]
-#[should_panic(expected = "blammo")]
-fn flush_panic() {
-    struct ExplodingStream(TcpStream);
-
-    impl Read for ExplodingStream {
-        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
-            self.0.read(buf)
-        }
+fn cert_compression_same_callback_types() {
+    // Both algorithms use callbacks of the same types, which must not be confused.
+    fn xor_compress(key: u8) -> impl Fn(&mut SslRef, &[u8]) -> io::Result<Vec<u8>> + Send + Sync {
+        move |_, input| Ok(input.iter().map(|b| b ^ key).collect())
     }
 
-    impl Write for ExplodingStream {
-        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-            self.0.write(buf)
-        }
-
-        fn flush(&mut self) -> io::Result<()> {
-            panic!("blammo");
-        }
+    fn xor_decompress(
+        key: u8,
+    ) -> impl Fn(&mut SslRef, &[u8], usize) -> io::Result<Vec<u8>> + Send + Sync {
+        move |_, input, _| Ok(input.iter().map(|b| b ^ key).collect())
     }
 
+    let first = CertCompressionAlgorithm::from_raw(0xff01);
+    let second = CertCompressionAlgorithm::from_raw(0xff02);
+
     let mut server = Server::builder();
-    server.should_error();
+    server.expected_connections_count(2);
+    server
+        .ctx()
+        .add_cert_compression_alg(first, xor_compress(1), xor_decompress(1))
+        .unwrap();
+    server
+        .ctx()
+        .add_cert_compression_alg(second, xor_compress(2), xor_decompress(2))
+        .unwrap();
     let server = server.build();
 
-    let stream = ExplodingStream(server.connect_tcp());
-
-    let ctx = SslContext::builder(SslMethod::tls()).unwrap();
-    let _ = Ssl::new(&ctx.build()).unwrap().connect(stream);
-}
-
-#[test]
-fn refcount_ssl_context() {
-    let mut ssl = {
-        let ctx = SslContext::builder(SslMethod::tls()).unwrap();
-        ssl::Ssl::new(&ctx.build()).unwrap()
-    };
-
-    {
-        let new_ctx_a = SslContext::builder(SslMethod::tls()).unwrap().build();
-        let _new_ctx_b = ssl.set_ssl_context(&new_ctx_a);
+    for &(algorithm, key) in &[(first, 1), (second, 2)] {
+        let mut client = server.client();
+        client
+            .ctx()
+            .add_cert_compression_alg(algorithm, xor_compress(key), xor_decompress(key))
+            .unwrap();
+        let s = client.connect();
+        assert!(s.ssl().peer_certificate().is_some());
     }
 }
 
 #[test]
-#[cfg_attr(target_os = "windows", ignore)]
-#[cfg_attr(all(target_os = "macos", feature = "vendored"), ignore)]
-fn default_verify_paths() {
-    let mut ctx = SslContext::builder(SslMethod::tls()).unwrap();
-    ctx.set_default_verify_paths().unwrap();
-    ctx.set_verify(SslVerifyMode::PEER);
-    let ctx = ctx.build();
-    let s = match TcpStream::connect("google.com:443") {
-        Ok(s) => s,
-        Err(_) => return,
-    };
-    let mut ssl = Ssl::new(&ctx).unwrap();
-    ssl.set_hostname("google.com").unwrap();
-    let mut socket = ssl.connect(s).unwrap();
+fn cert_compression_requires_peer_support() {
+    static COMPRESSED: AtomicUsize = AtomicUsize::new(0);
 
-    socket.write_all(b"GET / HTTP/1.0\r\n\r\n").unwrap();
-    let mut result = vec![];
-    socket.read_to_end(&mut result).unwrap();
+    let mut server = Server::builder();
+    server
+        .ctx()
+        .add_cert_compression_alg(
+            CertCompressionAlgorithm::ZLIB,
+            |_, input| {
+                COMPRESSED.fetch_add(1, Ordering::SeqCst);
+                Ok(input.to_vec())
+            },
+            |_, input, _| Ok(input.to_vec()),
+        )
+        .unwrap();
+    let server = server.build();
 
-    println!("{}", String::from_utf8_lossy(&result));
-    assert!(result.starts_with(b"HTTP/1.0"));
-    assert!(result.ends_with(b"</HTML>\r\n") || result.ends_with(b"</html>"));
+    server.client().connect();
+    assert_eq!(COMPRESSED.load(Ordering::SeqCst), 0);
 }
 
 #[test]
-fn add_extra_chain_cert() {
-    let cert = X509::from_pem(CERT).unwrap();
-    let mut ctx = SslContext::builder(SslMethod::tls()).unwrap();
-    ctx.add_extra_chain_cert(cert).unwrap();
+fn zero_length_buffers() {
+    let server = Server::builder().build();
+
+    let mut s = server.client().connect();
+    assert_eq!(s.write(&[]).unwrap(), 0);
+    assert_eq!(s.read(&mut []).unwrap(), 0);
 }
 
 #[test]
-fn verify_valid_hostname() {
+fn peer_certificate() {
     let server = Server::builder().build();
 
-    let mut client = server.client();
-    client.ctx().set_ca_file("test/root-ca.pem").unwrap();
-    client.ctx().set_verify(SslVerifyMode::PEER);
-
-    let mut client = client.build().builder();
-    client
-        .ssl()
-        .param_mut()
-        .set_hostflags(X509CheckFlags::NO_PARTIAL_WILDCARDS);
-    client.ssl().param_mut().set_host("foobar.com").unwrap();
-    client.connect();
+    let s = server.client().connect();
+    let cert = s.ssl().peer_certificate().unwrap();
+    let fingerprint = cert.digest(MessageDigest::sha1()).unwrap();
+    assert_eq!(
+        hex::encode(fingerprint),
+        "59172d9313e84459bcff27f967e79e6e9217e584"
+    );
 }
 
 #[test]
-fn verify_invalid_hostname() {
+fn pending() {
+    let mut server = Server::builder();
+    server.io_cb(|mut s| s.write_all(&[0; 10]).unwrap());
+    let server = server.build();
+
+    let mut s = server.client().connect();
+    s.read_exact(&mut [0]).unwrap();
+
+    assert_eq!(s.ssl().pending(), 9);
+    assert_eq!(s.read(&mut [0; 10]).unwrap(), 9);
+}
+
+#[test]
+fn state() {
+    let server = Server::builder().build();
+
+    let s = server.client().connect();
+    // NOTE: Boring returs a placeholder string for state_string
+    assert_eq!(s.ssl().state_string(), "!!!!!!");
+    assert_eq!(
+        s.ssl().state_string_long(),
+        "SSL negotiation finished successfully"
+    );
+}
+
+#[test]
+fn state_mid_handshake() {
+    // The listener never accepts, so the client stalls waiting for the ServerHello.
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let stream = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+    stream.set_nonblocking(true).unwrap();
+
+    let ctx = SslContext::builder(SslMethod::tls()).unwrap().build();
+    let mid = match Ssl::new(&ctx).unwrap().connect(stream) {
+        Err(HandshakeError::WouldBlock(mid)) => mid,
+        _ => panic!("expected the handshake to be suspended"),
+    };
+    assert_eq!(mid.error().code(), ErrorCode::WANT_READ);
+
+    let state = mid.ssl().state_string_long();
+    assert!(state.starts_with("TLS client"), "{}", state);
+    assert_ne!(state, "SSL negotiation finished successfully");
+}
+
+#[test]
+fn dtls_retransmission() {
+    type Queue = Arc<Mutex<VecDeque<Vec<u8>>>>;
+
+    // An in-memory datagram transport which loses the first datagram written to it.
+    struct Datagrams {
+        rx: Queue,
+        tx: Queue,
+        lose_next: bool,
+    }
+
+    impl Read for Datagrams {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            match self.rx.lock().unwrap().pop_front() {
+                Some(datagram) => {
+                    let len = datagram.len().min(buf.len());
+                    buf[..len].copy_from_slice(&datagram[..len]);
+                    Ok(len)
+                }
+                None => Err(io::ErrorKind::WouldBlock.into()),
+            }
+        }
+    }
+
+    impl Write for Datagrams {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            if self.lose_next {
+                self.lose_next = false;
+            } else {
+                self.tx.lock().unwrap().push_back(buf.to_vec());
+            }
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn step<S: Read + Write>(
+        mid: Option<MidHandshakeSslStream<S>>,
+        done: &mut Option<SslStream<S>>,
+    ) -> Option<MidHandshakeSslStream<S>> {
+        match mid?.handshake() {
+            Ok(stream) => {
+                *done = Some(stream);
+                None
+            }
+            Err(HandshakeError::WouldBlock(mid)) => Some(mid),
+            Err(_) => panic!("DTLS handshake failed"),
+        }
+    }
+
+    let client_to_server = Queue::default();
+    let server_to_client = Queue::default();
+
+    let mut ctx = SslContext::builder(SslMethod::dtls()).unwrap();
+    ctx.set_certificate_file(Path::new("test/cert.pem"), SslFiletype::PEM)
+        .unwrap();
+    ctx.set_private_key_file(Path::new("test/key.pem"), SslFiletype::PEM)
+        .unwrap();
+    let mut ssl = Ssl::new(&ctx.build()).unwrap();
+    ssl.set_mtu(1500).unwrap();
+    let mut server = match ssl.accept(Datagrams {
+        rx: client_to_server.clone(),
+        tx: server_to_client.clone(),
+        lose_next: false,
+    }) {
+        Err(HandshakeError::WouldBlock(mid)) => Some(mid),
+        _ => panic!("unexpected server handshake result"),
+    };
+
+    let ctx = SslContext::builder(SslMethod::dtls()).unwrap();
+    let mut ssl = Ssl::new(&ctx.build()).unwrap();
+    ssl.set_mtu(1500).unwrap();
+    let mut client = match ssl.connect(Datagrams {
+        rx: server_to_client,
+        tx: client_to_server,
+        lose_next: true,
+    }) {
+        Err(HandshakeError::WouldBlock(mid)) => mid,
+        _ => panic!("unexpected client handshake result"),
+    };
+
+    // The ClientHello was lost, so the server has nothing to respond to.
+    let mut server_stream = None;
+    server = step(server, &mut server_stream);
+    assert!(server.is_some());
+
+    let timeout = client.ssl().dtls_get_timeout().unwrap();
+    assert!(timeout > Duration::from_secs(0));
+    assert!(!client.ssl_mut().dtls_handle_timeout().unwrap());
+
+    thread::sleep(timeout + Duration::from_millis(10));
+    assert!(client.ssl_mut().dtls_handle_timeout().unwrap());
+
+    let mut client = Some(client);
+    let mut client_stream = None;
+    for _ in 0..100 {
+        if client.is_none() && server.is_none() {
+            break;
+        }
+        client = step(client, &mut client_stream);
+        server = step(server, &mut server_stream);
+    }
+
+    assert!(client_stream.is_some());
+    assert!(server_stream.is_some());
+}
+
+/// Tests that when both the client as well as the server use SRTP and their
+/// lists of supported protocols have an overlap -- with only ONE protocol
+/// being valid for both.
+#[test]
+fn test_connect_with_srtp_ctx() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let guard = thread::spawn(move || {
+        let stream = listener.accept().unwrap().0;
+        let mut ctx = SslContext::builder(SslMethod::dtls()).unwrap();
+        ctx.set_tlsext_use_srtp("SRTP_AES128_CM_SHA1_80:SRTP_AES128_CM_SHA1_32")
+            .unwrap();
+        ctx.set_certificate_file(Path::new("test/cert.pem"), SslFiletype::PEM)
+            .unwrap();
+        ctx.set_private_key_file(Path::new("test/key.pem"), SslFiletype::PEM)
+            .unwrap();
+        let mut ssl = Ssl::new(&ctx.build()).unwrap();
+        ssl.set_mtu(1500).unwrap();
+        let mut stream = ssl.accept(stream).unwrap();
+
+        let mut buf = [0; 60];
+        stream
+            .ssl()
+            .export_keying_material(&mut buf, "EXTRACTOR-dtls_srtp", None)
+            .unwrap();
+
+        stream.write_all(&[0]).unwrap();
+
+        buf
+    });
+
+    let stream = TcpStream::connect(addr).unwrap();
+    let mut ctx = SslContext::builder(SslMethod::dtls()).unwrap();
+    ctx.set_tlsext_use_srtp("SRTP_AES128_CM_SHA1_80:SRTP_AES128_CM_SHA1_32")
+        .unwrap();
+    let mut ssl = Ssl::new(&ctx.build()).unwrap();
+    ssl.set_mtu(1500).unwrap();
+    let mut stream = ssl.connect(stream).unwrap();
+
+    let mut buf = [1; 60];
+    {
+        let srtp_profile = stream.ssl().selected_srtp_profile().unwrap();
+        assert_eq!("SRTP_AES128_CM_SHA1_80", srtp_profile.name());
+        assert_eq!(SrtpProfileId::SRTP_AES128_CM_SHA1_80, srtp_profile.id());
+    }
+    stream
+        .ssl()
+        .export_keying_material(&mut buf, "EXTRACTOR-dtls_srtp", None)
+        .expect("extract");
+
+    stream.read_exact(&mut [0]).unwrap();
+
+    let buf2 = guard.join().unwrap();
+
+    assert_eq!(buf[..], buf2[..]);
+}
+
+/// Tests that when both the client as well as the server use SRTP and their
+/// lists of supported protocols have an overlap -- with only ONE protocol
+/// being valid for both.
+#[test]
+fn test_connect_with_srtp_ssl() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let guard = thread::spawn(move || {
+        let stream = listener.accept().unwrap().0;
+        let mut ctx = SslContext::builder(SslMethod::dtls()).unwrap();
+        ctx.set_certificate_file(Path::new("test/cert.pem"), SslFiletype::PEM)
+            .unwrap();
+        ctx.set_private_key_file(Path::new("test/key.pem"), SslFiletype::PEM)
+            .unwrap();
+        let mut ssl = Ssl::new(&ctx.build()).unwrap();
+        ssl.set_tlsext_use_srtp("SRTP_AES128_CM_SHA1_80:SRTP_AES128_CM_SHA1_32")
+            .unwrap();
+        let mut profilenames = String::new();
+        for profile in ssl.srtp_profiles().unwrap() {
+            if !profilenames.is_empty() {
+                profilenames.push(':');
+            }
+            profilenames += profile.name();
+        }
+        assert_eq!(
+            "SRTP_AES128_CM_SHA1_80:SRTP_AES128_CM_SHA1_32",
+            profilenames
+        );
+        ssl.set_mtu(1500).unwrap();
+        let mut stream = ssl.accept(stream).unwrap();
+
+        let mut buf = [0; 60];
+        stream
+            .ssl()
+            .export_keying_material(&mut buf, "EXTRACTOR-dtls_srtp", None)
+            .unwrap();
+
+        stream.write_all(&[0]).unwrap();
+
+        buf
+    });
+
+    let stream = TcpStream::connect(addr).unwrap();
+    let ctx = SslContext::builder(SslMethod::dtls()).unwrap();
+    let mut ssl = Ssl::new(&ctx.build()).unwrap();
+    ssl.set_tlsext_use_srtp("SRTP_AES128_CM_SHA1_80:SRTP_AES128_CM_SHA1_32")
+        .unwrap();
+    ssl.set_mtu(1500).unwrap();
+    let mut stream = ssl.connect(stream).unwrap();
+
+    let mut buf = [1; 60];
+    {
+        let srtp_profile = stream.ssl().selected_srtp_profile().unwrap();
+        assert_eq!("SRTP_AES128_CM_SHA1_80", srtp_profile.name());
+        assert_eq!(SrtpProfileId::SRTP_AES128_CM_SHA1_80, srtp_profile.id());
+    }
+    stream
+        .ssl()
+        .export_keying_material(&mut buf, "EXTRACTOR-dtls_srtp", None)
+        .expect("extract");
+
+    stream.read_exact(&mut [0]).unwrap();
+
+    let buf2 = guard.join().unwrap();
+
+    assert_eq!(buf[..], buf2[..]);
+}
+
+/// Tests that when the `SslStream` is created as a server stream, the protocols
+/// are correctly advertised to the client.
+#[test]
+fn test_alpn_server_advertise_multiple() {
+    let mut server = Server::builder();
+    server.ctx().set_alpn_select_callback(|_, client| {
+        ssl::select_next_proto(b"\x08http/1.1\x08spdy/3.1", client).ok_or(ssl::AlpnError::NOACK)
+    });
+    let server = server.build();
+
+    let mut client = server.client();
+    client.ctx().set_alpn_protos(b"\x08spdy/3.1").unwrap();
+    let s = client.connect();
+    assert_eq!(s.ssl().selected_alpn_protocol(), Some(&b"spdy/3.1"[..]));
+}
+
+#[test]
+fn test_alpn_protocols() {
+    let mut server = Server::builder();
+    server.ctx().set_alpn_select_callback(|_, client| {
+        ssl::select_next_proto(b"\x08http/1.1", client).ok_or(ssl::AlpnError::NOACK)
+    });
+    let server = server.build();
+
+    let mut client = server.client();
+    client
+        .ctx()
+        .set_alpn_protocols(&[b"h2", b"http/1.1"])
+        .unwrap();
+    let s = client.connect();
+    assert_eq!(s.ssl().selected_alpn_protocol(), Some(&b"http/1.1"[..]));
+}
+
+#[test]
+fn test_alpn_allow_unknown_protos() {
+    let mut server = Server::builder();
+    server
+        .ctx()
+        .set_alpn_select_callback(|_, _| Ok(&b"surprise"[..]));
+    server.should_error();
+    let server = server.build();
+
+    let mut client = server.client();
+    client.ctx().set_alpn_protos(b"\x02h2").unwrap();
+    client.connect_err();
+
+    let mut server = Server::builder();
+    server
+        .ctx()
+        .set_alpn_select_callback(|_, _| Ok(&b"surprise"[..]));
+    let server = server.build();
+
+    let mut client = server.client();
+    client.ctx().set_alpn_protos(b"\x02h2").unwrap();
+    client.ctx().set_allow_unknown_alpn_protos(true);
+    let s = client.connect();
+    assert_eq!(s.ssl().selected_alpn_protocol(), Some(&b"surprise"[..]));
+}
+
+#[test]
+fn test_alpn_protocols_invalid() {
+    let mut ctx = SslContext::builder(SslMethod::tls()).unwrap();
+    assert!(ctx.set_alpn_protocols(&[b"h2", b""]).is_err());
+    assert!(ctx.set_alpn_protocols(&[&[b'a'; 256][..]]).is_err());
+}
+
+#[test]
+fn test_alpn_server_select_none_fatal() {
+    let mut server = Server::builder();
+    server.ctx().set_alpn_select_callback(|_, client| {
+        ssl::select_next_proto(b"\x08http/1.1\x08spdy/3.1", client)
+            .ok_or(ssl::AlpnError::ALERT_FATAL)
+    });
+    #[cfg(not(feature = "fips"))]
+    server.should_error();
+    let server = server.build();
+
+    let mut client = server.client();
+    client.ctx().set_alpn_protos(b"\x06http/2").unwrap();
+
+    if cfg!(feature = "fips") {
+        let s = client.connect();
+        assert_eq!(None, s.ssl().selected_alpn_protocol());
+    } else {
+        client.connect_err();
+    }
+}
+
+#[test]
+fn test_alpn_server_select_none() {
+    let mut server = Server::builder();
+    server.ctx().set_alpn_select_callback(|_, client| {
+        ssl::select_next_proto(b"\x08http/1.1\x08spdy/3.1", client).ok_or(ssl::AlpnError::NOACK)
+    });
+    let server = server.build();
+
+    let mut client = server.client();
+    client.ctx().set_alpn_protos(b"\x06http/2").unwrap();
+    let s = client.connect();
+    assert_eq!(None, s.ssl().selected_alpn_protocol());
+}
+
+#[test]
+fn test_alpn_server_unilateral() {
+    let server = Server::builder().build();
+
+    let mut client = server.client();
+    client.ctx().set_alpn_protos(b"\x06http/2").unwrap();
+    let s = client.connect();
+    assert_eq!(None, s.ssl().selected_alpn_protocol());
+}
+
+#[test]
+fn test_select_cert_ok() {
+    let mut server = Server::builder();
+    server
+        .ctx()
+        .set_select_certificate_callback(|_client_hello| Ok(()));
+    let server = server.build();
+
+    let client = server.client();
+    client.connect();
+}
+
+#[test]
+fn dual_certificates() {
+    let (ec_cert, ec_key) = self_signed_cert("foobar.com");
+
+    let mut server = Server::builder();
+    server.expected_connections_count(2);
+    server.ctx().set_cert_callback(move |ssl| {
+        let supports_ecdsa = ssl
+            .peer_verify_algorithms()
+            .contains(&SslSignatureAlgorithm::ECDSA_SECP256R1_SHA256);
+        if supports_ecdsa {
+            ssl.set_certificate(&ec_cert)?;
+            ssl.set_private_key(&ec_key)?;
+        }
+        Ok(())
+    });
+    let server = server.build();
+
+    let peer_key_id = |prefs: Option<&[SslSignatureAlgorithm]>| {
+        let mut client = server.client();
+        if let Some(prefs) = prefs {
+            client.ctx().set_verify_algorithm_prefs(prefs).unwrap();
+        }
+        let s = client.connect();
+        let cert = s.ssl().peer_certificate().unwrap();
+        cert.public_key().unwrap().id()
+    };
+
+    assert_eq!(peer_key_id(None), Id::EC);
+    assert_eq!(
+        peer_key_id(Some(
+            &[
+                SslSignatureAlgorithm::RSA_PSS_RSAE_SHA256,
+                SslSignatureAlgorithm::RSA_PKCS1_SHA256,
+            ][..]
+        )),
+        Id::RSA
+    );
+}
+
+#[test]
+fn test_select_cert_error() {
+    let mut server = Server::builder();
+    server.should_error();
+    server
+        .ctx()
+        .set_select_certificate_callback(|_client_hello| Err(ssl::SelectCertError::ERROR));
+    let server = server.build();
+
+    let client = server.client();
+    client.connect_err();
+}
+
+#[test]
+fn test_select_cert_sni_allowlist() {
+    static SNI_CALLED: AtomicBool = AtomicBool::new(false);
+
+    let mut server = Server::builder();
+    server.should_error();
+    server
+        .ctx()
+        .set_select_certificate_callback(|client_hello| {
+            match client_hello.servername(ssl::NameType::HOST_NAME) {
+                Some("foobar.com") => Ok(()),
+                _ => Err(ssl::SelectCertError::ERROR),
+            }
+        });
+    server.ctx().set_servername_callback(|_, _| {
+        SNI_CALLED.store(true, Ordering::SeqCst);
+        Ok(())
+    });
+    let server = server.build();
+
+    let client = server.client();
+    let mut client = client.build().builder();
+    client.ssl().set_hostname("evil.com").unwrap();
+    client.connect_err();
+
+    assert!(!SNI_CALLED.load(Ordering::SeqCst));
+}
+
+/// Returns ECH keys holding a single retry configuration for `key`, and its `ECHConfigList`.
+#[cfg(not(feature = "fips"))]
+fn ech_keys(config_id: u8, key: &HpkeKeyRef, public_name: &str) -> (SslEchKeys, Vec<u8>) {
+    let config = ssl::marshal_ech_config(config_id, key, public_name, 0).unwrap();
+    let mut keys = SslEchKeys::builder().unwrap();
+    keys.add_key(true, &config, key).unwrap();
+    let keys = keys.build();
+    let list = keys.marshal_retry_configs().unwrap();
+    (keys, list)
+}
+
+#[test]
+#[cfg(not(feature = "fips"))]
+fn ech_outer_server_name() {
+    let outer_name = Arc::new(Mutex::new(None));
+
+    let mut server = Server::builder();
+    server.should_error();
+    let outer_name2 = outer_name.clone();
+    server.ctx().set_servername_callback(move |s, _| {
+        *outer_name2.lock().unwrap() = s.servername(ssl::NameType::HOST_NAME).map(str::to_owned);
+        Ok(())
+    });
+    let server = server.build();
+
+    let key = HpkeKey::generate(HpkeKem::x25519_hkdf_sha256()).unwrap();
+    let ctx = SslContext::builder(SslMethod::tls()).unwrap().build();
+    let mut ssl = Ssl::new(&ctx).unwrap();
+    ssl.set_hostname("foobar.com").unwrap();
+    let (_, list) = ech_keys(1, &key, "public.example");
+    ssl.set_ech_config_list(&list).unwrap();
+
+    // The server has no ECH keys, so it only sees the outer ClientHello and the client rejects
+    // the handshake.
+    let error = match ssl.connect(server.connect_tcp()) {
+        Err(HandshakeError::Failure(mid)) => {
+            assert!(!mid.ssl().ech_accepted());
+            assert_eq!(mid.ssl().ech_retry_configs(), None);
+            mid.into_error()
+        }
+        _ => panic!("expected the handshake to fail"),
+    };
+    let errors = error.ssl_error().unwrap().errors();
+    assert!(errors.iter().any(|e| e.reason() == Some("ECH_REJECTED")));
+
+    assert_eq!(
+        *outer_name.lock().unwrap(),
+        Some("public.example".to_string())
+    );
+}
+
+#[test]
+#[cfg(not(feature = "fips"))]
+fn ech_accepted() {
+    let inner_name = Arc::new(Mutex::new(None));
+
+    // The server rotated its keys: the new one is offered for retries, and the old one still
+    // decrypts ClientHellos from clients with the previous configuration.
+    let kem = HpkeKem::x25519_hkdf_sha256();
+    let old_key = HpkeKey::generate(kem).unwrap();
+    let new_key = HpkeKey::generate(kem).unwrap();
+    let (_, old_list) = ech_keys(1, &old_key, "public.example");
+    let old_config = ssl::marshal_ech_config(1, &old_key, "public.example", 0).unwrap();
+    let new_config = ssl::marshal_ech_config(2, &new_key, "public.example", 0).unwrap();
+    let mut keys = SslEchKeys::builder().unwrap();
+    keys.add_key(false, &old_config, &old_key).unwrap();
+    keys.add_key(true, &new_config, &new_key).unwrap();
+    let keys = keys.build();
+    let mut new_list = (new_config.len() as u16).to_be_bytes().to_vec();
+    new_list.extend_from_slice(&new_config);
+    assert_eq!(keys.marshal_retry_configs().unwrap(), new_list);
+
+    let mut server = Server::builder();
+    server.expected_connections_count(2);
+    server.ctx().set_ech_keys(&keys).unwrap();
+    let inner_name2 = inner_name.clone();
+    server.ctx().set_servername_callback(move |s, _| {
+        *inner_name2.lock().unwrap() = s.servername(ssl::NameType::HOST_NAME).map(str::to_owned);
+        Ok(())
+    });
+    server.io_cb(|s| assert!(s.ssl().ech_accepted()));
+    let server = server.build();
+
+    let client = server.client().build();
+    for list in &[old_list, new_list] {
+        let mut client = client.builder();
+        client.ssl().set_hostname("foobar.com").unwrap();
+        client.ssl().set_ech_config_list(list).unwrap();
+        let s = client.connect();
+        assert!(s.ssl().ech_accepted());
+        assert_eq!(*inner_name.lock().unwrap(), Some("foobar.com".to_string()));
+    }
+}
+
+#[test]
+fn test_select_cert_retry() {
+    static CALLS: AtomicUsize = AtomicUsize::new(0);
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let server = thread::spawn(move || {
+        let mut ctx = SslContext::builder(SslMethod::tls()).unwrap();
+        ctx.set_certificate_chain_file("test/cert.pem").unwrap();
+        ctx.set_private_key_file("test/key.pem", SslFiletype::PEM)
+            .unwrap();
+        ctx.set_select_certificate_callback(|hello| {
+            assert!(hello.ssl().is_server());
+            if CALLS.fetch_add(1, Ordering::SeqCst) == 0 {
+                Err(ssl::SelectCertError::RETRY)
+            } else {
+                Ok(())
+            }
+        });
+        let ctx = ctx.build();
+
+        let stream = listener.accept().unwrap().0;
+        let ssl = Ssl::new(&ctx).unwrap();
+        let mid = match ssl.accept(stream) {
+            Err(HandshakeError::WouldBlock(mid)) => mid,
+            _ => panic!("expected the handshake to be suspended"),
+        };
+        assert_eq!(mid.error().code(), ErrorCode::PENDING_CERTIFICATE);
+
+        let mut stream = mid.handshake().unwrap();
+        stream.write_all(&[0]).unwrap();
+    });
+
+    let ctx = SslContext::builder(SslMethod::tls()).unwrap().build();
+    let ssl = Ssl::new(&ctx).unwrap();
+    let mut stream = ssl.connect(TcpStream::connect(addr).unwrap()).unwrap();
+    stream.read_exact(&mut [0]).unwrap();
+
+    server.join().unwrap();
+    assert_eq!(CALLS.load(Ordering::SeqCst), 2);
+}
+
+#[test]
+fn test_cert_callback_retry() {
+    static CALLS: AtomicUsize = AtomicUsize::new(0);
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let server = thread::spawn(move || {
+        let mut ctx = SslContext::builder(SslMethod::tls()).unwrap();
+        ctx.set_cert_callback(|ssl| {
+            if CALLS.fetch_add(1, Ordering::SeqCst) == 0 {
+                return Err(ssl::CertCallbackError::RETRY);
+            }
+            let cert = X509::from_pem(CERT).unwrap();
+            let key = PKey::private_key_from_pem(KEY).unwrap();
+            ssl.set_certificate(&cert)?;
+            ssl.set_private_key(&key)?;
+            Ok(())
+        });
+        let ctx = ctx.build();
+
+        let stream = listener.accept().unwrap().0;
+        let ssl = Ssl::new(&ctx).unwrap();
+        let mid = match ssl.accept(stream) {
+            Err(HandshakeError::WouldBlock(mid)) => mid,
+            _ => panic!("expected the handshake to be suspended"),
+        };
+        assert_eq!(mid.error().code(), ErrorCode::WANT_X509_LOOKUP);
+
+        let mut stream = mid.handshake().unwrap();
+        stream.write_all(&[0]).unwrap();
+    });
+
+    let ctx = SslContext::builder(SslMethod::tls()).unwrap().build();
+    let ssl = Ssl::new(&ctx).unwrap();
+    let mut stream = ssl.connect(TcpStream::connect(addr).unwrap()).unwrap();
+    stream.read_exact(&mut [0]).unwrap();
+
+    server.join().unwrap();
+    assert_eq!(CALLS.load(Ordering::SeqCst), 2);
+}
+
+#[test]
+fn test_select_cert_unknown_extension() {
+    let mut server = Server::builder();
+    let unknown_extension = std::sync::Arc::new(std::sync::Mutex::new(Some(vec![])));
+
+    server.ctx().set_select_certificate_callback({
+        let unknown = unknown_extension.clone();
+        move |client_hello| {
+            let ext = client_hello
+                .get_extension(ExtensionType::SERVER_NAME)
+                .map(ToOwned::to_owned);
+            assert!(ext.is_none());
+            *unknown.lock().unwrap() = ext;
+            Ok(())
+        }
+    });
+
+    let server = server.build();
+    let client = server.client();
+
+    client.connect();
+    assert_eq!(unknown_extension.lock().unwrap().as_deref(), None);
+}
+
+#[test]
+fn test_select_cert_alpn_extension() {
+    let mut server = Server::builder();
+    let alpn_extension = std::sync::Arc::new(std::sync::Mutex::new(None));
+    server.ctx().set_select_certificate_callback({
+        let alpn = alpn_extension.clone();
+        move |client_hello| {
+            *alpn.lock().unwrap() = Some(
+                client_hello
+                    .get_extension(ExtensionType::APPLICATION_LAYER_PROTOCOL_NEGOTIATION)
+                    .unwrap()
+                    .to_owned(),
+            );
+            Ok(())
+        }
+    });
+    let server = server.build();
+
+    let mut client = server.client();
+    client.ctx().set_alpn_protos(b"\x06http/2").unwrap();
+    client.connect();
+    assert_eq!(
+        alpn_extension.lock().unwrap().as_deref(),
+        Some(&b"\x00\x07\x06http/2"[..]),
+    );
+}
+
+#[test]
+#[should_panic(expected = "blammo")]
+fn write_panic() {
+    struct ExplodingStream(TcpStream);
+
+    impl Read for ExplodingStream {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.0.read(buf)
+        }
+    }
+
+    impl Write for ExplodingStream {
+        fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+            panic!("blammo");
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            self.0.flush()
+        }
+    }
+
+    let mut server = Server::builder();
+    server.should_error();
+    let server = server.build();
+
+    let stream = ExplodingStream(server.connect_tcp());
+
+    let ctx = SslContext::builder(SslMethod::tls()).unwrap();
+    let _ = Ssl::new(&ctx.build()).unwrap().connect(stream);
+}
+
+#[test]
+#[should_panic(expected = "blammo")]
+fn read_panic() {
+    struct ExplodingStream(TcpStream);
+
+    impl Read for ExplodingStream {
+        fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+            panic!("blammo");
+        }
+    }
+
+    impl Write for ExplodingStream {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            self.0.flush()
+        }
+    }
+
+    let mut server = Server::builder();
+    server.should_error();
+    let server = server.build();
+
+    let stream = ExplodingStream(server.connect_tcp());
+
+    let ctx = SslContext::builder(SslMethod::tls()).unwrap();
+    let _ = Ssl::new(&ctx.build()).unwrap().connect(stream);
+}
+
+#[test]
+#[should_panic(expected = "blammo")]
+fn flush_panic() {
+    struct ExplodingStream(TcpStream);
+
+    impl Read for ExplodingStream {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.0.read(buf)
+        }
+    }
+
+    impl Write for ExplodingStream {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            panic!("blammo");
+        }
+    }
+
+    let mut server = Server::builder();
+    server.should_error();
+    let server = server.build();
+
+    let stream = ExplodingStream(server.connect_tcp());
+
+    let ctx = SslContext::builder(SslMethod::tls()).unwrap();
+    let _ = Ssl::new(&ctx.build()).unwrap().connect(stream);
+}
+
+#[test]
+fn refcount_ssl_context() {
+    let mut ssl = {
+        let ctx = SslContext::builder(SslMethod::tls()).unwrap();
+        ssl::Ssl::new(&ctx.build()).unwrap()
+    };
+
+    {
+        let new_ctx_a = SslContext::builder(SslMethod::tls()).unwrap().build();
+        let _new_ctx_b = ssl.set_ssl_context(&new_ctx_a);
+    }
+}
+
+#[test]
+#[cfg_attr(target_os = "windows", ignore)]
+#[cfg_attr(all(target_os = "macos", feature = "vendored"), ignore)]
+fn default_verify_paths() {
+    let mut ctx = SslContext::builder(SslMethod::tls()).unwrap();
+    ctx.set_default_verify_paths().unwrap();
+    ctx.set_verify(SslVerifyMode::PEER);
+    let ctx = ctx.build();
+    let s = match TcpStream::connect("google.com:443") {
+        Ok(s) => s,
+        Err(_) => return,
+    };
+    let mut ssl = Ssl::new(&ctx).unwrap();
+    ssl.set_hostname("google.com").unwrap();
+    let mut socket = ssl.connect(s).unwrap();
+
+    socket.write_all(b"GET / HTTP/1.0\r\n\r\n").unwrap();
+    let mut result = vec![];
+    socket.read_to_end(&mut result).unwrap();
+
+    println!("{}", String::from_utf8_lossy(&result));
+    assert!(result.starts_with(b"HTTP/1.0"));
+    assert!(result.ends_with(b"</HTML>\r\n") || result.ends_with(b"</html>"));
+}
+
+#[test]
+fn add_extra_chain_cert() {
+    let cert = X509::from_pem(CERT).unwrap();
+    let mut ctx = SslContext::builder(SslMethod::tls()).unwrap();
+    ctx.add_extra_chain_cert(cert).unwrap();
+}
+
+#[test]
+fn verify_valid_hostname() {
+    let server = Server::builder().build();
+
+    let mut client = server.client();
+    client.ctx().set_ca_file("test/root-ca.pem").unwrap();
+    client.ctx().set_verify(SslVerifyMode::PEER);
+
+    let mut client = client.build().builder();
+    client
+        .ssl()
+        .param_mut()
+        .set_hostflags(X509CheckFlags::NO_PARTIAL_WILDCARDS);
+    client.ssl().param_mut().set_host("foobar.com").unwrap();
+    client.connect();
+}
+
+#[test]
+fn verify_invalid_hostname() {
+    let mut server = Server::builder();
+    server.should_error();
+    let server = server.build();
+
+    let mut client = server.client();
+    client.ctx().set_ca_file("test/root-ca.pem").unwrap();
+    client.ctx().set_verify(SslVerifyMode::PEER);
+
+    let mut client = client.build().builder();
+    client
+        .ssl()
+        .param_mut()
+        .set_hostflags(X509CheckFlags::NO_PARTIAL_WILDCARDS);
+    client.ssl().param_mut().set_host("bogus.com").unwrap();
+    client.connect_err();
+}
+
+#[test]
+fn verify_any_added_hostname() {
+    let server = Server::builder().build();
+
+    let mut client = server.client();
+    client.ctx().set_ca_file("test/root-ca.pem").unwrap();
+    client.ctx().set_verify(SslVerifyMode::PEER);
+
+    let mut client = client.build().builder();
+    client
+        .ssl()
+        .set_hostflags(X509CheckFlags::NO_PARTIAL_WILDCARDS);
+    client.ssl().set_host("bogus.com").unwrap();
+    client.ssl().add_host("foobar.com").unwrap();
+    client.connect();
+}
+
+#[test]
+fn connector_valid_hostname() {
+    let server = Server::builder().build();
+
+    let mut connector = SslConnector::builder(SslMethod::tls()).unwrap();
+    connector.set_ca_file("test/root-ca.pem").unwrap();
+
+    let s = server.connect_tcp();
+    let mut s = connector.build().connect("foobar.com", s).unwrap();
+    s.read_exact(&mut [0]).unwrap();
+}
+
+#[test]
+fn connector_invalid_hostname() {
+    let mut server = Server::builder();
+    server.should_error();
+    let server = server.build();
+
+    let mut connector = SslConnector::builder(SslMethod::tls()).unwrap();
+    connector.set_ca_file("test/root-ca.pem").unwrap();
+
+    let s = server.connect_tcp();
+    connector.build().connect("bogus.com", s).unwrap_err();
+}
+
+#[test]
+fn connector_per_connection_verify_cert_store() {
+    let trusted = Server::builder().build();
+    let mut untrusted = Server::builder();
+    untrusted.should_error();
+    let untrusted = untrusted.build();
+
+    let connector = SslConnector::builder(SslMethod::tls()).unwrap().build();
+
+    let root_ca = X509::from_pem(ROOT_CERT).unwrap();
+    let mut store = X509StoreBuilder::new().unwrap();
+    store.add_cert(root_ca).unwrap();
+
+    let mut config = connector.configure().unwrap();
+    config.set_verify_cert_store(store.build()).unwrap();
+    let mut s = config.connect("foobar.com", trusted.connect_tcp()).unwrap();
+    s.read_exact(&mut [0]).unwrap();
+
+    let store = X509StoreBuilder::new().unwrap();
+    let mut config = connector.configure().unwrap();
+    config.set_verify_cert_store(store.build()).unwrap();
+    config
+        .connect("foobar.com", untrusted.connect_tcp())
+        .unwrap_err();
+}
+
+#[test]
+fn connector_invalid_no_hostname_verification() {
+    let server = Server::builder().build();
+
+    let mut connector = SslConnector::builder(SslMethod::tls()).unwrap();
+    connector.set_ca_file("test/root-ca.pem").unwrap();
+
+    let s = server.connect_tcp();
+    let mut s = connector
+        .build()
+        .configure()
+        .unwrap()
+        .verify_hostname(false)
+        .connect("bogus.com", s)
+        .unwrap();
+    s.read_exact(&mut [0]).unwrap();
+}
+
+#[test]
+fn connector_no_hostname_still_verifies() {
     let mut server = Server::builder();
     server.should_error();
     let server = server.build();
 
-    let mut client = server.client();
-    client.ctx().set_ca_file("test/root-ca.pem").unwrap();
-    client.ctx().set_verify(SslVerifyMode::PEER);
+    let connector = SslConnector::builder(SslMethod::tls()).unwrap().build();
+
+    let s = server.connect_tcp();
+    assert!(connector
+        .configure()
+        .unwrap()
+        .verify_hostname(false)
+        .connect("fizzbuzz.com", s)
+        .is_err());
+}
+
+#[test]
+fn connector_no_hostname_can_disable_verify() {
+    let server = Server::builder().build();
+
+    let mut connector = SslConnector::builder(SslMethod::tls()).unwrap();
+    connector.set_verify(SslVerifyMode::NONE);
+    let connector = connector.build();
+
+    let s = server.connect_tcp();
+    let mut s = connector
+        .configure()
+        .unwrap()
+        .verify_hostname(false)
+        .connect("foobar.com", s)
+        .unwrap();
+    s.read_exact(&mut [0]).unwrap();
+}
+
+fn test_mozilla_server(new: fn(SslMethod) -> Result<SslAcceptorBuilder, ErrorStack>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+
+    let t = thread::spawn(move || {
+        let key = PKey::private_key_from_pem(KEY).unwrap();
+        let cert = X509::from_pem(CERT).unwrap();
+        let mut acceptor = new(SslMethod::tls()).unwrap();
+        acceptor.set_private_key(&key).unwrap();
+        acceptor.set_certificate(&cert).unwrap();
+        let acceptor = acceptor.build();
+        let stream = listener.accept().unwrap().0;
+        let mut stream = acceptor.accept(stream).unwrap();
+
+        stream.write_all(b"hello").unwrap();
+    });
+
+    let mut connector = SslConnector::builder(SslMethod::tls()).unwrap();
+    connector.set_ca_file("test/root-ca.pem").unwrap();
+    let connector = connector.build();
+
+    let stream = TcpStream::connect(("127.0.0.1", port)).unwrap();
+    let mut stream = connector.connect("foobar.com", stream).unwrap();
+
+    let mut buf = [0; 5];
+    stream.read_exact(&mut buf).unwrap();
+    assert_eq!(b"hello", &buf);
+
+    t.join().unwrap();
+}
+
+#[test]
+fn connector_client_server_mozilla_intermediate() {
+    test_mozilla_server(SslAcceptor::mozilla_intermediate);
+}
+
+#[test]
+fn connector_client_server_mozilla_modern() {
+    test_mozilla_server(SslAcceptor::mozilla_modern);
+}
+
+#[test]
+fn connector_client_server_mozilla_intermediate_v5() {
+    test_mozilla_server(SslAcceptor::mozilla_intermediate_v5);
+}
+
+#[test]
+fn connector_failure_returns_stream() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let guard = thread::spawn(move || {
+        let mut stream = listener.accept().unwrap().0;
+
+        // Consume the ClientHello record and answer with something that is not TLS.
+        let mut header = [0; 5];
+        stream.read_exact(&mut header).unwrap();
+        let len = u16::from_be_bytes([header[3], header[4]]) as usize;
+        stream.read_exact(&mut vec![0; len]).unwrap();
+        stream
+            .write_all(b"HTTP/1.1 400 Bad Request\r\n\r\n")
+            .unwrap();
+
+        // The client may send an alert before giving up on the handshake.
+        let mut buf = vec![];
+        stream.read_to_end(&mut buf).unwrap();
+        assert!(buf.ends_with(b"ping"));
+    });
+
+    let connector = SslConnector::builder(SslMethod::tls()).unwrap().build();
+    let stream = TcpStream::connect(addr).unwrap();
+    let mut stream = match connector.connect("foobar.com", stream) {
+        Err(HandshakeError::Failure(mid)) => mid.into_source_stream(),
+        _ => panic!("expected a handshake failure"),
+    };
+    assert_eq!(stream.peer_addr().unwrap(), addr);
+    stream.write_all(b"ping").unwrap();
+    drop(stream);
+
+    guard.join().unwrap();
+}
+
+#[test]
+fn peek() {
+    let mut server = Server::builder();
+    server.io_cb(|mut s| s.write_all(b"hello world").unwrap());
+    let server = server.build();
+
+    let mut s = server.client().connect();
+
+    let mut buf = [0; 5];
+    let len = s.ssl_peek(&mut buf).unwrap();
+    assert!(len > 0);
+    assert_eq!(&buf[..len], &b"hello"[..len]);
+
+    let mut buf = [0; 11];
+    s.read_exact(&mut buf).unwrap();
+    assert_eq!(&buf, b"hello world");
+}
+
+#[test]
+fn shutdown() {
+    let mut server = Server::builder();
+    server.io_cb(|mut s| {
+        assert_eq!(s.read(&mut [0]).unwrap(), 0);
+        assert_eq!(s.shutdown().unwrap(), ShutdownResult::Received);
+    });
+    let server = server.build();
+
+    let mut s = server.client().connect();
+
+    assert_eq!(s.get_shutdown(), ShutdownState::empty());
+    assert_eq!(s.shutdown().unwrap(), ShutdownResult::Sent);
+    assert_eq!(s.get_shutdown(), ShutdownState::SENT);
+    assert_eq!(s.shutdown().unwrap(), ShutdownResult::Received);
+    assert_eq!(
+        s.get_shutdown(),
+        ShutdownState::SENT | ShutdownState::RECEIVED
+    );
+}
+
+#[test]
+fn quiet_shutdown() {
+    let mut server = Server::builder();
+    server.io_cb(|mut s| {
+        // The peer closed the connection without a close_notify alert.
+        let err = s.ssl_read(&mut [0]).unwrap_err();
+        assert_ne!(err.code(), ErrorCode::ZERO_RETURN);
+        assert!(!s.get_shutdown().contains(ShutdownState::RECEIVED));
+    });
+    let server = server.build();
+
+    let mut client = server.client();
+    client.ctx().set_quiet_shutdown(true);
+    let client = client.build();
+    let mut builder = client.builder();
+    assert!(builder.ssl().quiet_shutdown());
+    builder.ssl().set_quiet_shutdown(false);
+    assert!(!builder.ssl().quiet_shutdown());
+    builder.ssl().set_quiet_shutdown(true);
+    let mut s = builder.connect();
+
+    assert_eq!(s.shutdown().unwrap(), ShutdownResult::Received);
+    assert_eq!(
+        s.get_shutdown(),
+        ShutdownState::SENT | ShutdownState::RECEIVED
+    );
+}
+
+#[test]
+fn client_ca_list() {
+    let names = X509Name::load_client_ca_file("test/root-ca.pem").unwrap();
+    assert_eq!(names.len(), 1);
+
+    let mut ctx = SslContext::builder(SslMethod::tls()).unwrap();
+    ctx.set_client_ca_list(names);
+}
+
+#[test]
+fn cert_store() {
+    let server = Server::builder().build();
+
+    let mut client = server.client();
+    let cert = X509::from_pem(ROOT_CERT).unwrap();
+    client.ctx().cert_store_mut().add_cert(cert).unwrap();
+    client.ctx().set_verify(SslVerifyMode::PEER);
+
+    client.connect();
+}
+
+#[test]
+fn idle_session() {
+    let ctx = SslContext::builder(SslMethod::tls()).unwrap().build();
+    let ssl = Ssl::new(&ctx).unwrap();
+    assert!(ssl.session().is_none());
+}
+
+#[test]
+fn active_session() {
+    let server = Server::builder().build();
+
+    let s = server.client().connect();
+
+    let session = s.ssl().session().unwrap();
+    let len = session.master_key_len();
+    let mut buf = vec![0; len - 1];
+    let copied = session.master_key(&mut buf);
+    assert_eq!(copied, buf.len());
+    let mut buf = vec![0; len + 1];
+    let copied = session.master_key(&mut buf);
+    assert_eq!(copied, len);
+}
+
+#[test]
+fn new_session_callback() {
+    static CALLED_BACK: AtomicBool = AtomicBool::new(false);
+
+    let mut server = Server::builder();
+    server.ctx().set_session_id_context(b"foo").unwrap();
+
+    let server = server.build();
+
+    let mut client = server.client();
+
+    client
+        .ctx()
+        .set_session_cache_mode(SslSessionCacheMode::CLIENT | SslSessionCacheMode::NO_INTERNAL);
+    client
+        .ctx()
+        .set_new_session_callback(|_, _| CALLED_BACK.store(true, Ordering::SeqCst));
+
+    client.connect();
+
+    assert!(CALLED_BACK.load(Ordering::SeqCst));
+}
+
+#[test]
+fn external_session_cache() {
+    static LOOKUPS: AtomicUsize = AtomicUsize::new(0);
+
+    let cache = Arc::new(Mutex::new(HashMap::<Vec<u8>, SslSession>::new()));
+
+    let mut server = Server::builder();
+    server.expected_connections_count(2);
+    server
+        .ctx()
+        .set_max_proto_version(Some(SslVersion::TLS1_2))
+        .unwrap();
+    server.ctx().set_options(SslOptions::NO_TICKET);
+    server.ctx().set_session_id_context(b"foo").unwrap();
+    server
+        .ctx()
+        .set_session_cache_mode(SslSessionCacheMode::SERVER | SslSessionCacheMode::NO_INTERNAL);
+    let new_cache = cache.clone();
+    server.ctx().set_new_session_callback(move |_, session| {
+        new_cache
+            .lock()
+            .unwrap()
+            .insert(session.id().to_vec(), session);
+    });
+    let remove_cache = cache.clone();
+    server.ctx().set_remove_session_callback(move |_, session| {
+        remove_cache.lock().unwrap().remove(session.id());
+    });
+    let get_cache = cache.clone();
+    unsafe {
+        server.ctx().set_get_session_callback(move |_, id| {
+            LOOKUPS.fetch_add(1, Ordering::SeqCst);
+            get_cache.lock().unwrap().get(id).cloned()
+        });
+    }
+    let server = server.build();
+
+    let client = server.client().build();
+
+    let s = client.builder().connect();
+    assert!(!s.ssl().session_reused());
+    let session = s.ssl().session().unwrap().to_owned();
+    assert!(cache.lock().unwrap().contains_key(session.id()));
+
+    let mut builder = client.builder();
+    unsafe { builder.ssl().set_session(&session).unwrap() };
+    let s = builder.connect();
+    assert!(s.ssl().session_reused());
+    assert_eq!(LOOKUPS.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn new_session_callback_swapped_ctx() {
+    static CALLED_BACK: AtomicBool = AtomicBool::new(false);
+
+    let mut server = Server::builder();
+    server.ctx().set_session_id_context(b"foo").unwrap();
+
+    let server = server.build();
+
+    let mut client = server.client();
+
+    client
+        .ctx()
+        .set_session_cache_mode(SslSessionCacheMode::CLIENT | SslSessionCacheMode::NO_INTERNAL);
+    client
+        .ctx()
+        .set_new_session_callback(|_, _| CALLED_BACK.store(true, Ordering::SeqCst));
+
+    let mut client = client.build().builder();
+
+    let ctx = SslContextBuilder::new(SslMethod::tls()).unwrap().build();
+    client.ssl().set_ssl_context(&ctx).unwrap();
+
+    client.connect();
+
+    assert!(CALLED_BACK.load(Ordering::SeqCst));
+}
+
+#[test]
+fn set_session() {
+    let mut server = Server::builder();
+    server
+        .ctx()
+        .set_max_proto_version(Some(SslVersion::TLS1_2))
+        .unwrap();
+    server.expected_connections_count(2);
+    let server = server.build();
+
+    let client = server.client().build();
 
-    let mut client = client.build().builder();
-    client
-        .ssl()
-        .param_mut()
-        .set_hostflags(X509CheckFlags::NO_PARTIAL_WILDCARDS);
-    client.ssl().param_mut().set_host("bogus.com").unwrap();
-    client.connect_err();
+    let s = client.builder().connect();
+    assert!(!s.ssl().session_reused());
+    let session = s.ssl().session().unwrap().to_owned();
+
+    let mut builder = client.builder();
+    unsafe { builder.ssl().set_session(&session).unwrap() };
+    let s = builder.connect();
+    assert!(s.ssl().session_reused());
 }
 
 #[test]
-fn connector_valid_hostname() {
-    let server = Server::builder().build();
+fn reverify_on_resume() {
+    let mut store = X509StoreBuilder::new().unwrap();
+    store.add_cert(X509::from_pem(ROOT_CERT).unwrap()).unwrap();
+    let trust = Arc::new(Mutex::new(store.build()));
 
-    let mut connector = SslConnector::builder(SslMethod::tls()).unwrap();
-    connector.set_ca_file("test/root-ca.pem").unwrap();
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
 
-    let s = server.connect_tcp();
-    let mut s = connector.build().connect("foobar.com", s).unwrap();
-    s.read_exact(&mut [0]).unwrap();
+    let guard = thread::spawn(move || {
+        let mut ctx = SslContext::builder(SslMethod::tls()).unwrap();
+        ctx.set_max_proto_version(Some(SslVersion::TLS1_2)).unwrap();
+        ctx.set_certificate_file(Path::new("test/cert.pem"), SslFiletype::PEM)
+            .unwrap();
+        ctx.set_private_key_file(Path::new("test/key.pem"), SslFiletype::PEM)
+            .unwrap();
+        let ctx = ctx.build();
+
+        for _ in 0..3 {
+            let stream = listener.accept().unwrap().0;
+            // the last handshake is expected to be rejected by the client
+            let _ = Ssl::new(&ctx).unwrap().accept(stream);
+        }
+    });
+
+    let mut ctx = SslContext::builder(SslMethod::tls()).unwrap();
+    let trust2 = trust.clone();
+    ctx.set_custom_verify_callback(SslVerifyMode::PEER, move |ssl| {
+        let cert = ssl.peer_certificate().unwrap();
+        let chain = ssl.peer_cert_chain().unwrap();
+        let trust = trust2.lock().unwrap();
+        let mut context = X509StoreContext::new().unwrap();
+        match context.init(&trust, &cert, chain, |c| c.verify_cert()) {
+            Ok(true) => Ok(()),
+            _ => Err(SslAlert::BAD_CERTIFICATE),
+        }
+    });
+    ctx.set_reverify_on_resume(true);
+    let ctx = ctx.build();
+
+    let stream = TcpStream::connect(addr).unwrap();
+    let s = Ssl::new(&ctx).unwrap().connect(stream).unwrap();
+    assert!(!s.ssl().session_reused());
+    let session = s.ssl().session().unwrap().to_owned();
+
+    let mut ssl = Ssl::new(&ctx).unwrap();
+    unsafe { ssl.set_session(&session).unwrap() };
+    let s = ssl.connect(TcpStream::connect(addr).unwrap()).unwrap();
+    assert!(s.ssl().session_reused());
+
+    // Stop trusting the root, so that the resumed session's certificate no longer verifies.
+    *trust.lock().unwrap() = X509StoreBuilder::new().unwrap().build();
+
+    let mut ssl = Ssl::new(&ctx).unwrap();
+    unsafe { ssl.set_session(&session).unwrap() };
+    ssl.connect(TcpStream::connect(addr).unwrap()).unwrap_err();
+
+    guard.join().unwrap();
 }
 
 #[test]
-fn connector_invalid_hostname() {
+fn custom_verify_alert() {
+    assert_eq!(
+        SslAlert::from_verify_result(X509VerifyResult::CERT_REVOKED),
+        SslAlert::CERTIFICATE_REVOKED
+    );
+    assert_eq!(
+        SslAlert::from_verify_result(X509VerifyResult::CERT_HAS_EXPIRED),
+        SslAlert::CERTIFICATE_EXPIRED
+    );
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let guard = thread::spawn(move || {
+        let mut ctx = SslContext::builder(SslMethod::tls()).unwrap();
+        ctx.set_certificate_file(Path::new("test/cert.pem"), SslFiletype::PEM)
+            .unwrap();
+        ctx.set_private_key_file(Path::new("test/key.pem"), SslFiletype::PEM)
+            .unwrap();
+        let ctx = ctx.build();
+
+        let stream = listener.accept().unwrap().0;
+        let error = match Ssl::new(&ctx).unwrap().accept(stream) {
+            Err(HandshakeError::Failure(mid)) => mid.into_error(),
+            _ => panic!("expected the client to reject the certificate"),
+        };
+        let errors = error.ssl_error().unwrap().errors();
+        assert!(errors
+            .iter()
+            .any(|e| e.reason() == Some("SSLV3_ALERT_CERTIFICATE_REVOKED")));
+    });
+
+    let mut ctx = SslContext::builder(SslMethod::tls()).unwrap();
+    ctx.set_custom_verify_callback(SslVerifyMode::PEER, |_| Err(SslAlert::CERTIFICATE_REVOKED));
+    let ctx = ctx.build();
+    let ssl = Ssl::new(&ctx).unwrap();
+    ssl.connect(TcpStream::connect(addr).unwrap()).unwrap_err();
+
+    guard.join().unwrap();
+}
+
+#[test]
+fn ocsp_callback() {
     let mut server = Server::builder();
-    server.should_error();
+    server
+        .ctx()
+        .set_ocsp_callback(|ssl| {
+            Ok(ssl
+                .servername(ssl::NameType::HOST_NAME)
+                .map(|name| format!("staple for {}", name).into_bytes()))
+        })
+        .unwrap();
+    server.expected_connections_count(3);
     let server = server.build();
 
-    let mut connector = SslConnector::builder(SslMethod::tls()).unwrap();
-    connector.set_ca_file("test/root-ca.pem").unwrap();
+    let client = server.client().build();
 
-    let s = server.connect_tcp();
-    connector.build().connect("bogus.com", s).unwrap_err();
+    for name in &["foo.com", "bar.com"] {
+        let mut builder = client.builder();
+        builder.ssl().set_hostname(name).unwrap();
+        builder.ssl().set_status_type(StatusType::OCSP).unwrap();
+        let s = builder.connect();
+        let expected = format!("staple for {}", name);
+        assert_eq!(s.ssl().ocsp_status(), Some(expected.as_bytes()));
+    }
+
+    let mut builder = client.builder();
+    builder.ssl().set_status_type(StatusType::OCSP).unwrap();
+    let s = builder.connect();
+    assert_eq!(s.ssl().ocsp_status(), None);
 }
 
 #[test]
-fn connector_invalid_no_hostname_verification() {
-    let server = Server::builder().build();
+fn verify_algorithm_prefs() {
+    let cases: &[(&[SslSignatureAlgorithm], bool)] = &[
+        (
+            &[
+                SslSignatureAlgorithm::RSA_PKCS1_SHA256,
+                SslSignatureAlgorithm::RSA_PKCS1_SHA1,
+            ],
+            true,
+        ),
+        (
+            &[
+                SslSignatureAlgorithm::RSA_PKCS1_SHA256,
+                SslSignatureAlgorithm::RSA_PSS_RSAE_SHA256,
+            ],
+            false,
+        ),
+    ];
+
+    for &(prefs, accepted) in cases {
+        // the server can only sign with RSA-PKCS1-SHA1
+        let mut server = Server::builder();
+        server
+            .ctx()
+            .set_max_proto_version(Some(SslVersion::TLS1_2))
+            .unwrap();
+        server.ctx().set_sigalgs_list("RSA+SHA1").unwrap();
+        if !accepted {
+            server.should_error();
+        }
+        let server = server.build();
+
+        let mut client = server.client();
+        client.ctx().set_verify_algorithm_prefs(prefs).unwrap();
+        if accepted {
+            client.connect();
+        } else {
+            client.connect_err();
+        }
+    }
+}
 
-    let mut connector = SslConnector::builder(SslMethod::tls()).unwrap();
-    connector.set_ca_file("test/root-ca.pem").unwrap();
+#[test]
+fn check_signing_algorithm_prefs() {
+    let mut ctx = SslContext::builder(SslMethod::tls()).unwrap();
+    ctx.set_signing_algorithm_prefs(&[SslSignatureAlgorithm::ECDSA_SECP256R1_SHA256])
+        .unwrap();
+    // Nothing can be checked until a key is loaded.
+    ctx.check_signing_algorithm_prefs().unwrap();
 
-    let s = server.connect_tcp();
-    let mut s = connector
-        .build()
-        .configure()
-        .unwrap()
-        .verify_hostname(false)
-        .connect("bogus.com", s)
+    ctx.set_certificate_file(Path::new("test/cert.pem"), SslFiletype::PEM)
         .unwrap();
-    s.read_exact(&mut [0]).unwrap();
+    ctx.set_private_key_file(Path::new("test/key.pem"), SslFiletype::PEM)
+        .unwrap();
+    let err = ctx.check_signing_algorithm_prefs().unwrap_err();
+    assert_eq!(
+        err.errors()[0].reason(),
+        Some("NO_COMMON_SIGNATURE_ALGORITHMS")
+    );
+
+    ctx.set_signing_algorithm_prefs(&[
+        SslSignatureAlgorithm::ECDSA_SECP256R1_SHA256,
+        SslSignatureAlgorithm::RSA_PSS_RSAE_SHA256,
+    ])
+    .unwrap();
+    ctx.check_signing_algorithm_prefs().unwrap();
+
+    // ECDSA algorithms are bound to a curve.
+    let mut ctx = SslContext::builder(SslMethod::tls()).unwrap();
+    let key = PKey::generate_ec(Nid::SECP384R1).unwrap();
+    ctx.set_private_key(&key).unwrap();
+    ctx.set_signing_algorithm_prefs(&[SslSignatureAlgorithm::ECDSA_SECP256R1_SHA256])
+        .unwrap();
+    let err = ctx.check_signing_algorithm_prefs().unwrap_err();
+    assert_eq!(
+        err.errors()[0].reason(),
+        Some("NO_COMMON_SIGNATURE_ALGORITHMS")
+    );
+
+    ctx.set_signing_algorithm_prefs(&[SslSignatureAlgorithm::ECDSA_SECP384R1_SHA384])
+        .unwrap();
+    ctx.check_signing_algorithm_prefs().unwrap();
 }
 
 #[test]
-fn connector_no_hostname_still_verifies() {
+fn key_update() {
     let mut server = Server::builder();
-    server.should_error();
+    server.io_cb(|mut s| {
+        let mut buf = [0; 4];
+        for _ in 0..2 {
+            s.read_exact(&mut buf).unwrap();
+            s.write_all(&buf).unwrap();
+        }
+        s.ssl_mut()
+            .key_update(KeyUpdateRequest::NOT_REQUESTED)
+            .unwrap();
+        s.write_all(b"done").unwrap();
+    });
     let server = server.build();
 
-    let connector = SslConnector::builder(SslMethod::tls()).unwrap().build();
+    let mut s = server.client().connect();
+    assert_eq!(s.ssl().version2(), Some(SslVersion::TLS1_3));
+    let mut buf = [0; 4];
 
-    let s = server.connect_tcp();
-    assert!(connector
-        .configure()
-        .unwrap()
-        .verify_hostname(false)
-        .connect("fizzbuzz.com", s)
-        .is_err());
+    s.write_all(b"ping").unwrap();
+    s.read_exact(&mut buf).unwrap();
+    assert_eq!(&buf, b"ping");
+
+    s.ssl_mut().key_update(KeyUpdateRequest::REQUESTED).unwrap();
+    s.write_all(b"pong").unwrap();
+    s.read_exact(&mut buf).unwrap();
+    assert_eq!(&buf, b"pong");
+
+    s.read_exact(&mut buf).unwrap();
+    assert_eq!(&buf, b"done");
 }
 
 #[test]
-fn connector_no_hostname_can_disable_verify() {
-    let server = Server::builder().build();
+fn handshake_randoms() {
+    let mut server = Server::builder();
+    server.io_cb(|mut s| {
+        let mut buf = [0; 64];
+        assert_eq!(s.ssl().client_random(&mut []), 32);
+        assert_eq!(s.ssl().client_random(&mut buf[..32]), 32);
+        assert_eq!(s.ssl().server_random(&mut buf[32..]), 32);
+        s.write_all(&buf).unwrap();
+    });
+    let server = server.build();
 
-    let mut connector = SslConnector::builder(SslMethod::tls()).unwrap();
-    connector.set_verify(SslVerifyMode::NONE);
-    let connector = connector.build();
+    let mut s = server.client().connect();
+    let mut client_random = [0; 32];
+    let mut server_random = [0; 32];
+    assert_eq!(s.ssl().client_random(&mut client_random), 32);
+    assert_eq!(s.ssl().server_random(&mut server_random), 32);
+    assert_ne!(client_random, server_random);
+
+    let mut buf = [0; 64];
+    s.read_exact(&mut buf).unwrap();
+    assert_eq!(&buf[..32], &client_random[..]);
+    assert_eq!(&buf[32..], &server_random[..]);
+}
 
-    let s = server.connect_tcp();
-    let mut s = connector
-        .configure()
-        .unwrap()
-        .verify_hostname(false)
-        .connect("foobar.com", s)
+#[test]
+fn session_timeout() {
+    let mut server = Server::builder();
+    server
+        .ctx()
+        .set_max_proto_version(Some(SslVersion::TLS1_2))
         .unwrap();
-    s.read_exact(&mut [0]).unwrap();
+    assert_eq!(
+        server.ctx().set_session_timeout(Duration::from_secs(60)),
+        Duration::from_secs(2 * 60 * 60)
+    );
+    assert_eq!(
+        server.ctx().set_session_timeout(Duration::from_secs(1)),
+        Duration::from_secs(60)
+    );
+    server.ssl_cb(|ssl| {
+        assert_eq!(ssl.ssl_context().session_timeout(), Duration::from_secs(1));
+    });
+    server.expected_connections_count(3);
+    let server = server.build();
+
+    let client = server.client().build();
+
+    let s = client.builder().connect();
+    let session = s.ssl().session().unwrap().to_owned();
+
+    // the session resumes while it is still valid
+    let mut builder = client.builder();
+    unsafe { builder.ssl().set_session(&session).unwrap() };
+    let s = builder.connect();
+    assert!(s.ssl().session_reused());
+
+    // the server honors the lifetime of the session it issued
+    thread::sleep(Duration::from_secs(2));
+
+    let mut builder = client.builder();
+    unsafe { builder.ssl().set_session(&session).unwrap() };
+    let s = builder.connect();
+    assert!(!s.ssl().session_reused());
 }
 
-fn test_mozilla_server(new: fn(SslMethod) -> Result<SslAcceptorBuilder, ErrorStack>) {
-    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
-    let port = listener.local_addr().unwrap().port();
+#[test]
+fn verify_mode() {
+    let mode = SslVerifyMode::PEER | SslVerifyMode::FAIL_IF_NO_PEER_CERT;
 
-    let t = thread::spawn(move || {
-        let key = PKey::private_key_from_pem(KEY).unwrap();
-        let cert = X509::from_pem(CERT).unwrap();
-        let mut acceptor = new(SslMethod::tls()).unwrap();
-        acceptor.set_private_key(&key).unwrap();
-        acceptor.set_certificate(&cert).unwrap();
-        let acceptor = acceptor.build();
-        let stream = listener.accept().unwrap().0;
-        let mut stream = acceptor.accept(stream).unwrap();
+    let mut ctx = SslContext::builder(SslMethod::tls()).unwrap();
+    ctx.set_verify(mode);
+    let ctx = ctx.build();
+    assert_eq!(ctx.verify_mode(), mode);
 
-        stream.write_all(b"hello").unwrap();
+    let mut ssl = Ssl::new(&ctx).unwrap();
+    assert_eq!(ssl.verify_mode(), mode);
+    ssl.set_verify(SslVerifyMode::NONE);
+    assert_eq!(ssl.verify_mode(), SslVerifyMode::NONE);
+    assert_eq!(ctx.verify_mode(), mode);
+}
+
+#[test]
+fn tmp_ecdh() {
+    let mut server = Server::builder();
+    server
+        .ctx()
+        .set_max_proto_version(Some(SslVersion::TLS1_2))
+        .unwrap();
+    let key = EcKey::from_curve_name(Nid::SECP384R1).unwrap();
+    server.ctx().set_tmp_ecdh(&key).unwrap();
+    server.io_cb(|s| {
+        assert_eq!(s.ssl().curve_name(), Some("P-384"));
     });
+    let server = server.build();
 
-    let mut connector = SslConnector::builder(SslMethod::tls()).unwrap();
-    connector.set_ca_file("test/root-ca.pem").unwrap();
-    let connector = connector.build();
+    let s = server.client().connect();
+    assert_eq!(s.ssl().version2(), Some(SslVersion::TLS1_2));
+    assert_eq!(s.ssl().curve_name(), Some("P-384"));
+}
 
-    let stream = TcpStream::connect(("127.0.0.1", port)).unwrap();
-    let mut stream = connector.connect("foobar.com", stream).unwrap();
+#[test]
+fn session_der_round_trip() {
+    let mut server = Server::builder();
+    server
+        .ctx()
+        .set_max_proto_version(Some(SslVersion::TLS1_2))
+        .unwrap();
+    server.expected_connections_count(2);
+    let server = server.build();
 
-    let mut buf = [0; 5];
-    stream.read_exact(&mut buf).unwrap();
-    assert_eq!(b"hello", &buf);
+    let client = server.client().build();
 
-    t.join().unwrap();
+    let s = client.builder().connect();
+    let der = s.ssl().session().unwrap().to_der().unwrap();
+
+    assert!(SslSession::from_der(&der[..der.len() - 1]).is_err());
+    let mut trailing = der.clone();
+    trailing.push(0);
+    assert!(SslSession::from_der(&trailing).is_err());
+    assert!(SslSession::from_der(b"not a session").is_err());
+
+    let session = SslSession::from_der(&der).unwrap();
+    assert_eq!(session.id(), s.ssl().session().unwrap().id());
+
+    let mut builder = client.builder();
+    unsafe { builder.ssl().set_session(&session).unwrap() };
+    let s = builder.connect();
+    assert!(s.ssl().session_reused());
 }
 
 #[test]
-fn connector_client_server_mozilla_intermediate() {
-    test_mozilla_server(SslAcceptor::mozilla_intermediate);
+fn read_early_data() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let mut ctx = SslContext::builder(SslMethod::tls()).unwrap();
+    ctx.set_certificate_file(Path::new("test/cert.pem"), SslFiletype::PEM)
+        .unwrap();
+    ctx.set_private_key_file(Path::new("test/key.pem"), SslFiletype::PEM)
+        .unwrap();
+    ctx.set_early_data_enabled(true);
+    let server_ctx = ctx.build();
+
+    let guard = thread::spawn(move || {
+        // The first connection issues a ticket which permits early data.
+        let stream = listener.accept().unwrap().0;
+        let mut stream = Ssl::new(&server_ctx).unwrap().accept(stream).unwrap();
+        stream.write_all(&[0]).unwrap();
+        stream.read_exact(&mut [0]).unwrap();
+
+        let stream = listener.accept().unwrap().0;
+        let mut stream = Ssl::new(&server_ctx).unwrap().accept(stream).unwrap();
+        assert!(stream.ssl().in_early_data());
+
+        let mut buf = [0; 5];
+        let mut len = 0;
+        while len < buf.len() {
+            match stream.read_early_data(&mut buf[len..]).unwrap() {
+                ReadEarlyDataResult::Success(n) => len += n,
+                ReadEarlyDataResult::Finished => panic!("early data ended prematurely"),
+            }
+        }
+        assert_eq!(&buf, b"hello");
+        stream.write_all(&[1]).unwrap();
+
+        // The data sent after the early data is not reported as early data, but kept for the
+        // next read.
+        assert_eq!(
+            stream.read_early_data(&mut buf).unwrap(),
+            ReadEarlyDataResult::Finished
+        );
+        assert!(!stream.ssl().in_early_data());
+        stream.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"world");
+        assert_eq!(stream.bytes_read(), 10);
+        assert_eq!(
+            stream.read_early_data(&mut buf).unwrap(),
+            ReadEarlyDataResult::Finished
+        );
+        assert!(stream.ssl().session_reused());
+        assert!(stream.ssl().early_data_accepted());
+    });
+
+    let session = Arc::new(Mutex::new(None));
+
+    let mut ctx = SslContext::builder(SslMethod::tls()).unwrap();
+    ctx.set_session_cache_mode(SslSessionCacheMode::CLIENT | SslSessionCacheMode::NO_INTERNAL);
+    ctx.set_early_data_enabled(true);
+    let session2 = session.clone();
+    ctx.set_new_session_callback(move |_, s| *session2.lock().unwrap() = Some(s));
+    let client_ctx = ctx.build();
+
+    let stream = TcpStream::connect(addr).unwrap();
+    let mut stream = Ssl::new(&client_ctx).unwrap().connect(stream).unwrap();
+    stream.read_exact(&mut [0]).unwrap();
+    stream.write_all(&[0]).unwrap();
+    let session = session.lock().unwrap().take().unwrap();
+
+    let mut ssl = Ssl::new(&client_ctx).unwrap();
+    unsafe { ssl.set_session(&session).unwrap() };
+    let stream = TcpStream::connect(addr).unwrap();
+    let mut stream = ssl.connect(stream).unwrap();
+    assert!(stream.ssl().in_early_data());
+    stream.write_all(b"hello").unwrap();
+
+    stream.read_exact(&mut [0]).unwrap();
+    assert!(stream.ssl().early_data_accepted());
+    stream.write_all(b"world").unwrap();
+
+    guard.join().unwrap();
 }
 
 #[test]
-fn connector_client_server_mozilla_modern() {
-    test_mozilla_server(SslAcceptor::mozilla_modern);
-}
+fn false_start_without_alpn() {
+    let mut server = Server::builder();
+    server
+        .ctx()
+        .set_max_proto_version(Some(SslVersion::TLS1_2))
+        .unwrap();
+    server.expected_connections_count(2);
+    let server = server.build();
+
+    for &allowed in &[false, true] {
+        let mut ctx = SslContext::builder(SslMethod::tls()).unwrap();
+        ctx.set_mode(SslMode::ENABLE_FALSE_START);
+        ctx.set_false_start_allowed_without_alpn(allowed);
+        let ctx = ctx.build();
+
+        let mut s = Ssl::new(&ctx)
+            .unwrap()
+            .connect(server.connect_tcp())
+            .unwrap();
+        assert_eq!(s.ssl().in_false_start(), allowed);
 
-#[test]
-fn connector_client_server_mozilla_intermediate_v5() {
-    test_mozilla_server(SslAcceptor::mozilla_intermediate_v5);
+        s.read_exact(&mut [0]).unwrap();
+        assert!(!s.ssl().in_false_start());
+    }
 }
 
 #[test]
-fn shutdown() {
+fn session_max_early_data() {
     let mut server = Server::builder();
-    server.io_cb(|mut s| {
-        assert_eq!(s.read(&mut [0]).unwrap(), 0);
-        assert_eq!(s.shutdown().unwrap(), ShutdownResult::Received);
-    });
+    server.ctx().set_early_data_enabled(true);
+    server.expected_connections_count(2);
     let server = server.build();
 
-    let mut s = server.client().connect();
+    let session = Arc::new(Mutex::new(None));
 
-    assert_eq!(s.get_shutdown(), ShutdownState::empty());
-    assert_eq!(s.shutdown().unwrap(), ShutdownResult::Sent);
-    assert_eq!(s.get_shutdown(), ShutdownState::SENT);
-    assert_eq!(s.shutdown().unwrap(), ShutdownResult::Received);
-    assert_eq!(
-        s.get_shutdown(),
-        ShutdownState::SENT | ShutdownState::RECEIVED
-    );
+    let mut client = server.client();
+    client
+        .ctx()
+        .set_session_cache_mode(SslSessionCacheMode::CLIENT | SslSessionCacheMode::NO_INTERNAL);
+    let session2 = session.clone();
+    client
+        .ctx()
+        .set_new_session_callback(move |_, s| *session2.lock().unwrap() = Some(s));
+    let client = client.build();
+
+    client.builder().connect();
+    let session = session.lock().unwrap().take().unwrap();
+    assert_eq!(session.max_early_data(), 14336);
+
+    let mut builder = client.builder();
+    unsafe { builder.ssl().set_session(&session).unwrap() };
+    let s = builder.connect();
+    assert!(s.ssl().session_reused());
+    assert_eq!(s.ssl().session().unwrap().max_early_data(), 14336);
 }
 
 #[test]
-fn client_ca_list() {
-    let names = X509Name::load_client_ca_file("test/root-ca.pem").unwrap();
-    assert_eq!(names.len(), 1);
-
-    let mut ctx = SslContext::builder(SslMethod::tls()).unwrap();
-    ctx.set_client_ca_list(names);
-}
+fn session_max_early_data_disabled() {
+    let session = Arc::new(Mutex::new(None));
 
-#[test]
-fn cert_store() {
     let server = Server::builder().build();
 
     let mut client = server.client();
-    let cert = X509::from_pem(ROOT_CERT).unwrap();
-    client.ctx().cert_store_mut().add_cert(cert).unwrap();
-    client.ctx().set_verify(SslVerifyMode::PEER);
+    client
+        .ctx()
+        .set_session_cache_mode(SslSessionCacheMode::CLIENT | SslSessionCacheMode::NO_INTERNAL);
+    let session2 = session.clone();
+    client
+        .ctx()
+        .set_new_session_callback(move |_, s| *session2.lock().unwrap() = Some(s));
 
     client.connect();
+    let session = session.lock().unwrap().take().unwrap();
+    assert_eq!(session.max_early_data(), 0);
 }
 
 #[test]
-fn idle_session() {
-    let ctx = SslContext::builder(SslMethod::tls()).unwrap().build();
-    let ssl = Ssl::new(&ctx).unwrap();
-    assert!(ssl.session().is_none());
-}
+fn session_id_context_isolates_early_data() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
 
-#[test]
-fn active_session() {
-    let server = Server::builder().build();
+    let mut ctx = SslContext::builder(SslMethod::tls()).unwrap();
+    ctx.set_certificate_file(Path::new("test/cert.pem"), SslFiletype::PEM)
+        .unwrap();
+    ctx.set_private_key_file(Path::new("test/key.pem"), SslFiletype::PEM)
+        .unwrap();
+    ctx.set_early_data_enabled(true);
+    let server_ctx = ctx.build();
 
-    let s = server.client().connect();
+    let guard = thread::spawn(move || {
+        // The first connection issues a ticket scoped to context A.
+        let stream = listener.accept().unwrap().0;
+        let mut ssl = Ssl::new(&server_ctx).unwrap();
+        assert!(ssl.set_session_id_context(&[0; 33]).is_err());
+        ssl.set_session_id_context(b"A").unwrap();
+        let mut stream = ssl.accept(stream).unwrap();
+        stream.write_all(&[0]).unwrap();
+        stream.read_exact(&mut [0]).unwrap();
 
-    let session = s.ssl().session().unwrap();
-    let len = session.master_key_len();
-    let mut buf = vec![0; len - 1];
-    let copied = session.master_key(&mut buf);
-    assert_eq!(copied, buf.len());
-    let mut buf = vec![0; len + 1];
-    let copied = session.master_key(&mut buf);
-    assert_eq!(copied, len);
-}
+        // The ticket does not resume under context B, so its early data is rejected.
+        let stream = listener.accept().unwrap().0;
+        let mut ssl = Ssl::new(&server_ctx).unwrap();
+        ssl.set_session_id_context(b"B").unwrap();
+        let mut stream = ssl.accept(stream).unwrap();
+        assert!(!stream.ssl().session_reused());
+        assert!(!stream.ssl().early_data_accepted());
+        stream.write_all(&[1]).unwrap();
+        stream.read_exact(&mut [0]).unwrap();
+    });
 
-#[test]
-fn new_session_callback() {
-    static CALLED_BACK: AtomicBool = AtomicBool::new(false);
+    let session = Arc::new(Mutex::new(None));
 
-    let mut server = Server::builder();
-    server.ctx().set_session_id_context(b"foo").unwrap();
+    let mut ctx = SslContext::builder(SslMethod::tls()).unwrap();
+    ctx.set_session_cache_mode(SslSessionCacheMode::CLIENT | SslSessionCacheMode::NO_INTERNAL);
+    ctx.set_early_data_enabled(true);
+    let session2 = session.clone();
+    ctx.set_new_session_callback(move |_, s| *session2.lock().unwrap() = Some(s));
+    let client_ctx = ctx.build();
 
-    let server = server.build();
+    let stream = TcpStream::connect(addr).unwrap();
+    let mut stream = Ssl::new(&client_ctx).unwrap().connect(stream).unwrap();
+    stream.read_exact(&mut [0]).unwrap();
+    stream.write_all(&[0]).unwrap();
+    let session = session.lock().unwrap().take().unwrap();
 
-    let mut client = server.client();
+    let mut ssl = Ssl::new(&client_ctx).unwrap();
+    unsafe { ssl.set_session(&session).unwrap() };
+    let stream = TcpStream::connect(addr).unwrap();
+    let mut stream = ssl.connect(stream).unwrap();
+    assert!(stream.ssl().in_early_data());
+    stream.write_all(b"hello").unwrap();
 
-    client
-        .ctx()
-        .set_session_cache_mode(SslSessionCacheMode::CLIENT | SslSessionCacheMode::NO_INTERNAL);
-    client
-        .ctx()
-        .set_new_session_callback(|_, _| CALLED_BACK.store(true, Ordering::SeqCst));
+    let err = stream.ssl_read(&mut [0]).unwrap_err();
+    assert_eq!(err.code(), ErrorCode::EARLY_DATA_REJECTED);
+    stream.ssl_mut().reset_early_data_reject();
 
-    client.connect();
+    stream.read_exact(&mut [0]).unwrap();
+    assert!(!stream.ssl().session_reused());
+    assert!(!stream.ssl().early_data_accepted());
+    stream.write_all(&[2]).unwrap();
 
-    assert!(CALLED_BACK.load(Ordering::SeqCst));
+    guard.join().unwrap();
 }
 
 #[test]
-fn new_session_callback_swapped_ctx() {
-    static CALLED_BACK: AtomicBool = AtomicBool::new(false);
-
+fn quic_early_data_context_ignored_over_tls() {
     let mut server = Server::builder();
-    server.ctx().set_session_id_context(b"foo").unwrap();
-
+    server.ctx().set_early_data_enabled(true);
+    server.expected_connections_count(2);
+    let mut contexts = vec![&b"B"[..], &b"A"[..]];
+    server.ssl_cb(move |ssl| {
+        ssl.set_quic_early_data_context(contexts.pop().unwrap())
+            .unwrap()
+    });
     let server = server.build();
 
-    let mut client = server.client();
+    let session = Arc::new(Mutex::new(None));
 
+    let mut client = server.client();
     client
         .ctx()
         .set_session_cache_mode(SslSessionCacheMode::CLIENT | SslSessionCacheMode::NO_INTERNAL);
+    let session2 = session.clone();
     client
         .ctx()
-        .set_new_session_callback(|_, _| CALLED_BACK.store(true, Ordering::SeqCst));
-
-    let mut client = client.build().builder();
-
-    let ctx = SslContextBuilder::new(SslMethod::tls()).unwrap().build();
-    client.ssl().set_ssl_context(&ctx).unwrap();
+        .set_new_session_callback(move |_, s| *session2.lock().unwrap() = Some(s));
+    let client = client.build();
 
-    client.connect();
+    client.builder().connect();
+    let session = session.lock().unwrap().take().unwrap();
 
-    assert!(CALLED_BACK.load(Ordering::SeqCst));
+    // Only QUIC connections compare the context, so the ticket still resumes.
+    let mut builder = client.builder();
+    unsafe { builder.ssl().set_session(&session).unwrap() };
+    let s = builder.connect();
+    assert!(s.ssl().session_reused());
 }
 
 #[test]
@@ -1094,6 +2951,43 @@ fn psk_ciphers() {
     assert!(CLIENT_CALLED.load(Ordering::SeqCst) && SERVER_CALLED.load(Ordering::SeqCst));
 }
 
+#[cfg(not(osslconf = "OPENSSL_NO_PSK"))]
+#[test]
+fn psk_identity_hint() {
+    const CIPHER: &str = "PSK-AES128-CBC-SHA";
+    const PSK: &[u8] = b"thisisaverysecurekey";
+    const HINT: &[u8] = b"use-key-2";
+    const CLIENT_IDENT: &[u8] = b"thisisaclient\0";
+    static HINT_RECEIVED: AtomicBool = AtomicBool::new(false);
+
+    let mut server = Server::builder();
+    server.ctx().set_cipher_list(CIPHER).unwrap();
+    server.ctx().set_psk_identity_hint(HINT).unwrap();
+    server.ctx().set_psk_server_callback(|_, _, psk| {
+        psk[..PSK.len()].copy_from_slice(PSK);
+        Ok(PSK.len())
+    });
+
+    let server = server.build();
+
+    let mut client = server.client();
+    client.ctx().set_options(super::SslOptions::NO_TLSV1_3);
+    client.ctx().set_cipher_list(CIPHER).unwrap();
+    client
+        .ctx()
+        .set_psk_client_callback(|_, hint, identity, psk| {
+            assert_eq!(hint, Some(HINT));
+            HINT_RECEIVED.store(true, Ordering::SeqCst);
+            identity[..CLIENT_IDENT.len()].copy_from_slice(CLIENT_IDENT);
+            psk[..PSK.len()].copy_from_slice(PSK);
+            Ok(PSK.len())
+        });
+
+    client.connect();
+
+    assert!(HINT_RECEIVED.load(Ordering::SeqCst));
+}
+
 #[test]
 fn sni_callback_swapped_ctx() {
     static CALLED_BACK: AtomicBool = AtomicBool::new(false);
@@ -1116,6 +3010,264 @@ fn sni_callback_swapped_ctx() {
     assert!(CALLED_BACK.load(Ordering::SeqCst));
 }
 
+fn self_signed_cert(name: &str) -> (X509, PKey<Private>) {
+    let key = PKey::generate_ec(Nid::X9_62_PRIME256V1).unwrap();
+    let mut subject = X509Name::builder().unwrap();
+    subject.append_entry_by_nid(Nid::COMMONNAME, name).unwrap();
+    let subject = subject.build();
+    let mut cert = X509::builder().unwrap();
+    cert.set_version(2).unwrap();
+    cert.set_subject_name(&subject).unwrap();
+    cert.set_issuer_name(&subject).unwrap();
+    cert.set_pubkey(&key).unwrap();
+    cert.set_not_before(&Asn1Time::days_from_now(0).unwrap())
+        .unwrap();
+    cert.set_not_after(&Asn1Time::days_from_now(1).unwrap())
+        .unwrap();
+    cert.sign(&key, MessageDigest::sha256()).unwrap();
+    (cert.build(), key)
+}
+
+/// Returns a self-signed certificate valid for the IP address `ip`.
+fn ip_address_cert(ip: &str) -> (X509, PKey<Private>) {
+    use crate::x509::extension::SubjectAlternativeName;
+
+    let key = PKey::generate_ec(Nid::X9_62_PRIME256V1).unwrap();
+    let mut subject = X509Name::builder().unwrap();
+    subject.append_entry_by_nid(Nid::COMMONNAME, "ip").unwrap();
+    let subject = subject.build();
+    let mut cert = X509::builder().unwrap();
+    cert.set_version(2).unwrap();
+    cert.set_subject_name(&subject).unwrap();
+    cert.set_issuer_name(&subject).unwrap();
+    cert.set_pubkey(&key).unwrap();
+    cert.set_not_before(&Asn1Time::days_from_now(0).unwrap())
+        .unwrap();
+    cert.set_not_after(&Asn1Time::days_from_now(1).unwrap())
+        .unwrap();
+    let san = SubjectAlternativeName::new()
+        .ip(ip)
+        .build(&cert.x509v3_context(None, None))
+        .unwrap();
+    cert.append_extension(san).unwrap();
+    cert.sign(&key, MessageDigest::sha256()).unwrap();
+    (cert.build(), key)
+}
+
+#[test]
+fn connect_ip_address() {
+    let (cert, key) = ip_address_cert("127.0.0.1");
+
+    let mut connector = SslConnector::builder(SslMethod::tls()).unwrap();
+    connector.cert_store_mut().add_cert(cert.clone()).unwrap();
+    let connector = connector.build();
+
+    assert_eq!(
+        ServerName::from("127.0.0.1"),
+        ServerName::IpAddress("127.0.0.1".parse().unwrap())
+    );
+    assert_eq!(
+        ServerName::from("foobar.com"),
+        ServerName::DnsName("foobar.com".to_string())
+    );
+
+    let mut server = Server::builder();
+    server.ctx().set_certificate(&cert).unwrap();
+    server.ctx().set_private_key(&key).unwrap();
+    server.io_cb(|s| {
+        assert_eq!(s.ssl().servername(ssl::NameType::HOST_NAME), None);
+    });
+    let server = server.build();
+    let s = connector
+        .connect("127.0.0.1", server.connect_tcp())
+        .unwrap();
+    assert_eq!(s.ssl().servername(ssl::NameType::HOST_NAME), None);
+
+    let mut server = Server::builder();
+    server.ctx().set_certificate(&cert).unwrap();
+    server.ctx().set_private_key(&key).unwrap();
+    server.should_error();
+    let server = server.build();
+    let name = ServerName::IpAddress("127.0.0.2".parse().unwrap());
+    connector
+        .configure()
+        .unwrap()
+        .connect_server_name(&name, server.connect_tcp())
+        .unwrap_err();
+}
+
+#[test]
+fn servername_certificates() {
+    let (wildcard_cert, wildcard_key) = self_signed_cert("*.example.org");
+    let mut certificates = HashMap::new();
+    certificates.insert(
+        "*.Example.org".to_string(),
+        (wildcard_cert.clone(), Stack::new().unwrap(), wildcard_key),
+    );
+    // Collides with the entry above, which sorts first and so is always used.
+    certificates.insert(
+        "*.example.org".to_string(),
+        (
+            X509::from_pem(CERT).unwrap(),
+            Stack::new().unwrap(),
+            PKey::private_key_from_pem(KEY).unwrap(),
+        ),
+    );
+    certificates.insert(
+        "foobar.com".to_string(),
+        (
+            X509::from_pem(CERT).unwrap(),
+            Stack::new().unwrap(),
+            PKey::private_key_from_pem(KEY).unwrap(),
+        ),
+    );
+
+    let (default_cert, default_key) = self_signed_cert("default");
+    let mut server = Server::builder();
+    server.expected_connections_count(3);
+    server.ctx().set_certificate(&default_cert).unwrap();
+    server.ctx().set_private_key(&default_key).unwrap();
+    server.ctx().set_servername_certificates(certificates);
+    let server = server.build();
+
+    let client = server.client().build();
+    let peer_der = |host: Option<&str>| {
+        let mut builder = client.builder();
+        if let Some(host) = host {
+            builder.ssl().set_hostname(host).unwrap();
+        }
+        let s = builder.connect();
+        let cert = s.ssl().peer_certificate().unwrap();
+        cert.to_der().unwrap()
+    };
+
+    assert_eq!(
+        peer_der(Some("www.example.org")),
+        wildcard_cert.to_der().unwrap()
+    );
+    assert_eq!(
+        peer_der(Some("foobar.com")),
+        X509::from_pem(CERT).unwrap().to_der().unwrap()
+    );
+    assert_eq!(peer_der(None), default_cert.to_der().unwrap());
+}
+
+#[test]
+fn match_servername() {
+    assert!(ssl::match_servername("*.example.com", "a.example.com"));
+    assert!(ssl::match_servername("*.Example.com", "A.example.COM"));
+    assert!(ssl::match_servername("*.example.com", "a.example.com."));
+    assert!(!ssl::match_servername("*.example.com", "a.b.example.com"));
+    assert!(!ssl::match_servername("*.example.com", "example.com"));
+    assert!(!ssl::match_servername("*.example.com", ".example.com"));
+    assert!(!ssl::match_servername("*.com", "example.com"));
+    assert!(!ssl::match_servername("a*.example.com", "ab.example.com"));
+    assert!(!ssl::match_servername("*.*.example.com", "a.b.example.com"));
+    assert!(ssl::match_servername("example.com", "EXAMPLE.com"));
+    assert!(!ssl::match_servername("example.com", "www.example.com"));
+    assert!(!ssl::match_servername("", ""));
+}
+
+#[test]
+fn tls13_downgrade_sentinel_with_tls12_client() {
+    // The TLS 1.3 capable server sends the downgrade sentinel when it negotiates TLS 1.2, which a
+    // client that did not offer TLS 1.3 accepts.
+    let server = Server::builder().build();
+
+    let mut client = server.client();
+    client
+        .ctx()
+        .set_max_proto_version(Some(SslVersion::TLS1_2))
+        .unwrap();
+    let s = client.connect();
+    assert_eq!(s.ssl().version2(), Some(SslVersion::TLS1_2));
+}
+
+#[test]
+fn available_ciphers() {
+    let names = |ciphers: Vec<&SslCipherRef>| {
+        ciphers
+            .iter()
+            .map(|c| c.standard_name().unwrap())
+            .collect::<Vec<_>>()
+    };
+
+    let all = names(ssl::all_ciphers());
+    assert!(all.contains(&"TLS_AES_128_GCM_SHA256"));
+    assert!(all.contains(&"TLS_ECDHE_RSA_WITH_AES_128_GCM_SHA256"));
+    for weak in &["RC4", "EXPORT", "MD5"] {
+        assert!(all.iter().all(|name| !name.contains(weak)), "{}", weak);
+    }
+
+    let ctx = SslContext::builder(SslMethod::tls()).unwrap().build();
+    let tls13 = names(ctx.available_ciphers(SslVersion::TLS1_3));
+    assert!(tls13.contains(&"TLS_AES_128_GCM_SHA256"));
+    assert!(!tls13.contains(&"TLS_ECDHE_RSA_WITH_AES_128_GCM_SHA256"));
+    let tls12 = names(ctx.available_ciphers(SslVersion::TLS1_2));
+    assert!(tls12.contains(&"TLS_ECDHE_RSA_WITH_AES_128_GCM_SHA256"));
+    assert!(!tls12.contains(&"TLS_AES_128_GCM_SHA256"));
+
+    let mut ctx = SslContext::builder(SslMethod::tls()).unwrap();
+    ctx.set_cipher_list("ECDHE-ECDSA-AES256-GCM-SHA384")
+        .unwrap();
+    let ctx = ctx.build();
+    assert_eq!(
+        names(ctx.available_ciphers(SslVersion::TLS1_2)),
+        ["TLS_ECDHE_ECDSA_WITH_AES_256_GCM_SHA384"]
+    );
+    assert_eq!(ctx.available_ciphers(SslVersion::TLS1_3).len(), tls13.len());
+}
+
+#[test]
+fn session_cache_len() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let mut ctx = SslContext::builder(SslMethod::tls()).unwrap();
+    ctx.set_certificate_file(Path::new("test/cert.pem"), SslFiletype::PEM)
+        .unwrap();
+    ctx.set_private_key_file(Path::new("test/key.pem"), SslFiletype::PEM)
+        .unwrap();
+    ctx.set_max_proto_version(Some(SslVersion::TLS1_2)).unwrap();
+    ctx.set_options(SslOptions::NO_TICKET);
+    ctx.set_session_id_context(b"foo").unwrap();
+    let server_ctx = ctx.build();
+    assert_eq!(server_ctx.session_cache_len(), 0);
+
+    let ctx = server_ctx.clone();
+    let guard = thread::spawn(move || {
+        let mut reused = 0;
+        for _ in 0..3 {
+            let stream = listener.accept().unwrap().0;
+            let mut stream = Ssl::new(&ctx).unwrap().accept(stream).unwrap();
+            if stream.ssl().session_reused() {
+                reused += 1;
+            }
+            stream.write_all(&[0]).unwrap();
+        }
+        reused
+    });
+
+    let client_ctx = SslContext::builder(SslMethod::tls()).unwrap().build();
+    let connect = |session: Option<&SslSessionRef>| {
+        let mut ssl = Ssl::new(&client_ctx).unwrap();
+        if let Some(session) = session {
+            unsafe { ssl.set_session(session).unwrap() };
+        }
+        let mut stream = ssl.connect(TcpStream::connect(addr).unwrap()).unwrap();
+        stream.read_exact(&mut [0]).unwrap();
+        stream.ssl().session().unwrap().to_owned()
+    };
+
+    // Two full handshakes each add a session, the resumption reuses one of them.
+    let session = connect(None);
+    connect(None);
+    connect(Some(&*session));
+
+    assert_eq!(guard.join().unwrap(), 1);
+    assert_eq!(server_ctx.session_cache_len(), 2);
+}
+
 #[test]
 fn session_cache_size() {
     let mut ctx = SslContext::builder(SslMethod::tls()).unwrap();
@@ -1123,3 +3275,66 @@ fn session_cache_size() {
     let ctx = ctx.build();
     assert_eq!(ctx.session_cache_size(), 1234);
 }
+
+#[test]
+fn flush_on_write() {
+    struct CountingStream {
+        inner: TcpStream,
+        buf: Vec<u8>,
+        writes: Vec<Vec<u8>>,
+    }
+
+    impl Read for CountingStream {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.inner.read(buf)
+        }
+    }
+
+    impl Write for CountingStream {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.buf.extend_from_slice(buf);
+            self.writes.push(buf.to_vec());
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            self.inner.write_all(&self.buf)?;
+            self.buf.clear();
+            self.inner.flush()
+        }
+    }
+
+    let mut server = Server::builder();
+    server.expected_connections_count(2);
+    let server = server.build();
+
+    // With TLS 1.2, the client's second flight is made of ClientKeyExchange, ChangeCipherSpec and
+    // Finished records.
+    let mut ctx = SslContext::builder(SslMethod::tls()).unwrap();
+    ctx.set_max_proto_version(Some(SslVersion::TLS1_2)).unwrap();
+    let ctx = ctx.build();
+
+    let writes = |flush_on_write| {
+        let stream = CountingStream {
+            inner: server.connect_tcp(),
+            buf: vec![],
+            writes: vec![],
+        };
+        let mut builder = SslStreamBuilder::new(Ssl::new(&ctx).unwrap(), stream);
+        builder.set_flush_on_write(flush_on_write);
+        let mut stream = builder.connect().unwrap();
+        stream.read_exact(&mut [0]).unwrap();
+        stream.get_ref().writes.clone()
+    };
+
+    let is_single_record =
+        |write: &Vec<u8>| write.len() == 5 + ((write[3] as usize) << 8 | write[4] as usize);
+
+    let buffered = writes(false);
+    let unbuffered = writes(true);
+
+    assert!(!buffered.iter().all(is_single_record));
+    assert!(unbuffered.iter().all(is_single_record));
+    assert!(unbuffered.len() > buffered.len());
+    assert_eq!(buffered.concat().len(), unbuffered.concat().len());
+}
@@ -29,6 +29,7 @@ impl Server {
             ssl_cb: Box::new(|_| {}),
             io_cb: Box::new(|_| {}),
             should_error: false,
+            expected_connections_count: 1,
         }
     }
 
@@ -49,6 +50,7 @@ pub struct Builder {
     ssl_cb: Box<dyn FnMut(&mut SslRef) + Send>,
     io_cb: Box<dyn FnMut(SslStream<TcpStream>) + Send>,
     should_error: bool,
+    expected_connections_count: usize,
 }
 
 impl Builder {
@@ -74,6 +76,10 @@ impl Builder {
         self.should_error = true;
     }
 
+    pub fn expected_connections_count(&mut self, count: usize) {
+        self.expected_connections_count = count;
+    }
+
     pub fn build(self) -> Server {
         let ctx = self.ctx.build();
         let socket = TcpListener::bind("127.0.0.1:0").unwrap();
@@ -81,18 +87,21 @@ impl Builder {
         let mut ssl_cb = self.ssl_cb;
         let mut io_cb = self.io_cb;
         let should_error = self.should_error;
+        let expected_connections_count = self.expected_connections_count;
 
         let handle = thread::spawn(move || {
-            let socket = socket.accept().unwrap().0;
-            let mut ssl = Ssl::new(&ctx).unwrap();
-            ssl_cb(&mut ssl);
-            let r = ssl.accept(socket);
-            if should_error {
-                r.unwrap_err();
-            } else {
-                let mut socket = r.unwrap();
-                socket.write_all(&[0]).unwrap();
-                io_cb(socket);
+            for _ in 0..expected_connections_count {
+                let socket = socket.accept().unwrap().0;
+                let mut ssl = Ssl::new(&ctx).unwrap();
+                ssl_cb(&mut ssl);
+                let r = ssl.accept(socket);
+                if should_error {
+                    r.unwrap_err();
+                } else {
+                    let mut socket = r.unwrap();
+                    socket.write_all(&[0]).unwrap();
+                    io_cb(socket);
+                }
             }
         });
 
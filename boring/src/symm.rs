@@ -55,9 +55,11 @@
 use crate::ffi;
 use libc::{c_int, c_uint};
 use std::cmp;
+use std::ffi::CString;
+use std::io::{self, Read, Write};
 use std::ptr;
 
-use crate::error::ErrorStack;
+use crate::error::{put_error, ErrorStack};
 use crate::nid::Nid;
 use crate::{cvt, cvt_p};
 
@@ -72,6 +74,12 @@ pub enum Mode {
 /// See OpenSSL doc at [`EVP_EncryptInit`] for more information on each algorithms.
 ///
 /// [`EVP_EncryptInit`]: https://www.openssl.org/docs/man1.1.0/crypto/EVP_EncryptInit.html
+///
+/// Unlike OpenSSL, BoringSSL does not implement AES-CCM through the `EVP_CIPHER` interface, so
+/// there is no `Cipher::aes_128_ccm` or `Cipher::aes_256_ccm`. BoringSSL only exposes CCM as a
+/// handful of fixed profiles (AES-128 with a 13-byte nonce and a 4, 8 or 16-byte tag) through its
+/// `EVP_AEAD` API, which does not use the OpenSSL "set the total length before the AAD" calling
+/// sequence.
 #[derive(Copy, Clone, PartialEq, Eq)]
 pub struct Cipher(*const ffi::EVP_CIPHER);
 
@@ -90,6 +98,21 @@ impl Cipher {
         }
     }
 
+    /// Looks up a cipher by its name, such as `"aes-256-gcm"`.
+    ///
+    /// This corresponds to [`EVP_get_cipherbyname`]
+    ///
+    /// [`EVP_get_cipherbyname`]: https://www.openssl.org/docs/man1.0.2/crypto/EVP_get_cipherbyname.html
+    pub fn from_name(name: &str) -> Option<Cipher> {
+        let name = CString::new(name).ok()?;
+        let ptr = unsafe { ffi::EVP_get_cipherbyname(name.as_ptr()) };
+        if ptr.is_null() {
+            None
+        } else {
+            Some(Cipher(ptr))
+        }
+    }
+
     pub fn aes_128_ecb() -> Cipher {
         unsafe { Cipher(ffi::EVP_aes_128_ecb()) }
     }
@@ -150,6 +173,18 @@ impl Cipher {
         unsafe { Cipher(ffi::EVP_aes_256_ofb()) }
     }
 
+    /// AES-256 in XTS mode.
+    ///
+    /// The key is the concatenation of the two 256-bit AES keys (64 bytes in total) and the
+    /// 16-byte tweak is passed as the IV. Each data unit must be processed in a single call to
+    /// `Crypter::update` and must be at least one block (16 bytes) long.
+    ///
+    /// This requires the `decrepit` feature.
+    #[cfg(feature = "decrepit")]
+    pub fn aes_256_xts() -> Cipher {
+        unsafe { Cipher(ffi::EVP_aes_256_xts()) }
+    }
+
     pub fn des_cbc() -> Cipher {
         unsafe { Cipher(ffi::EVP_des_cbc()) }
     }
@@ -213,6 +248,10 @@ impl Cipher {
     pub fn block_size(&self) -> usize {
         unsafe { EVP_CIPHER_block_size(self.0) as usize }
     }
+
+    fn is_aead(self) -> bool {
+        unsafe { ffi::EVP_CIPHER_flags(self.0) & ffi::EVP_CIPH_FLAG_AEAD_CIPHER as u32 != 0 }
+    }
 }
 
 unsafe impl Sync for Cipher {}
@@ -281,6 +320,8 @@ unsafe impl Send for Cipher {}
 pub struct Crypter {
     ctx: *mut ffi::EVP_CIPHER_CTX,
     block_size: usize,
+    xts: bool,
+    aead: bool,
 }
 
 unsafe impl Sync for Crypter {}
@@ -307,6 +348,8 @@ impl Crypter {
             let crypter = Crypter {
                 ctx,
                 block_size: t.block_size(),
+                xts: ffi::EVP_CIPHER_mode(t.as_ptr()) == ffi::EVP_CIPH_XTS_MODE as u32,
+                aead: t.is_aead(),
             };
 
             let mode = match mode {
@@ -361,8 +404,16 @@ impl Crypter {
 
     /// Enables or disables padding.
     ///
+    /// Block ciphers such as AES-CBC use PKCS#7 padding by default, which allows input of any
+    /// length and is removed again on decryption. Disable it to handle data which is already
+    /// padded or block-aligned by other means.
+    ///
     /// If padding is disabled, total amount of data encrypted/decrypted must
-    /// be a multiple of the cipher's block size.
+    /// be a multiple of the cipher's block size, and `finalize` fails otherwise.
+    ///
+    /// This corresponds to [`EVP_CIPHER_CTX_set_padding`].
+    ///
+    /// [`EVP_CIPHER_CTX_set_padding`]: https://www.openssl.org/docs/man1.1.0/crypto/EVP_CIPHER_CTX_set_padding.html
     pub fn pad(&mut self, padding: bool) {
         unsafe {
             ffi::EVP_CIPHER_CTX_set_padding(self.ctx, padding as c_int);
@@ -457,8 +508,17 @@ impl Crypter {
     /// where `block_size` is the block size of the cipher (see `Cipher::block_size`).
     ///
     /// Panics if `output.len() > c_int::max_value()`.
+    ///
+    /// Returns an error for XTS ciphers if `input` is shorter than one block.
     pub fn update(&mut self, input: &[u8], output: &mut [u8]) -> Result<usize, ErrorStack> {
         unsafe {
+            if self.xts && input.len() < 16 {
+                return Err(put_error(
+                    ffi::ERR_LIB_CIPHER as c_int,
+                    ffi::CIPHER_R_DATA_NOT_MULTIPLE_OF_BLOCK_LENGTH as c_int,
+                ));
+            }
+
             let block_size = if self.block_size > 1 {
                 self.block_size
             } else {
@@ -509,6 +569,43 @@ impl Crypter {
         }
     }
 
+    /// Finishes the encryption/decryption process of an AEAD cipher such as AES GCM, writing any
+    /// remaining data to `output`.
+    ///
+    /// The number of bytes written to `output` is returned. AES GCM does not buffer any data, so
+    /// this is zero for it.
+    ///
+    /// AEAD ciphers authenticate the whole message, so the `Crypter` must be driven in this order:
+    ///
+    /// 1. `new`,
+    /// 2. `aad_update` with the Additional Authenticated Data, if any,
+    /// 3. `update` with the plaintext or ciphertext, in as many chunks as needed,
+    /// 4. when decrypting, `set_tag` with the expected authentication tag,
+    /// 5. `finalize_aead`,
+    /// 6. when encrypting, `get_tag` to retrieve the authentication tag.
+    ///
+    /// When decrypting, this returns an error if the tag does not match. Any plaintext returned by
+    /// `update` must not be trusted until this has returned successfully.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the cipher is not an AEAD cipher.
+    pub fn finalize_aead(&mut self, output: &mut [u8]) -> Result<usize, ErrorStack> {
+        assert!(self.aead, "finalize_aead requires an AEAD cipher");
+
+        unsafe {
+            let mut outl = cmp::min(output.len(), c_int::max_value() as usize) as c_int;
+
+            cvt(ffi::EVP_CipherFinal_ex(
+                self.ctx,
+                output.as_mut_ptr(),
+                &mut outl,
+            ))?;
+
+            Ok(outl as usize)
+        }
+    }
+
     /// Retrieves the authentication tag used to authenticate ciphertext in AEAD ciphers such
     /// as AES GCM.
     ///
@@ -684,6 +781,325 @@ pub fn decrypt_aead(
     Ok(out)
 }
 
+/// Encrypts data with an AEAD cipher such as AES GCM, returning the ciphertext followed by the
+/// 16-byte authentication tag.
+///
+/// The output is `ciphertext || tag`, where the ciphertext is as long as `data`. This is the
+/// layout produced by libsodium's `crypto_aead_aes256gcm_encrypt` and read by `open_combined`, so
+/// with `Cipher::aes_256_gcm` and a 12-byte `nonce` the two interoperate.
+pub fn seal_combined(
+    t: Cipher,
+    key: &[u8],
+    nonce: &[u8],
+    aad: &[u8],
+    data: &[u8],
+) -> Result<Vec<u8>, ErrorStack> {
+    let mut tag = [0; AEAD_TAG_LEN];
+    let mut out = encrypt_aead(t, key, Some(nonce), aad, data, &mut tag)?;
+    out.extend_from_slice(&tag);
+    Ok(out)
+}
+
+/// Decrypts data produced by `seal_combined`, or by libsodium's `crypto_aead_aes256gcm_encrypt`.
+///
+/// `data` must be the ciphertext followed by the 16-byte authentication tag. An error is returned
+/// if it is shorter than the tag or if authentication fails.
+pub fn open_combined(
+    t: Cipher,
+    key: &[u8],
+    nonce: &[u8],
+    aad: &[u8],
+    data: &[u8],
+) -> Result<Vec<u8>, ErrorStack> {
+    if data.len() < AEAD_TAG_LEN {
+        return Err(put_error(
+            ffi::ERR_LIB_CIPHER as c_int,
+            ffi::CIPHER_R_BAD_DECRYPT as c_int,
+        ));
+    }
+
+    let (ciphertext, tag) = data.split_at(data.len() - AEAD_TAG_LEN);
+    decrypt_aead(t, key, Some(nonce), aad, ciphertext, tag)
+}
+
+/// The length of the tag appended by `EncryptWriter` and checked by `DecryptReader` for AEAD
+/// ciphers.
+const AEAD_TAG_LEN: usize = 16;
+
+const STREAM_CHUNK_LEN: usize = 8192;
+
+fn stack_to_io_error(e: ErrorStack) -> io::Error {
+    io::Error::new(io::ErrorKind::Other, e)
+}
+
+/// A writer which encrypts data with a `Cipher` before passing it to an inner writer.
+///
+/// The final block, and for AEAD ciphers such as AES GCM the 16-byte authentication tag, is only
+/// written by `finish`. Dropping the writer attempts to finish it, but ignores any error, so call
+/// `finish` explicitly to observe failures.
+///
+/// Input is encrypted as soon as it is accepted. If the inner writer fails, the ciphertext it did
+/// not take is kept and written before any further input, and the error is reported by the next
+/// call to `write`, `flush` or `finish`.
+///
+/// # Examples
+///
+/// ```
+/// use boring::symm::{Cipher, DecryptReader, EncryptWriter};
+/// use std::io::{Read, Write};
+///
+/// let cipher = Cipher::aes_256_gcm();
+/// let key = [0x42; 32];
+/// let iv = [0x24; 12];
+///
+/// let mut writer = EncryptWriter::new(vec![], cipher, &key, Some(&iv)).unwrap();
+/// writer.write_all(b"Some Crypto Text").unwrap();
+/// let ciphertext = writer.finish().unwrap();
+///
+/// let mut reader = DecryptReader::new(&ciphertext[..], cipher, &key, Some(&iv)).unwrap();
+/// let mut plaintext = vec![];
+/// reader.read_to_end(&mut plaintext).unwrap();
+/// assert_eq!(plaintext, b"Some Crypto Text");
+/// ```
+pub struct EncryptWriter<W: Write> {
+    inner: Option<W>,
+    crypter: Crypter,
+    cipher: Cipher,
+    buf: Vec<u8>,
+    buf_pos: usize,
+}
+
+impl<W: Write> EncryptWriter<W> {
+    /// Creates a new `EncryptWriter` which writes ciphertext to `inner`.
+    ///
+    /// See `Crypter::new` for the requirements on `key` and `iv`.
+    pub fn new(
+        inner: W,
+        cipher: Cipher,
+        key: &[u8],
+        iv: Option<&[u8]>,
+    ) -> Result<EncryptWriter<W>, ErrorStack> {
+        let crypter = Crypter::new(cipher, Mode::Encrypt, key, iv)?;
+
+        Ok(EncryptWriter {
+            inner: Some(inner),
+            crypter,
+            cipher,
+            buf: vec![],
+            buf_pos: 0,
+        })
+    }
+
+    /// Finalizes the encryption, writes any remaining output and returns the inner writer.
+    ///
+    /// For AEAD ciphers, the authentication tag is appended to the ciphertext.
+    pub fn finish(mut self) -> io::Result<W> {
+        let r = self.finish_inner();
+        let inner = self.inner.take().unwrap();
+        r.map(|()| inner)
+    }
+
+    fn finish_inner(&mut self) -> io::Result<()> {
+        if self.inner.is_none() {
+            return Ok(());
+        }
+
+        self.write_buffered()?;
+        self.buf.resize(self.cipher.block_size(), 0);
+        let count = self
+            .crypter
+            .finalize(&mut self.buf)
+            .map_err(stack_to_io_error)?;
+        let inner = self.inner.as_mut().unwrap();
+        inner.write_all(&self.buf[..count])?;
+
+        if self.cipher.is_aead() {
+            let mut tag = [0; AEAD_TAG_LEN];
+            self.crypter.get_tag(&mut tag).map_err(stack_to_io_error)?;
+            inner.write_all(&tag)?;
+        }
+
+        inner.flush()
+    }
+
+    /// Writes the ciphertext the inner writer has not taken yet.
+    fn write_buffered(&mut self) -> io::Result<()> {
+        let inner = self.inner.as_mut().unwrap();
+
+        while self.buf_pos < self.buf.len() {
+            match inner.write(&self.buf[self.buf_pos..]) {
+                Ok(0) => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::WriteZero,
+                        "failed to write encrypted data",
+                    ))
+                }
+                Ok(n) => self.buf_pos += n,
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<W: Write> Write for EncryptWriter<W> {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        // The cipher state is already past any ciphertext held back, so it must go out first.
+        self.write_buffered()?;
+
+        let data = &data[..cmp::min(data.len(), STREAM_CHUNK_LEN)];
+        self.buf.resize(data.len() + self.cipher.block_size(), 0);
+        let count = self
+            .crypter
+            .update(data, &mut self.buf)
+            .map_err(stack_to_io_error)?;
+        self.buf.truncate(count);
+        self.buf_pos = 0;
+
+        // `data` has been consumed, so a failure is reported by the next call instead.
+        let _ = self.write_buffered();
+        Ok(data.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if self.inner.is_none() {
+            return Ok(());
+        }
+
+        self.write_buffered()?;
+        self.inner.as_mut().unwrap().flush()
+    }
+}
+
+impl<W: Write> Drop for EncryptWriter<W> {
+    fn drop(&mut self) {
+        let _ = self.finish_inner();
+    }
+}
+
+/// A reader which decrypts data read from an inner reader with a `Cipher`.
+///
+/// For AEAD ciphers such as AES GCM, the last 16 bytes of the inner reader are taken to be the
+/// authentication tag, as written by `EncryptWriter`. The tag is verified when the inner reader
+/// reaches EOF, and that read and every later one return an error of kind `InvalidData` if it
+/// does not match. Data returned before that point has not been authenticated yet.
+pub struct DecryptReader<R: Read> {
+    inner: R,
+    crypter: Crypter,
+    cipher: Cipher,
+    pending: Vec<u8>,
+    out: Vec<u8>,
+    out_pos: usize,
+    done: bool,
+    error: Option<(io::ErrorKind, String)>,
+}
+
+impl<R: Read> DecryptReader<R> {
+    /// Creates a new `DecryptReader` which reads ciphertext from `inner`.
+    ///
+    /// See `Crypter::new` for the requirements on `key` and `iv`.
+    pub fn new(
+        inner: R,
+        cipher: Cipher,
+        key: &[u8],
+        iv: Option<&[u8]>,
+    ) -> Result<DecryptReader<R>, ErrorStack> {
+        let crypter = Crypter::new(cipher, Mode::Decrypt, key, iv)?;
+
+        Ok(DecryptReader {
+            inner,
+            crypter,
+            cipher,
+            pending: vec![],
+            out: vec![],
+            out_pos: 0,
+            done: false,
+            error: None,
+        })
+    }
+
+    /// Returns the inner reader.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    fn fill_out(&mut self) -> io::Result<()> {
+        let mut chunk = [0; STREAM_CHUNK_LEN];
+        let n = self.inner.read(&mut chunk)?;
+        self.out_pos = 0;
+
+        // Unlike errors of the inner reader, a failure to decrypt is final.
+        self.decrypt(&chunk[..n]).map_err(|e| {
+            self.error = Some((e.kind(), e.to_string()));
+            e
+        })
+    }
+
+    fn decrypt(&mut self, chunk: &[u8]) -> io::Result<()> {
+        let tag_len = if self.cipher.is_aead() {
+            AEAD_TAG_LEN
+        } else {
+            0
+        };
+
+        if chunk.is_empty() {
+            self.done = true;
+            if tag_len != 0 {
+                if self.pending.len() != tag_len {
+                    return Err(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        "ciphertext is missing its authentication tag",
+                    ));
+                }
+                self.crypter
+                    .set_tag(&self.pending)
+                    .map_err(stack_to_io_error)?;
+            }
+
+            self.out.resize(self.cipher.block_size(), 0);
+            let count = self
+                .crypter
+                .finalize(&mut self.out)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+            self.out.truncate(count);
+            return Ok(());
+        }
+
+        self.pending.extend_from_slice(chunk);
+        let len = self.pending.len().saturating_sub(tag_len);
+        self.out.resize(len + self.cipher.block_size(), 0);
+        let count = self
+            .crypter
+            .update(&self.pending[..len], &mut self.out)
+            .map_err(stack_to_io_error)?;
+        self.out.truncate(count);
+        self.pending.drain(..len);
+
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for DecryptReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if let Some((kind, ref message)) = self.error {
+            return Err(io::Error::new(kind, message.clone()));
+        }
+
+        while self.out_pos == self.out.len() {
+            if self.done {
+                return Ok(0);
+            }
+            self.fill_out()?;
+        }
+
+        let len = cmp::min(buf.len(), self.out.len() - self.out_pos);
+        buf[..len].copy_from_slice(&self.out[self.out_pos..self.out_pos + len]);
+        self.out_pos += len;
+        Ok(len)
+    }
+}
+
 use crate::ffi::{EVP_CIPHER_block_size, EVP_CIPHER_iv_length, EVP_CIPHER_key_length};
 
 #[cfg(test)]
@@ -691,6 +1107,109 @@ mod tests {
     use super::*;
     use hex::{self, FromHex};
 
+    #[test]
+    fn test_cipher_from_name() {
+        let cipher = Cipher::from_name("aes-256-gcm").unwrap();
+        assert!(cipher == Cipher::aes_256_gcm());
+        assert_eq!(cipher.key_len(), 32);
+        assert_eq!(cipher.iv_len(), Some(12));
+        assert_eq!(cipher.block_size(), 1);
+
+        assert!(Cipher::from_name("not-a-cipher").is_none());
+    }
+
+    #[test]
+    fn test_stream_adapters_aes_256_gcm() {
+        let cipher = Cipher::aes_256_gcm();
+        let key = [0x01; 32];
+        let iv = [0x02; 12];
+        let data = (0..3 * 1024 * 1024 + 7)
+            .map(|i| (i % 251) as u8)
+            .collect::<Vec<_>>();
+
+        let mut writer = EncryptWriter::new(vec![], cipher, &key, Some(&iv)).unwrap();
+        for chunk in data.chunks(10000) {
+            writer.write_all(chunk).unwrap();
+        }
+        let mut ciphertext = writer.finish().unwrap();
+        assert_eq!(ciphertext.len(), data.len() + AEAD_TAG_LEN);
+
+        let mut reader = DecryptReader::new(&ciphertext[..], cipher, &key, Some(&iv)).unwrap();
+        let mut plaintext = vec![];
+        reader.read_to_end(&mut plaintext).unwrap();
+        assert!(plaintext == data);
+
+        let last = ciphertext.len() - 1;
+        ciphertext[last] ^= 1;
+        let mut reader = DecryptReader::new(&ciphertext[..], cipher, &key, Some(&iv)).unwrap();
+        let err = reader.read_to_end(&mut vec![]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        let err = reader.read(&mut [0; 16]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_stream_adapters_aes_128_cbc() {
+        let cipher = Cipher::aes_128_cbc();
+        let key = [0x03; 16];
+        let iv = [0x04; 16];
+        let data = b"Some Stream of Crypto Text";
+
+        let mut ciphertext = vec![];
+        {
+            let mut writer = EncryptWriter::new(&mut ciphertext, cipher, &key, Some(&iv)).unwrap();
+            writer.write_all(data).unwrap();
+        }
+        assert_eq!(ciphertext, encrypt(cipher, &key, Some(&iv), data).unwrap());
+
+        let mut reader = DecryptReader::new(&ciphertext[..], cipher, &key, Some(&iv)).unwrap();
+        let mut plaintext = vec![];
+        reader.read_to_end(&mut plaintext).unwrap();
+        assert_eq!(plaintext, data);
+    }
+
+    #[test]
+    fn test_encrypt_writer_inner_error() {
+        struct FailOnce {
+            out: Vec<u8>,
+            failed: bool,
+        }
+
+        impl Write for FailOnce {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                if !self.failed {
+                    self.failed = true;
+                    return Err(io::Error::new(io::ErrorKind::Other, "failed"));
+                }
+                self.out.extend_from_slice(buf);
+                Ok(buf.len())
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let cipher = Cipher::aes_256_gcm();
+        let key = [0x05; 32];
+        let iv = [0x06; 12];
+        let inner = FailOnce {
+            out: vec![],
+            failed: false,
+        };
+
+        // The ciphertext of the first write is held back and written before the second one.
+        let mut writer = EncryptWriter::new(inner, cipher, &key, Some(&iv)).unwrap();
+        assert_eq!(writer.write(b"hello").unwrap(), 5);
+        writer.write_all(b", world").unwrap();
+        let ciphertext = writer.finish().unwrap().out;
+
+        let mut reader = DecryptReader::new(&ciphertext[..], cipher, &key, Some(&iv)).unwrap();
+        let mut plaintext = vec![];
+        reader.read_to_end(&mut plaintext).unwrap();
+        assert_eq!(plaintext, b"hello, world");
+    }
+
     #[test]
     fn test_stream_cipher_output() {
         let key = [0u8; 16];
@@ -754,6 +1273,48 @@ mod tests {
         assert_eq!(hex::encode(p1), hex::encode(p0));
     }
 
+    #[test]
+    fn test_cbc_padding() {
+        fn crypt(
+            cipher: Cipher,
+            mode: Mode,
+            pad: bool,
+            data: &[u8],
+        ) -> Result<Vec<u8>, ErrorStack> {
+            let key = vec![0x42; cipher.key_len()];
+            let iv = vec![0x24; cipher.iv_len().unwrap()];
+            let mut c = Crypter::new(cipher, mode, &key, Some(&iv))?;
+            c.pad(pad);
+            let mut out = vec![0; data.len() + cipher.block_size()];
+            let count = c.update(data, &mut out)?;
+            let rest = c.finalize(&mut out[count..])?;
+            out.truncate(count + rest);
+            Ok(out)
+        }
+
+        for &cipher in &[Cipher::aes_128_cbc(), Cipher::aes_256_cbc()] {
+            // With padding, input of any length round-trips and gains up to a block of padding.
+            let data = b"seventeen bytes!!";
+            let ct = crypt(cipher, Mode::Encrypt, true, data).unwrap();
+            assert_eq!(ct.len(), 32);
+            assert_eq!(crypt(cipher, Mode::Decrypt, true, &ct).unwrap(), &data[..]);
+
+            // Without padding, block-aligned input maps to ciphertext of the same length.
+            let data = [0x11; 32];
+            let ct = crypt(cipher, Mode::Encrypt, false, &data).unwrap();
+            assert_eq!(ct.len(), 32);
+            assert_eq!(crypt(cipher, Mode::Decrypt, false, &ct).unwrap(), &data[..]);
+
+            // Decrypting padded ciphertext without padding leaves the padding in place.
+            let ct = crypt(cipher, Mode::Encrypt, true, &data).unwrap();
+            let pt = crypt(cipher, Mode::Decrypt, false, &ct).unwrap();
+            assert_eq!(&pt[..32], &data[..]);
+            assert_eq!(&pt[32..], &[16; 16][..]);
+
+            assert!(crypt(cipher, Mode::Encrypt, false, &data[..31]).is_err());
+        }
+    }
+
     #[test]
     fn test_aes_256_cbc_decrypt() {
         let iv = [
@@ -880,6 +1441,61 @@ mod tests {
         cipher_test(super::Cipher::aes_256_ofb(), pt, ct, key, iv);
     }
 
+    #[test]
+    #[cfg(feature = "decrepit")]
+    fn test_aes256_xts() {
+        // XTS-AES-256 test vector 10 from IEEE 1619-2007, appendix B.
+
+        let pt = "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f2021222324252627\
+                  28292a2b2c2d2e2f303132333435363738393a3b3c3d3e3f404142434445464748494a4b4c4d4e4f\
+                  505152535455565758595a5b5c5d5e5f606162636465666768696a6b6c6d6e6f7071727374757677\
+                  78797a7b7c7d7e7f808182838485868788898a8b8c8d8e8f909192939495969798999a9b9c9d9e9f\
+                  a0a1a2a3a4a5a6a7a8a9aaabacadaeafb0b1b2b3b4b5b6b7b8b9babbbcbdbebfc0c1c2c3c4c5c6c7\
+                  c8c9cacbcccdcecfd0d1d2d3d4d5d6d7d8d9dadbdcdddedfe0e1e2e3e4e5e6e7e8e9eaebecedeeef\
+                  f0f1f2f3f4f5f6f7f8f9fafbfcfdfeff000102030405060708090a0b0c0d0e0f1011121314151617\
+                  18191a1b1c1d1e1f202122232425262728292a2b2c2d2e2f303132333435363738393a3b3c3d3e3f\
+                  404142434445464748494a4b4c4d4e4f505152535455565758595a5b5c5d5e5f6061626364656667\
+                  68696a6b6c6d6e6f707172737475767778797a7b7c7d7e7f808182838485868788898a8b8c8d8e8f\
+                  909192939495969798999a9b9c9d9e9fa0a1a2a3a4a5a6a7a8a9aaabacadaeafb0b1b2b3b4b5b6b7\
+                  b8b9babbbcbdbebfc0c1c2c3c4c5c6c7c8c9cacbcccdcecfd0d1d2d3d4d5d6d7d8d9dadbdcdddedf\
+                  e0e1e2e3e4e5e6e7e8e9eaebecedeeeff0f1f2f3f4f5f6f7f8f9fafbfcfdfeff";
+        let ct = "1c3b3a102f770386e4836c99e370cf9bea00803f5e482357a4ae12d414a3e63b5d31e276f8fe4a8d\
+                  66b317f9ac683f44680a86ac35adfc3345befecb4bb188fd5776926c49a3095eb108fd1098baec70\
+                  aaa66999a72a82f27d848b21d4a741b0c5cd4d5fff9dac89aeba122961d03a757123e9870f8acf10\
+                  00020887891429ca2a3e7a7d7df7b10355165c8b9a6d0a7de8b062c4500dc4cd120c0f7418dae3d0\
+                  b5781c34803fa75421c790dfe1de1834f280d7667b327f6c8cd7557e12ac3a0f93ec05c52e0493ef\
+                  31a12d3d9260f79a289d6a379bc70c50841473d1a8cc81ec583e9645e07b8d9670655ba5bbcfecc6\
+                  dc3966380ad8fecb17b6ba02469a020a84e18e8f84252070c13e9f1f289be54fbc481457778f6160\
+                  15e1327a02b140f1505eb309326d68378f8374595c849d84f4c333ec4423885143cb47bd71c5edae\
+                  9be69a2ffeceb1bec9de244fbe15992b11b77c040f12bd8f6a975a44a0f90c29a9abc3d4d8939272\
+                  84c58754cce294529f8614dcd2aba991925fedc4ae74ffac6e333b93eb4aff0479da9a410e4450e0\
+                  dd7ae4c6e2910900575da401fc07059f645e8b7e9bfdef33943054ff84011493c27b3429eaedb4ed\
+                  5376441a77ed43851ad77f16f541dfd269d50d6a5f14fb0aab1cbb4c1550be97f7ab4066193c4caa\
+                  773dad38014bd2092fa755c824bb5e54c4f36ffda9fcea70b9c6e693e148c151";
+        let key = "27182818284590452353602874713526624977572470936999595749669676273141592653589793238462643383279502884197169399375105820974944592";
+        let iv = "ff000000000000000000000000000000";
+
+        cipher_test(super::Cipher::aes_256_xts(), pt, ct, key, iv);
+
+        let pt = Vec::from_hex(pt).unwrap();
+        let key = Vec::from_hex(key).unwrap();
+        let iv = Vec::from_hex(iv).unwrap();
+        let computed = super::encrypt(super::Cipher::aes_256_xts(), &key, Some(&iv), &pt).unwrap();
+        assert_eq!(hex::encode(computed), ct);
+    }
+
+    #[test]
+    #[cfg(feature = "decrepit")]
+    fn test_aes256_xts_short_input() {
+        let key = [0x42; 64];
+        let iv = [0; 16];
+
+        let mut crypter =
+            Crypter::new(super::Cipher::aes_256_xts(), Mode::Encrypt, &key, Some(&iv)).unwrap();
+        let mut out = [0; 15];
+        assert!(crypter.update(&[0; 15], &mut out).is_err());
+    }
+
     #[test]
     fn test_des_cbc() {
         let pt = "54686973206973206120746573742e";
@@ -920,6 +1536,86 @@ mod tests {
         cipher_test(super::Cipher::des_ede3_cbc(), pt, ct, key, iv);
     }
 
+    #[test]
+    fn test_aes_256_gcm_finalize_aead() {
+        let cipher = Cipher::aes_256_gcm();
+        let key = [0x42; 32];
+        let iv = [0x24; 12];
+        let aad = b"some authenticated header";
+        let pt = (0..100u8).collect::<Vec<_>>();
+
+        let mut c = Crypter::new(cipher, Mode::Encrypt, &key, Some(&iv)).unwrap();
+        c.aad_update(&aad[..4]).unwrap();
+        c.aad_update(&aad[4..]).unwrap();
+        let mut ct = vec![0; pt.len() + cipher.block_size()];
+        let mut count = 0;
+        for chunk in [&pt[..10], &pt[10..50], &pt[50..]].iter() {
+            count += c.update(chunk, &mut ct[count..]).unwrap();
+        }
+        count += c.finalize_aead(&mut ct[count..]).unwrap();
+        ct.truncate(count);
+        let mut tag = [0; 16];
+        c.get_tag(&mut tag).unwrap();
+
+        let mut expected_tag = [0; 16];
+        let expected = encrypt_aead(cipher, &key, Some(&iv), aad, &pt, &mut expected_tag).unwrap();
+        assert_eq!(ct, expected);
+        assert_eq!(tag, expected_tag);
+
+        let decrypt = |tag: &[u8]| {
+            let mut c = Crypter::new(cipher, Mode::Decrypt, &key, Some(&iv)).unwrap();
+            c.aad_update(aad).unwrap();
+            let mut out = vec![0; ct.len() + cipher.block_size()];
+            let mut count = 0;
+            for chunk in ct.chunks(7) {
+                count += c.update(chunk, &mut out[count..]).unwrap();
+            }
+            c.set_tag(tag).unwrap();
+            count += c.finalize_aead(&mut out[count..])?;
+            out.truncate(count);
+            Ok::<_, ErrorStack>(out)
+        };
+
+        assert_eq!(decrypt(&tag).unwrap(), pt);
+
+        let mut tampered = tag;
+        tampered[0] ^= 1;
+        assert!(decrypt(&tampered).is_err());
+    }
+
+    #[test]
+    #[should_panic(expected = "finalize_aead requires an AEAD cipher")]
+    fn test_finalize_aead_non_aead() {
+        let mut c = Crypter::new(Cipher::aes_128_ecb(), Mode::Encrypt, &[0; 16], None).unwrap();
+        let _ = c.finalize_aead(&mut [0; 16]);
+    }
+
+    #[test]
+    fn test_aes_256_gcm_combined() {
+        let key = (0..32).collect::<Vec<u8>>();
+        let nonce = (0x10..0x1c).collect::<Vec<u8>>();
+        let aad = b"libsodium aad";
+        let pt = b"libsodium interop message";
+        // Produced by libsodium's crypto_aead_aes256gcm_encrypt.
+        let sodium =
+            "1197fa6526ad53c6a75561737b1c1b3ca770236b68b136d68213a9141c9bcdc9c59d75bba4cbb0\
+             7447";
+        let sodium = Vec::from_hex(sodium).unwrap();
+
+        let cipher = Cipher::aes_256_gcm();
+        let sealed = seal_combined(cipher, &key, &nonce, aad, pt).unwrap();
+        assert_eq!(sealed.len(), pt.len() + 16);
+        assert_eq!(sealed, sodium);
+
+        let opened = open_combined(cipher, &key, &nonce, aad, &sodium).unwrap();
+        assert_eq!(opened, &pt[..]);
+
+        let mut tampered = sodium.clone();
+        *tampered.last_mut().unwrap() ^= 1;
+        assert!(open_combined(cipher, &key, &nonce, aad, &tampered).is_err());
+        assert!(open_combined(cipher, &key, &nonce, aad, &sodium[..15]).is_err());
+    }
+
     #[test]
     fn test_aes128_gcm() {
         let key = "0e00c76561d2bd9b40c3c15427e2b08f";
@@ -11,6 +11,19 @@
 // limitations under the License.
 //
 
+//! Build information of the linked library.
+//!
+//! These mirror OpenSSL's `OpenSSL_version` strings, which is useful to identify the library in
+//! logs and bug reports. BoringSSL fills in fixed placeholder values for most of them; the
+//! version string always starts with `"BoringSSL"`.
+//!
+//! ```
+//! use boring::version;
+//!
+//! assert!(version::version().starts_with("BoringSSL"));
+//! println!("{} ({})", version::version(), version::built_on());
+//! ```
+
 use std::ffi::CStr;
 
 use crate::ffi::{
@@ -10,7 +10,7 @@
 use crate::ffi;
 use foreign_types::{ForeignType, ForeignTypeRef};
 use libc::{c_int, c_long};
-use std::convert::TryInto;
+use std::convert::{TryFrom, TryInto};
 use std::error::Error;
 use std::ffi::{CStr, CString};
 use std::fmt;
@@ -21,8 +21,11 @@ use std::ptr;
 use std::slice;
 use std::str;
 
-use crate::asn1::{Asn1BitStringRef, Asn1IntegerRef, Asn1ObjectRef, Asn1StringRef, Asn1TimeRef};
-use crate::bio::MemBioSlice;
+use crate::asn1::{
+    Asn1BitStringRef, Asn1IntegerRef, Asn1Object, Asn1ObjectRef, Asn1OctetStringRef, Asn1StringRef,
+    Asn1TimeRef,
+};
+use crate::bio::{MemBio, MemBioSlice};
 use crate::conf::ConfRef;
 use crate::error::ErrorStack;
 use crate::ex_data::Index;
@@ -146,9 +149,29 @@ impl X509StoreContextRef {
     /// Verifies the stored certificate.
     ///
     /// Returns `true` if verification succeeds. The `error` method will return the specific
-    /// validation error if the certificate was not valid.
+    /// validation error if the certificate was not valid, and `error_depth` the position in the
+    /// chain at which it occurred.
     ///
-    /// This will only work inside of a call to `init`.
+    /// This will only work inside of a call to `init`. This can be used to verify certificates
+    /// obtained outside of TLS, for example:
+    ///
+    /// ```
+    /// use boring::stack::Stack;
+    /// use boring::x509::store::X509StoreBuilder;
+    /// use boring::x509::{X509StoreContext, X509};
+    ///
+    /// let cert = X509::from_pem(include_bytes!("../../test/cert.pem")).unwrap();
+    /// let chain = Stack::new().unwrap();
+    /// let store = X509StoreBuilder::new().unwrap().build();
+    ///
+    /// let mut context = X509StoreContext::new().unwrap();
+    /// let error = context
+    ///     .init(&store, &cert, &chain, |c| {
+    ///         c.verify_cert().map(|ok| if ok { None } else { Some(c.error()) })
+    ///     })
+    ///     .unwrap();
+    /// assert!(error.is_some());
+    /// ```
     ///
     /// This corresponds to [`X509_verify_cert`].
     ///
@@ -185,6 +208,39 @@ impl X509StoreContextRef {
         }
     }
 
+    /// Returns a reference to the certificate being verified.
+    ///
+    /// This corresponds to [`X509_STORE_CTX_get0_cert`].
+    ///
+    /// [`X509_STORE_CTX_get0_cert`]: https://www.openssl.org/docs/man1.1.0/crypto/X509_STORE_CTX_get0_cert.html
+    pub fn cert(&self) -> Option<&X509Ref> {
+        unsafe {
+            let ptr = ffi::X509_STORE_CTX_get0_cert(self.as_ptr());
+            if ptr.is_null() {
+                None
+            } else {
+                Some(X509Ref::from_ptr(ptr))
+            }
+        }
+    }
+
+    /// Returns the untrusted certificates supplied alongside the certificate being verified, such
+    /// as the intermediates sent by a TLS peer.
+    ///
+    /// This corresponds to [`X509_STORE_CTX_get0_untrusted`].
+    ///
+    /// [`X509_STORE_CTX_get0_untrusted`]: https://www.openssl.org/docs/man1.1.0/crypto/X509_STORE_CTX_get0_untrusted.html
+    pub fn untrusted(&self) -> Option<&StackRef<X509>> {
+        unsafe {
+            let ptr = ffi::X509_STORE_CTX_get0_untrusted(self.as_ptr());
+            if ptr.is_null() {
+                None
+            } else {
+                Some(StackRef::from_ptr(ptr))
+            }
+        }
+    }
+
     /// Returns a non-negative integer representing the depth in the certificate
     /// chain where the error occurred. If it is zero it occurred in the end
     /// entity certificate, one if it is the certificate which signed the end
@@ -308,7 +364,10 @@ impl X509Builder {
 
     /// Returns a context object which is needed to create certain X509 extension values.
     ///
-    /// Set `issuer` to `None` if the certificate will be self-signed.
+    /// Set `issuer` to `None` if the certificate will be self-signed. The issuer is used to fill
+    /// in the `AuthorityKeyIdentifier` extension from the issuer's subject key identifier, issuer
+    /// name and serial number, while the subject's public key is used to compute the
+    /// `SubjectKeyIdentifier` extension, so the public key must be set beforehand.
     pub fn x509v3_context<'a>(
         &'a self,
         issuer: Option<&'a X509Ref>,
@@ -373,6 +432,41 @@ impl X509Builder {
     }
 }
 
+bitflags! {
+    /// The usages permitted by a certificate's key usage extension.
+    pub struct KeyUsageFlags: u32 {
+        const DIGITAL_SIGNATURE = ffi::KU_DIGITAL_SIGNATURE as _;
+        const NON_REPUDIATION = ffi::KU_NON_REPUDIATION as _;
+        const KEY_ENCIPHERMENT = ffi::KU_KEY_ENCIPHERMENT as _;
+        const DATA_ENCIPHERMENT = ffi::KU_DATA_ENCIPHERMENT as _;
+        const KEY_AGREEMENT = ffi::KU_KEY_AGREEMENT as _;
+        const KEY_CERT_SIGN = ffi::KU_KEY_CERT_SIGN as _;
+        const CRL_SIGN = ffi::KU_CRL_SIGN as _;
+        const ENCIPHER_ONLY = ffi::KU_ENCIPHER_ONLY as _;
+        const DECIPHER_ONLY = ffi::KU_DECIPHER_ONLY as _;
+    }
+}
+
+/// The contents of a certificate's basic constraints extension.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct X509BasicConstraints {
+    ca: bool,
+    pathlen: Option<u32>,
+}
+
+impl X509BasicConstraints {
+    /// Returns whether the certificate is a certificate authority.
+    pub fn ca(&self) -> bool {
+        self.ca
+    }
+
+    /// Returns the maximum number of intermediate certificates that may follow this one in a
+    /// chain, if constrained.
+    pub fn pathlen(&self) -> Option<u32> {
+        self.pathlen
+    }
+}
+
 foreign_type_and_impl_send_sync! {
     type CType = ffi::X509;
     fn drop = ffi::X509_free;
@@ -457,6 +551,126 @@ impl X509Ref {
         }
     }
 
+    /// Returns this certificate's basic constraints, if the extension is present.
+    ///
+    /// This corresponds to [`X509_get_ext_d2i`] called with `NID_basic_constraints`.
+    ///
+    /// [`X509_get_ext_d2i`]: https://www.openssl.org/docs/man1.1.0/crypto/X509_get_ext_d2i.html
+    pub fn basic_constraints(&self) -> Option<X509BasicConstraints> {
+        unsafe {
+            let bc = ffi::X509_get_ext_d2i(
+                self.as_ptr(),
+                ffi::NID_basic_constraints,
+                ptr::null_mut(),
+                ptr::null_mut(),
+            ) as *mut ffi::BASIC_CONSTRAINTS;
+            if bc.is_null() {
+                return None;
+            }
+
+            let pathlen = if (*bc).pathlen.is_null() {
+                None
+            } else {
+                let pathlen = Asn1IntegerRef::from_ptr((*bc).pathlen).get();
+                if pathlen < 0 || pathlen > i64::from(u32::max_value()) {
+                    None
+                } else {
+                    Some(pathlen as u32)
+                }
+            };
+            let constraints = X509BasicConstraints {
+                ca: (*bc).ca != 0,
+                pathlen,
+            };
+            ffi::BASIC_CONSTRAINTS_free(bc);
+
+            Some(constraints)
+        }
+    }
+
+    /// Determines if this certificate is a certificate authority according to its basic
+    /// constraints extension.
+    ///
+    /// Certificates without the extension are not considered certificate authorities.
+    pub fn is_ca(&self) -> bool {
+        self.basic_constraints().map_or(false, |bc| bc.ca())
+    }
+
+    /// Returns the value of this certificate's subject key identifier extension, if present.
+    ///
+    /// This corresponds to [`X509_get0_subject_key_id`].
+    ///
+    /// [`X509_get0_subject_key_id`]: https://commondatastorage.googleapis.com/chromium-boringssl-docs/x509v3.h.html#X509_get0_subject_key_id
+    pub fn subject_key_id(&self) -> Option<&Asn1OctetStringRef> {
+        unsafe {
+            let id = ffi::X509_get0_subject_key_id(self.as_ptr());
+            if id.is_null() {
+                None
+            } else {
+                Some(Asn1OctetStringRef::from_ptr(id as *mut _))
+            }
+        }
+    }
+
+    /// Returns the key identifier of this certificate's authority key identifier extension, if
+    /// present.
+    ///
+    /// This corresponds to [`X509_get0_authority_key_id`].
+    ///
+    /// [`X509_get0_authority_key_id`]: https://commondatastorage.googleapis.com/chromium-boringssl-docs/x509v3.h.html#X509_get0_authority_key_id
+    pub fn authority_key_id(&self) -> Option<&Asn1OctetStringRef> {
+        unsafe {
+            let id = ffi::X509_get0_authority_key_id(self.as_ptr());
+            if id.is_null() {
+                None
+            } else {
+                Some(Asn1OctetStringRef::from_ptr(id as *mut _))
+            }
+        }
+    }
+
+    /// Returns the usages permitted by this certificate's key usage extension, if present.
+    ///
+    /// This corresponds to [`X509_get_key_usage`].
+    ///
+    /// [`X509_get_key_usage`]: https://commondatastorage.googleapis.com/chromium-boringssl-docs/x509v3.h.html#X509_get_key_usage
+    pub fn key_usage(&self) -> Option<KeyUsageFlags> {
+        unsafe {
+            let usage = ffi::X509_get_key_usage(self.as_ptr());
+            if usage == u32::max_value() {
+                None
+            } else {
+                Some(KeyUsageFlags::from_bits_truncate(usage))
+            }
+        }
+    }
+
+    /// Returns the purposes listed in this certificate's extended key usage extension, if
+    /// present.
+    ///
+    /// Purposes without a known NID are reported as [`Nid::UNDEF`].
+    ///
+    /// This corresponds to [`X509_get_ext_d2i`] called with `NID_ext_key_usage`.
+    ///
+    /// [`Nid::UNDEF`]: ../nid/struct.Nid.html#associatedconstant.UNDEF
+    /// [`X509_get_ext_d2i`]: https://www.openssl.org/docs/man1.1.0/crypto/X509_get_ext_d2i.html
+    pub fn extended_key_usage(&self) -> Option<Vec<Nid>> {
+        unsafe {
+            let stack = ffi::X509_get_ext_d2i(
+                self.as_ptr(),
+                ffi::NID_ext_key_usage,
+                ptr::null_mut(),
+                ptr::null_mut(),
+            );
+            if stack.is_null() {
+                None
+            } else {
+                let stack = Stack::<Asn1Object>::from_ptr(stack as *mut _);
+                Some(stack.iter().map(|obj| obj.nid()).collect())
+            }
+        }
+    }
+
     pub fn public_key(&self) -> Result<PKey<Public>, ErrorStack> {
         unsafe {
             let pkey = cvt_p(ffi::X509_get_pubkey(self.as_ptr()))?;
@@ -590,12 +804,32 @@ impl X509Ref {
     to_der! {
         /// Serializes the certificate into a DER-encoded X509 structure.
         ///
+        /// The to-be-signed portion of a parsed certificate is kept in its original encoding, and
+        /// BoringSSL only accepts DER, so a certificate created with [`X509::from_der`] serializes
+        /// to exactly the bytes it was parsed from. This makes it suitable for handing
+        /// certificates to other parsers.
+        ///
         /// This corresponds to [`i2d_X509`].
         ///
+        /// [`X509::from_der`]: struct.X509.html#method.from_der
+        ///
         /// [`i2d_X509`]: https://www.openssl.org/docs/man1.1.0/crypto/i2d_X509.html
         to_der,
         ffi::i2d_X509
     }
+
+    /// Converts the certificate to human readable text.
+    ///
+    /// This corresponds to [`X509_print`].
+    ///
+    /// [`X509_print`]: https://www.openssl.org/docs/manmaster/man3/X509_print.html
+    pub fn to_text(&self) -> Result<Vec<u8>, ErrorStack> {
+        let bio = MemBio::new()?;
+        unsafe {
+            cvt(ffi::X509_print(bio.as_ptr(), self.as_ptr()))?;
+        }
+        Ok(bio.get_buf().to_owned())
+    }
 }
 
 impl ToOwned for X509Ref {
@@ -669,6 +903,59 @@ impl X509 {
             Ok(certs)
         }
     }
+
+    /// Deserializes a list of PEM-formatted certificates, reporting failures per entry.
+    ///
+    /// Unlike [`stack_from_pem`], a malformed certificate does not abort parsing: each PEM block
+    /// in `pem` yields one entry of the returned list, in order, so the index of an `Err` entry
+    /// identifies the offending certificate in the bundle.
+    ///
+    /// [`stack_from_pem`]: #method.stack_from_pem
+    pub fn stack_from_pem_lenient(pem: &[u8]) -> Result<Vec<Result<X509, ErrorStack>>, ErrorStack> {
+        unsafe {
+            ffi::init();
+            let bio = MemBioSlice::new(pem)?;
+
+            let mut certs = vec![];
+            loop {
+                let remaining = ffi::BIO_pending(bio.as_ptr());
+                let r =
+                    ffi::PEM_read_bio_X509(bio.as_ptr(), ptr::null_mut(), None, ptr::null_mut());
+                if r.is_null() {
+                    let err = ffi::ERR_peek_last_error();
+
+                    if ffi::ERR_GET_LIB(err) == ffi::ERR_LIB_PEM.0.try_into().unwrap()
+                        && ffi::ERR_GET_REASON(err) == ffi::PEM_R_NO_START_LINE
+                    {
+                        ffi::ERR_clear_error();
+                        break;
+                    }
+
+                    certs.push(Err(ErrorStack::get()));
+
+                    // a failure that consumed no input would be reported forever
+                    if ffi::BIO_pending(bio.as_ptr()) == remaining {
+                        break;
+                    }
+                } else {
+                    certs.push(Ok(X509::from_ptr(r)));
+                }
+            }
+
+            Ok(certs)
+        }
+    }
+}
+
+impl<'a> TryFrom<&'a [u8]> for X509 {
+    type Error = ErrorStack;
+
+    /// Deserializes a DER-encoded X509 structure, like [`X509::from_der`].
+    ///
+    /// [`X509::from_der`]: struct.X509.html#method.from_der
+    fn try_from(der: &'a [u8]) -> Result<X509, ErrorStack> {
+        X509::from_der(der)
+    }
 }
 
 impl Clone for X509 {
@@ -790,6 +1077,62 @@ impl X509Extension {
                 .map(|p| X509Extension::from_ptr(p))
         }
     }
+
+    /// Constructs a new X509 extension value from its OID, whether it's critical, and its
+    /// DER-encoded contents.
+    ///
+    /// This can be used for extensions that are not known to BoringSSL, such as ones registered
+    /// with [`Nid::create`].
+    ///
+    /// This corresponds to [`X509_EXTENSION_create_by_OBJ`].
+    ///
+    /// [`Nid::create`]: ../nid/struct.Nid.html#method.create
+    /// [`X509_EXTENSION_create_by_OBJ`]: https://commondatastorage.googleapis.com/chromium-boringssl-docs/x509.h.html#X509_EXTENSION_create_by_OBJ
+    pub fn new_from_der(
+        oid: &Asn1ObjectRef,
+        critical: bool,
+        der_contents: &Asn1OctetStringRef,
+    ) -> Result<X509Extension, ErrorStack> {
+        unsafe {
+            ffi::init();
+            cvt_p(ffi::X509_EXTENSION_create_by_OBJ(
+                ptr::null_mut(),
+                oid.as_ptr(),
+                critical as _,
+                der_contents.as_ptr(),
+            ))
+            .map(|p| X509Extension::from_ptr(p))
+        }
+    }
+}
+
+impl X509ExtensionRef {
+    /// Returns the extension's object identifier.
+    ///
+    /// This corresponds to [`X509_EXTENSION_get_object`].
+    ///
+    /// [`X509_EXTENSION_get_object`]: https://commondatastorage.googleapis.com/chromium-boringssl-docs/x509.h.html#X509_EXTENSION_get_object
+    pub fn object(&self) -> &Asn1ObjectRef {
+        unsafe { Asn1ObjectRef::from_ptr(ffi::X509_EXTENSION_get_object(self.as_ptr())) }
+    }
+
+    /// Returns `true` if the extension is marked critical.
+    ///
+    /// This corresponds to [`X509_EXTENSION_get_critical`].
+    ///
+    /// [`X509_EXTENSION_get_critical`]: https://commondatastorage.googleapis.com/chromium-boringssl-docs/x509.h.html#X509_EXTENSION_get_critical
+    pub fn critical(&self) -> bool {
+        unsafe { ffi::X509_EXTENSION_get_critical(self.as_ptr()) != 0 }
+    }
+
+    /// Returns the DER-encoded contents of the extension.
+    ///
+    /// This corresponds to [`X509_EXTENSION_get_data`].
+    ///
+    /// [`X509_EXTENSION_get_data`]: https://commondatastorage.googleapis.com/chromium-boringssl-docs/x509.h.html#X509_EXTENSION_get_data
+    pub fn data(&self) -> &Asn1OctetStringRef {
+        unsafe { Asn1OctetStringRef::from_ptr(ffi::X509_EXTENSION_get_data(self.as_ptr())) }
+    }
 }
 
 /// A builder used to construct an `X509Name`.
@@ -872,7 +1215,10 @@ impl X509Name {
     /// This is commonly used in conjunction with `SslContextBuilder::set_client_ca_list`.
     pub fn load_client_ca_file<P: AsRef<Path>>(file: P) -> Result<Stack<X509Name>, ErrorStack> {
         let file = CString::new(file.as_ref().as_os_str().to_str().unwrap()).unwrap();
-        unsafe { cvt_p(ffi::SSL_load_client_CA_file(file.as_ptr())).map(|p| Stack::from_ptr(p)) }
+        unsafe {
+            ffi::init();
+            cvt_p(ffi::SSL_load_client_CA_file(file.as_ptr())).map(|p| Stack::from_ptr(p))
+        }
     }
 }
 
@@ -1163,6 +1509,19 @@ impl X509ReqRef {
         ffi::i2d_X509_REQ
     }
 
+    /// Converts the certificate request to human readable text.
+    ///
+    /// This corresponds to [`X509_REQ_print`].
+    ///
+    /// [`X509_REQ_print`]: https://www.openssl.org/docs/manmaster/man3/X509_REQ_print.html
+    pub fn to_text(&self) -> Result<Vec<u8>, ErrorStack> {
+        let bio = MemBio::new()?;
+        unsafe {
+            cvt(ffi::X509_REQ_print(bio.as_ptr(), self.as_ptr()))?;
+        }
+        Ok(bio.get_buf().to_owned())
+    }
+
     #[cfg(not(feature = "fips"))]
     /// Returns the numerical value of the version field of the certificate request.
     ///
@@ -1224,6 +1583,395 @@ impl X509ReqRef {
     }
 }
 
+/// The reason a certificate was revoked, as recorded in a CRL entry.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct CrlReason(c_int);
+
+impl CrlReason {
+    pub const UNSPECIFIED: CrlReason = CrlReason(ffi::CRL_REASON_UNSPECIFIED as c_int);
+    pub const KEY_COMPROMISE: CrlReason = CrlReason(ffi::CRL_REASON_KEY_COMPROMISE as c_int);
+    pub const CA_COMPROMISE: CrlReason = CrlReason(ffi::CRL_REASON_CA_COMPROMISE as c_int);
+    pub const AFFILIATION_CHANGED: CrlReason =
+        CrlReason(ffi::CRL_REASON_AFFILIATION_CHANGED as c_int);
+    pub const SUPERSEDED: CrlReason = CrlReason(ffi::CRL_REASON_SUPERSEDED as c_int);
+    pub const CESSATION_OF_OPERATION: CrlReason =
+        CrlReason(ffi::CRL_REASON_CESSATION_OF_OPERATION as c_int);
+    pub const CERTIFICATE_HOLD: CrlReason = CrlReason(ffi::CRL_REASON_CERTIFICATE_HOLD as c_int);
+    pub const REMOVE_FROM_CRL: CrlReason = CrlReason(ffi::CRL_REASON_REMOVE_FROM_CRL as c_int);
+    pub const PRIVILEGE_WITHDRAWN: CrlReason =
+        CrlReason(ffi::CRL_REASON_PRIVILEGE_WITHDRAWN as c_int);
+    pub const AA_COMPROMISE: CrlReason = CrlReason(ffi::CRL_REASON_AA_COMPROMISE as c_int);
+
+    /// Constructs a `CrlReason` from a raw reason code.
+    pub fn from_raw(raw: c_int) -> CrlReason {
+        CrlReason(raw)
+    }
+
+    /// Returns the raw reason code.
+    #[allow(clippy::trivially_copy_pass_by_ref)]
+    pub fn as_raw(&self) -> c_int {
+        self.0
+    }
+}
+
+/// A builder used to construct an `X509Crl`.
+pub struct X509CrlBuilder(X509Crl);
+
+impl X509CrlBuilder {
+    /// Returns a builder for a version 2 certificate revocation list.
+    ///
+    /// This corresponds to [`X509_CRL_new`].
+    ///
+    /// [`X509_CRL_new`]: https://www.openssl.org/docs/man1.1.0/crypto/X509_CRL_new.html
+    pub fn new() -> Result<X509CrlBuilder, ErrorStack> {
+        unsafe {
+            ffi::init();
+            let crl = X509Crl::from_ptr(cvt_p(ffi::X509_CRL_new())?);
+            // Version 2 is required for the entry extensions carrying revocation reasons.
+            cvt(ffi::X509_CRL_set_version(crl.as_ptr(), 1))?;
+            Ok(X509CrlBuilder(crl))
+        }
+    }
+
+    /// Sets the issuer name of the CRL, which must match the subject name of the CA signing it.
+    ///
+    /// This corresponds to [`X509_CRL_set_issuer_name`].
+    ///
+    /// [`X509_CRL_set_issuer_name`]: https://www.openssl.org/docs/man1.1.0/crypto/X509_CRL_set_issuer_name.html
+    pub fn set_issuer_name(&mut self, issuer_name: &X509NameRef) -> Result<(), ErrorStack> {
+        unsafe {
+            cvt(ffi::X509_CRL_set_issuer_name(
+                self.0.as_ptr(),
+                issuer_name.as_ptr(),
+            ))
+            .map(|_| ())
+        }
+    }
+
+    /// Sets the time at which the CRL was issued.
+    ///
+    /// This corresponds to [`X509_CRL_set_lastUpdate`].
+    ///
+    /// [`X509_CRL_set_lastUpdate`]: https://www.openssl.org/docs/man1.1.0/crypto/X509_CRL_set1_lastUpdate.html
+    pub fn set_last_update(&mut self, last_update: &Asn1TimeRef) -> Result<(), ErrorStack> {
+        unsafe {
+            cvt(ffi::X509_CRL_set_lastUpdate(
+                self.0.as_ptr(),
+                last_update.as_ptr(),
+            ))
+            .map(|_| ())
+        }
+    }
+
+    /// Sets the time by which the next CRL will be issued.
+    ///
+    /// This corresponds to [`X509_CRL_set_nextUpdate`].
+    ///
+    /// [`X509_CRL_set_nextUpdate`]: https://www.openssl.org/docs/man1.1.0/crypto/X509_CRL_set1_nextUpdate.html
+    pub fn set_next_update(&mut self, next_update: &Asn1TimeRef) -> Result<(), ErrorStack> {
+        unsafe {
+            cvt(ffi::X509_CRL_set_nextUpdate(
+                self.0.as_ptr(),
+                next_update.as_ptr(),
+            ))
+            .map(|_| ())
+        }
+    }
+
+    /// Adds an entry revoking the certificate with serial number `serial_number`.
+    ///
+    /// If `reason` is set, it is recorded in a reason code extension of the entry.
+    ///
+    /// This corresponds to [`X509_CRL_add0_revoked`].
+    ///
+    /// [`X509_CRL_add0_revoked`]: https://www.openssl.org/docs/man1.1.0/crypto/X509_CRL_add0_revoked.html
+    pub fn add_revoked(
+        &mut self,
+        serial_number: &Asn1IntegerRef,
+        revocation_date: &Asn1TimeRef,
+        reason: Option<CrlReason>,
+    ) -> Result<(), ErrorStack> {
+        unsafe {
+            let revoked = X509Revoked::from_ptr(cvt_p(ffi::X509_REVOKED_new())?);
+            cvt(ffi::X509_REVOKED_set_serialNumber(
+                revoked.as_ptr(),
+                serial_number.as_ptr(),
+            ))?;
+            cvt(ffi::X509_REVOKED_set_revocationDate(
+                revoked.as_ptr(),
+                revocation_date.as_ptr(),
+            ))?;
+
+            if let Some(reason) = reason {
+                let code = cvt_p(ffi::ASN1_ENUMERATED_new())?;
+                let r = cvt(ffi::ASN1_ENUMERATED_set(code, reason.0 as c_long)).and_then(|_| {
+                    cvt(ffi::X509_REVOKED_add1_ext_i2d(
+                        revoked.as_ptr(),
+                        ffi::NID_crl_reason,
+                        code as *mut _,
+                        0,
+                        0,
+                    ))
+                });
+                ffi::ASN1_ENUMERATED_free(code);
+                r?;
+            }
+
+            cvt(ffi::X509_CRL_add0_revoked(
+                self.0.as_ptr(),
+                revoked.as_ptr(),
+            ))?;
+            mem::forget(revoked);
+            Ok(())
+        }
+    }
+
+    /// Sorts the entries of the CRL and signs it using a private key.
+    ///
+    /// This corresponds to [`X509_CRL_sign`].
+    ///
+    /// [`X509_CRL_sign`]: https://www.openssl.org/docs/man1.1.0/crypto/X509_CRL_sign.html
+    pub fn sign<T>(&mut self, key: &PKeyRef<T>, hash: MessageDigest) -> Result<(), ErrorStack>
+    where
+        T: HasPrivate,
+    {
+        unsafe {
+            cvt(ffi::X509_CRL_sort(self.0.as_ptr()))?;
+            cvt(ffi::X509_CRL_sign(
+                self.0.as_ptr(),
+                key.as_ptr(),
+                hash.as_ptr(),
+            ))
+            .map(|_| ())
+        }
+    }
+
+    /// Returns the `X509Crl`.
+    pub fn build(self) -> X509Crl {
+        self.0
+    }
+}
+
+foreign_type_and_impl_send_sync! {
+    type CType = ffi::X509_CRL;
+    fn drop = ffi::X509_CRL_free;
+
+    /// An `X509` certificate revocation list.
+    pub struct X509Crl;
+}
+
+impl X509Crl {
+    /// A builder for `X509Crl`.
+    pub fn builder() -> Result<X509CrlBuilder, ErrorStack> {
+        X509CrlBuilder::new()
+    }
+
+    from_pem! {
+        /// Deserializes a PEM-encoded certificate revocation list.
+        ///
+        /// The input should have a header of `-----BEGIN X509 CRL-----`.
+        ///
+        /// This corresponds to [`PEM_read_bio_X509_CRL`].
+        ///
+        /// [`PEM_read_bio_X509_CRL`]: https://www.openssl.org/docs/man1.0.2/crypto/PEM_read_bio_X509_CRL.html
+        from_pem,
+        X509Crl,
+        ffi::PEM_read_bio_X509_CRL
+    }
+
+    from_der! {
+        /// Deserializes a DER-encoded certificate revocation list.
+        ///
+        /// This corresponds to [`d2i_X509_CRL`].
+        ///
+        /// [`d2i_X509_CRL`]: https://www.openssl.org/docs/man1.1.0/crypto/d2i_X509_CRL.html
+        from_der,
+        X509Crl,
+        ffi::d2i_X509_CRL,
+        ::libc::c_long
+    }
+}
+
+impl X509CrlRef {
+    to_pem! {
+        /// Serializes the certificate revocation list to a PEM-encoded structure.
+        ///
+        /// The output will have a header of `-----BEGIN X509 CRL-----`.
+        ///
+        /// This corresponds to [`PEM_write_bio_X509_CRL`].
+        ///
+        /// [`PEM_write_bio_X509_CRL`]: https://www.openssl.org/docs/man1.0.2/crypto/PEM_write_bio_X509_CRL.html
+        to_pem,
+        ffi::PEM_write_bio_X509_CRL
+    }
+
+    to_der! {
+        /// Serializes the certificate revocation list to a DER-encoded structure.
+        ///
+        /// This corresponds to [`i2d_X509_CRL`].
+        ///
+        /// [`i2d_X509_CRL`]: https://www.openssl.org/docs/man1.0.2/crypto/i2d_X509_CRL.html
+        to_der,
+        ffi::i2d_X509_CRL
+    }
+
+    /// Returns the issuer name of the CRL.
+    ///
+    /// This corresponds to [`X509_CRL_get_issuer`].
+    ///
+    /// [`X509_CRL_get_issuer`]: https://www.openssl.org/docs/man1.1.0/crypto/X509_CRL_get_issuer.html
+    pub fn issuer_name(&self) -> &X509NameRef {
+        unsafe {
+            let name = ffi::X509_CRL_get_issuer(self.as_ptr());
+            assert!(!name.is_null());
+            X509NameRef::from_ptr(name)
+        }
+    }
+
+    /// Returns the time at which the CRL was issued.
+    ///
+    /// This corresponds to [`X509_CRL_get0_lastUpdate`].
+    ///
+    /// [`X509_CRL_get0_lastUpdate`]: https://www.openssl.org/docs/man1.1.0/crypto/X509_CRL_get0_lastUpdate.html
+    pub fn last_update(&self) -> &Asn1TimeRef {
+        unsafe {
+            let date = ffi::X509_CRL_get0_lastUpdate(self.as_ptr());
+            assert!(!date.is_null());
+            Asn1TimeRef::from_ptr(date as *mut _)
+        }
+    }
+
+    /// Returns the time by which the next CRL will be issued, if set.
+    ///
+    /// This corresponds to [`X509_CRL_get0_nextUpdate`].
+    ///
+    /// [`X509_CRL_get0_nextUpdate`]: https://www.openssl.org/docs/man1.1.0/crypto/X509_CRL_get0_nextUpdate.html
+    pub fn next_update(&self) -> Option<&Asn1TimeRef> {
+        unsafe {
+            let date = ffi::X509_CRL_get0_nextUpdate(self.as_ptr());
+            if date.is_null() {
+                None
+            } else {
+                Some(Asn1TimeRef::from_ptr(date as *mut _))
+            }
+        }
+    }
+
+    /// Returns the entries of the CRL, if any.
+    ///
+    /// This corresponds to [`X509_CRL_get_REVOKED`].
+    ///
+    /// [`X509_CRL_get_REVOKED`]: https://www.openssl.org/docs/man1.1.0/crypto/X509_CRL_get_REVOKED.html
+    pub fn revoked(&self) -> Option<&StackRef<X509Revoked>> {
+        unsafe {
+            let revoked = ffi::X509_CRL_get_REVOKED(self.as_ptr());
+            if revoked.is_null() {
+                None
+            } else {
+                Some(StackRef::from_ptr(revoked))
+            }
+        }
+    }
+
+    /// Returns the entry revoking the certificate with serial number `serial_number`, if any.
+    ///
+    /// This corresponds to [`X509_CRL_get0_by_serial`].
+    ///
+    /// [`X509_CRL_get0_by_serial`]: https://www.openssl.org/docs/man1.1.0/crypto/X509_CRL_get0_by_serial.html
+    pub fn get_by_serial(&self, serial_number: &Asn1IntegerRef) -> Option<&X509RevokedRef> {
+        unsafe {
+            let mut revoked = ptr::null_mut();
+            let r = ffi::X509_CRL_get0_by_serial(
+                self.as_ptr(),
+                &mut revoked,
+                serial_number.as_ptr() as *mut _,
+            );
+            if r == 0 || revoked.is_null() {
+                None
+            } else {
+                Some(X509RevokedRef::from_ptr(revoked))
+            }
+        }
+    }
+
+    /// Check if the CRL is signed using the given public key.
+    ///
+    /// Returns `true` if verification succeeds.
+    ///
+    /// This corresponds to [`X509_CRL_verify`].
+    ///
+    /// [`X509_CRL_verify`]: https://www.openssl.org/docs/man1.1.0/crypto/X509_CRL_verify.html
+    pub fn verify<T>(&self, key: &PKeyRef<T>) -> Result<bool, ErrorStack>
+    where
+        T: HasPublic,
+    {
+        unsafe { cvt_n(ffi::X509_CRL_verify(self.as_ptr(), key.as_ptr())).map(|n| n != 0) }
+    }
+}
+
+foreign_type_and_impl_send_sync! {
+    type CType = ffi::X509_REVOKED;
+    fn drop = ffi::X509_REVOKED_free;
+
+    /// An entry of a certificate revocation list.
+    pub struct X509Revoked;
+}
+
+impl Stackable for X509Revoked {
+    type StackType = ffi::stack_st_X509_REVOKED;
+}
+
+impl X509RevokedRef {
+    /// Returns the serial number of the revoked certificate.
+    ///
+    /// This corresponds to [`X509_REVOKED_get0_serialNumber`].
+    ///
+    /// [`X509_REVOKED_get0_serialNumber`]: https://www.openssl.org/docs/man1.1.0/crypto/X509_REVOKED_get0_serialNumber.html
+    pub fn serial_number(&self) -> &Asn1IntegerRef {
+        unsafe {
+            let r = ffi::X509_REVOKED_get0_serialNumber(self.as_ptr());
+            assert!(!r.is_null());
+            Asn1IntegerRef::from_ptr(r as *mut _)
+        }
+    }
+
+    /// Returns the time at which the certificate was revoked.
+    ///
+    /// This corresponds to [`X509_REVOKED_get0_revocationDate`].
+    ///
+    /// [`X509_REVOKED_get0_revocationDate`]: https://www.openssl.org/docs/man1.1.0/crypto/X509_REVOKED_get0_revocationDate.html
+    pub fn revocation_date(&self) -> &Asn1TimeRef {
+        unsafe {
+            let date = ffi::X509_REVOKED_get0_revocationDate(self.as_ptr());
+            assert!(!date.is_null());
+            Asn1TimeRef::from_ptr(date as *mut _)
+        }
+    }
+
+    /// Returns the reason the certificate was revoked, if the entry records a valid one.
+    ///
+    /// This corresponds to [`X509_REVOKED_get_ext_d2i`] with `NID_crl_reason`.
+    ///
+    /// [`X509_REVOKED_get_ext_d2i`]: https://www.openssl.org/docs/man1.1.0/crypto/X509_REVOKED_get_ext_d2i.html
+    pub fn reason(&self) -> Option<CrlReason> {
+        unsafe {
+            let code = ffi::X509_REVOKED_get_ext_d2i(
+                self.as_ptr(),
+                ffi::NID_crl_reason,
+                ptr::null_mut(),
+                ptr::null_mut(),
+            ) as *mut ffi::ASN1_ENUMERATED;
+            if code.is_null() {
+                ErrorStack::clear();
+                return None;
+            }
+            let reason = ffi::ASN1_ENUMERATED_get(code);
+            ffi::ASN1_ENUMERATED_free(code);
+            Some(CrlReason(reason as c_int))
+        }
+    }
+}
+
 /// The result of peer certificate verification.
 #[derive(Copy, Clone, PartialEq, Eq)]
 pub struct X509VerifyResult(c_int);
@@ -1282,6 +2030,26 @@ impl X509VerifyResult {
     /// Application verification failure.
     pub const APPLICATION_VERIFICATION: X509VerifyResult =
         X509VerifyResult(ffi::X509_V_ERR_APPLICATION_VERIFICATION);
+    /// The issuer of a certificate could not be found in the trusted store.
+    pub const UNABLE_TO_GET_ISSUER_CERT_LOCALLY: X509VerifyResult =
+        X509VerifyResult(ffi::X509_V_ERR_UNABLE_TO_GET_ISSUER_CERT_LOCALLY);
+    /// The chain ends in a self-signed certificate that is not trusted.
+    pub const SELF_SIGNED_CERT_IN_CHAIN: X509VerifyResult =
+        X509VerifyResult(ffi::X509_V_ERR_SELF_SIGNED_CERT_IN_CHAIN);
+    /// The certificate is self-signed and not trusted.
+    pub const DEPTH_ZERO_SELF_SIGNED_CERT: X509VerifyResult =
+        X509VerifyResult(ffi::X509_V_ERR_DEPTH_ZERO_SELF_SIGNED_CERT);
+    /// The signature of a certificate is invalid.
+    pub const CERT_SIGNATURE_FAILURE: X509VerifyResult =
+        X509VerifyResult(ffi::X509_V_ERR_CERT_SIGNATURE_FAILURE);
+    /// A certificate is not yet valid.
+    pub const CERT_NOT_YET_VALID: X509VerifyResult =
+        X509VerifyResult(ffi::X509_V_ERR_CERT_NOT_YET_VALID);
+    /// A certificate has expired.
+    pub const CERT_HAS_EXPIRED: X509VerifyResult =
+        X509VerifyResult(ffi::X509_V_ERR_CERT_HAS_EXPIRED);
+    /// A certificate has been revoked.
+    pub const CERT_REVOKED: X509VerifyResult = X509VerifyResult(ffi::X509_V_ERR_CERT_REVOKED);
 }
 
 foreign_type_and_impl_send_sync! {
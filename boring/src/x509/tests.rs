@@ -1,7 +1,9 @@
 use hex::{self, FromHex};
+use std::convert::TryFrom;
 
-use crate::asn1::Asn1Time;
+use crate::asn1::{Asn1Object, Asn1OctetString, Asn1Time};
 use crate::bn::{BigNum, MsbOption};
+use crate::error::ErrorStack;
 use crate::hash::MessageDigest;
 use crate::nid::Nid;
 use crate::pkey::{PKey, Private};
@@ -12,7 +14,10 @@ use crate::x509::extension::{
     SubjectKeyIdentifier,
 };
 use crate::x509::store::X509StoreBuilder;
-use crate::x509::{X509Name, X509Req, X509StoreContext, X509VerifyResult, X509};
+use crate::x509::{
+    CrlReason, KeyUsageFlags, X509Builder, X509Crl, X509Extension, X509Name, X509Ref, X509Req,
+    X509StoreContext, X509VerifyResult, X509,
+};
 
 fn pkey() -> PKey<Private> {
     let rsa = Rsa::generate(2048).unwrap();
@@ -45,6 +50,16 @@ fn test_debug() {
     assert!(debugged.contains(r#"not_after: Aug 12 17:00:03 2026 GMT"#));
 }
 
+#[test]
+fn test_to_text() {
+    let cert = include_bytes!("../../test/cert.pem");
+    let cert = X509::from_pem(cert).unwrap();
+    let text = String::from_utf8(cert.to_text().unwrap()).unwrap();
+
+    assert!(text.contains("CN=foobar.com") || text.contains("CN = foobar.com"));
+    assert!(text.contains("Serial Number:"));
+}
+
 #[test]
 fn test_cert_issue_validity() {
     let cert = include_bytes!("../../test/cert.pem");
@@ -65,6 +80,16 @@ fn test_save_der() {
     assert!(!der.is_empty());
 }
 
+#[test]
+fn test_der_round_trip() {
+    let der = include_bytes!("../../test/cert.der");
+    let cert = X509::from_der(der).unwrap();
+    assert_eq!(cert.to_der().unwrap(), &der[..]);
+
+    let cert = X509::from_pem(include_bytes!("../../test/cert.pem")).unwrap();
+    assert_eq!(cert.to_der().unwrap(), &der[..]);
+}
+
 #[test]
 fn test_subject_read_cn() {
     let cert = include_bytes!("../../test/cert.pem");
@@ -250,6 +275,74 @@ fn x509_builder() {
     assert_eq!(serial, x509.serial_number().to_bn().unwrap());
 }
 
+#[test]
+fn x509_builder_key_identifiers() {
+    fn builder(cn: &str, issuer: Option<&X509Ref>, pkey: &PKey<Private>) -> X509Builder {
+        let mut name = X509Name::builder().unwrap();
+        name.append_entry_by_nid(Nid::COMMONNAME, cn).unwrap();
+        let name = name.build();
+
+        let mut builder = X509::builder().unwrap();
+        builder.set_version(2).unwrap();
+        builder.set_subject_name(&name).unwrap();
+        builder
+            .set_issuer_name(issuer.map_or(&*name, |issuer| issuer.subject_name()))
+            .unwrap();
+        builder
+            .set_not_before(&Asn1Time::days_from_now(0).unwrap())
+            .unwrap();
+        builder
+            .set_not_after(&Asn1Time::days_from_now(365).unwrap())
+            .unwrap();
+        builder.set_pubkey(pkey).unwrap();
+
+        let mut serial = BigNum::new().unwrap();
+        serial.rand(128, MsbOption::MAYBE_ZERO, false).unwrap();
+        builder
+            .set_serial_number(&serial.to_asn1_integer().unwrap())
+            .unwrap();
+
+        let subject_key_identifier = SubjectKeyIdentifier::new()
+            .build(&builder.x509v3_context(issuer, None))
+            .unwrap();
+        builder.append_extension(subject_key_identifier).unwrap();
+        let authority_key_identifier = AuthorityKeyIdentifier::new()
+            .keyid(true)
+            .issuer(true)
+            .build(&builder.x509v3_context(issuer, None))
+            .unwrap();
+        builder.append_extension(authority_key_identifier).unwrap();
+        builder
+    }
+
+    let ca_key = pkey();
+    let mut ca = builder("ca", None, &ca_key);
+    let basic_constraints = BasicConstraints::new().critical().ca().build().unwrap();
+    ca.append_extension(basic_constraints).unwrap();
+    ca.sign(&ca_key, MessageDigest::sha256()).unwrap();
+    let ca = ca.build();
+
+    let leaf_key = PKey::from_rsa(Rsa::generate(2048).unwrap()).unwrap();
+    let mut leaf = builder("leaf", Some(&ca), &leaf_key);
+    leaf.sign(&ca_key, MessageDigest::sha256()).unwrap();
+    let leaf = leaf.build();
+
+    let ca_skid = ca.subject_key_id().unwrap();
+    assert_eq!(
+        ca.authority_key_id().unwrap().as_slice(),
+        ca_skid.as_slice()
+    );
+    assert_eq!(
+        leaf.authority_key_id().unwrap().as_slice(),
+        ca_skid.as_slice()
+    );
+    assert_ne!(
+        leaf.subject_key_id().unwrap().as_slice(),
+        ca_skid.as_slice()
+    );
+    assert_eq!(ca.issued(&leaf), X509VerifyResult::OK);
+}
+
 #[test]
 fn x509_req_builder() {
     let pkey = pkey();
@@ -284,6 +377,9 @@ fn x509_req_builder() {
     assert!(req.public_key().unwrap().public_eq(&pkey));
     assert_eq!(req.extensions().unwrap().len(), extensions.len());
     assert!(req.verify(&pkey).unwrap());
+
+    let text = String::from_utf8(req.to_text().unwrap()).unwrap();
+    assert!(text.contains("foobar.com"));
 }
 
 #[test]
@@ -302,6 +398,145 @@ fn test_stack_from_pem() {
     );
 }
 
+#[test]
+fn test_basic_constraints() {
+    fn build(basic_constraints: X509Extension) -> X509 {
+        let pkey = pkey();
+        let mut name = X509Name::builder().unwrap();
+        name.append_entry_by_nid(Nid::COMMONNAME, "example.com")
+            .unwrap();
+        let name = name.build();
+
+        let mut builder = X509::builder().unwrap();
+        builder.set_version(2).unwrap();
+        builder.set_subject_name(&name).unwrap();
+        builder.set_issuer_name(&name).unwrap();
+        builder
+            .set_not_before(&Asn1Time::days_from_now(0).unwrap())
+            .unwrap();
+        builder
+            .set_not_after(&Asn1Time::days_from_now(365).unwrap())
+            .unwrap();
+        builder.set_pubkey(&pkey).unwrap();
+        builder.append_extension(basic_constraints).unwrap();
+        builder.sign(&pkey, MessageDigest::sha256()).unwrap();
+        builder.build()
+    }
+
+    let ca = build(
+        BasicConstraints::new()
+            .critical()
+            .ca()
+            .pathlen(0)
+            .build()
+            .unwrap(),
+    );
+    let bc = ca.basic_constraints().unwrap();
+    assert!(bc.ca());
+    assert_eq!(bc.pathlen(), Some(0));
+    assert!(ca.is_ca());
+
+    let leaf = build(BasicConstraints::new().build().unwrap());
+    let bc = leaf.basic_constraints().unwrap();
+    assert!(!bc.ca());
+    assert_eq!(bc.pathlen(), None);
+    assert!(!leaf.is_ca());
+
+    let root = X509::from_pem(include_bytes!("../../test/root-ca.pem")).unwrap();
+    assert!(root.is_ca());
+    assert_eq!(root.basic_constraints().unwrap().pathlen(), None);
+
+    let cert = X509::from_pem(include_bytes!("../../test/cert.pem")).unwrap();
+    assert_eq!(cert.basic_constraints(), None);
+    assert!(!cert.is_ca());
+}
+
+#[test]
+fn test_key_usage() {
+    let pkey = pkey();
+
+    let mut name = X509Name::builder().unwrap();
+    name.append_entry_by_nid(Nid::COMMONNAME, "ca.example.com")
+        .unwrap();
+    let name = name.build();
+
+    let mut builder = X509::builder().unwrap();
+    builder.set_version(2).unwrap();
+    builder.set_subject_name(&name).unwrap();
+    builder.set_issuer_name(&name).unwrap();
+    builder
+        .set_not_before(&Asn1Time::days_from_now(0).unwrap())
+        .unwrap();
+    builder
+        .set_not_after(&Asn1Time::days_from_now(365).unwrap())
+        .unwrap();
+    builder.set_pubkey(&pkey).unwrap();
+    let key_usage = KeyUsage::new()
+        .critical()
+        .key_cert_sign()
+        .crl_sign()
+        .build()
+        .unwrap();
+    builder.append_extension(key_usage).unwrap();
+    let ext_key_usage = ExtendedKeyUsage::new()
+        .server_auth()
+        .other("2.999.1")
+        .build()
+        .unwrap();
+    builder.append_extension(ext_key_usage).unwrap();
+    builder.sign(&pkey, MessageDigest::sha256()).unwrap();
+    let x509 = builder.build();
+
+    assert_eq!(
+        x509.key_usage(),
+        Some(KeyUsageFlags::KEY_CERT_SIGN | KeyUsageFlags::CRL_SIGN)
+    );
+    assert_eq!(
+        x509.extended_key_usage(),
+        Some(vec![Nid::SERVER_AUTH, Nid::UNDEF])
+    );
+
+    let cert = include_bytes!("../../test/cert.pem");
+    let cert = X509::from_pem(cert).unwrap();
+    assert_eq!(cert.key_usage(), None);
+    assert_eq!(cert.extended_key_usage(), None);
+}
+
+#[test]
+fn test_try_from_der() {
+    let der = X509::from_pem(include_bytes!("../../test/cert.pem"))
+        .unwrap()
+        .to_der()
+        .unwrap();
+    let cert = X509::try_from(&der[..]).unwrap();
+    assert_eq!(cert.to_der().unwrap(), der);
+
+    let err: ErrorStack = X509::try_from(&b"not a certificate"[..]).unwrap_err();
+    assert!(!err.errors().is_empty());
+}
+
+#[test]
+fn test_stack_from_pem_lenient() {
+    let cert = String::from_utf8(include_bytes!("../../test/cert.pem").to_vec()).unwrap();
+    let root = String::from_utf8(include_bytes!("../../test/root-ca.pem").to_vec()).unwrap();
+    let corrupted = cert.replacen("MII", "AAA", 1);
+    let bundle = format!("{}{}{}", cert, corrupted, root);
+
+    assert!(X509::stack_from_pem(bundle.as_bytes()).is_err());
+
+    let certs = X509::stack_from_pem_lenient(bundle.as_bytes()).unwrap();
+    assert_eq!(certs.len(), 3);
+    assert!(certs[0].is_ok());
+    assert!(certs[1].is_err());
+    assert!(certs[2].is_ok());
+
+    let bad = certs
+        .iter()
+        .position(|cert| cert.is_err())
+        .expect("no malformed certificate reported");
+    assert_eq!(bad, 1);
+}
+
 #[test]
 fn issued() {
     let cert = include_bytes!("../../test/cert.pem");
@@ -378,4 +613,107 @@ fn test_verify_fails() {
     assert!(!context
         .init(&store, &cert, &chain, |c| c.verify_cert())
         .unwrap());
+
+    let error = context
+        .init(&store, &cert, &chain, |c| {
+            assert!(!c.verify_cert()?);
+            Ok(c.error())
+        })
+        .unwrap();
+    assert_eq!(error, X509VerifyResult::UNABLE_TO_GET_ISSUER_CERT_LOCALLY);
+}
+
+#[test]
+fn test_verify_untrusted_chain() {
+    let cert = include_bytes!("../../test/cert.pem");
+    let cert = X509::from_pem(cert).unwrap();
+    let ca = include_bytes!("../../test/root-ca.pem");
+    let ca = X509::from_pem(ca).unwrap();
+    let mut chain = Stack::new().unwrap();
+    chain.push(ca.clone()).unwrap();
+
+    let store = X509StoreBuilder::new().unwrap().build();
+    let mut context = X509StoreContext::new().unwrap();
+    let (ok, error) = context
+        .init(&store, &cert, &chain, |c| Ok((c.verify_cert()?, c.error())))
+        .unwrap();
+    assert!(!ok);
+    assert_eq!(error, X509VerifyResult::SELF_SIGNED_CERT_IN_CHAIN);
+
+    let mut store_bldr = X509StoreBuilder::new().unwrap();
+    store_bldr.add_cert(ca).unwrap();
+    let store = store_bldr.build();
+    let (ok, error) = context
+        .init(&store, &cert, &chain, |c| Ok((c.verify_cert()?, c.error())))
+        .unwrap();
+    assert!(ok);
+    assert_eq!(error, X509VerifyResult::OK);
+}
+
+#[test]
+fn custom_oid_extension() {
+    let nid = Nid::create(
+        "1.3.6.1.4.1.55555.2",
+        "boringCustomExt",
+        "boring custom extension",
+    )
+    .unwrap();
+
+    let object = Asn1Object::from_nid(nid).unwrap();
+    // DER encoding of the UTF8String "hello"
+    let contents = Asn1OctetString::new_from_bytes(b"\x0c\x05hello").unwrap();
+    let extension = X509Extension::new_from_der(&object, false, &contents).unwrap();
+
+    let by_oid = Asn1Object::from_str("1.3.6.1.4.1.55555.2").unwrap();
+    assert_eq!(extension.object().nid(), by_oid.nid());
+    assert_eq!(extension.object().nid(), nid);
+    assert!(!extension.critical());
+    assert_eq!(extension.data().as_slice(), b"\x0c\x05hello");
+}
+
+#[test]
+fn test_crl_builder() {
+    let ca = X509::from_pem(include_bytes!("../../test/root-ca.pem")).unwrap();
+    let ca_key = PKey::private_key_from_pem(include_bytes!("../../test/root-ca.key")).unwrap();
+    let serial = |n| BigNum::from_u32(n).unwrap().to_asn1_integer().unwrap();
+
+    let mut builder = X509Crl::builder().unwrap();
+    builder.set_issuer_name(ca.subject_name()).unwrap();
+    builder
+        .set_last_update(&Asn1Time::days_from_now(0).unwrap())
+        .unwrap();
+    builder
+        .set_next_update(&Asn1Time::days_from_now(7).unwrap())
+        .unwrap();
+    builder
+        .add_revoked(
+            &serial(0x1234),
+            &Asn1Time::days_from_now(0).unwrap(),
+            Some(CrlReason::KEY_COMPROMISE),
+        )
+        .unwrap();
+    builder
+        .add_revoked(&serial(0x5678), &Asn1Time::days_from_now(0).unwrap(), None)
+        .unwrap();
+    builder.sign(&ca_key, MessageDigest::sha256()).unwrap();
+    let pem = builder.build().to_pem().unwrap();
+
+    let crl = X509Crl::from_pem(&pem).unwrap();
+    assert!(crl.verify(&ca.public_key().unwrap()).unwrap());
+    assert!(!crl.verify(&pkey()).unwrap());
+    assert_eq!(
+        format!("{:?}", crl.issuer_name()),
+        format!("{:?}", ca.subject_name())
+    );
+    assert!(crl.next_update().is_some());
+    assert_eq!(crl.revoked().unwrap().len(), 2);
+
+    let entry = crl.get_by_serial(&serial(0x1234)).unwrap();
+    assert_eq!(
+        entry.serial_number().to_bn().unwrap(),
+        BigNum::from_u32(0x1234).unwrap()
+    );
+    assert_eq!(entry.reason(), Some(CrlReason::KEY_COMPROMISE));
+    assert_eq!(crl.get_by_serial(&serial(0x5678)).unwrap().reason(), None);
+    assert!(crl.get_by_serial(&serial(1)).is_none());
 }
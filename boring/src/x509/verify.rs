@@ -26,6 +26,11 @@ foreign_type_and_impl_send_sync! {
     fn drop = ffi::X509_VERIFY_PARAM_free;
 
     /// Adjust parameters associated with certificate verification.
+    ///
+    /// BoringSSL does not implement OpenSSL's security levels, so there is no equivalent of
+    /// `X509_VERIFY_PARAM_set_auth_level`. Policies on the keys and signature algorithms of a
+    /// chain, such as rejecting SHA-1 signed intermediates, must be enforced by inspecting the
+    /// chain from a verify callback.
     pub struct X509VerifyParam;
 }
 
@@ -57,6 +62,26 @@ impl X509VerifyParamRef {
         }
     }
 
+    /// Adds an additional expected DNS hostname.
+    ///
+    /// The certificate is accepted if it matches any of the names set by [`set_host`] and
+    /// `add_host`.
+    ///
+    /// This corresponds to [`X509_VERIFY_PARAM_add1_host`].
+    ///
+    /// [`set_host`]: #method.set_host
+    /// [`X509_VERIFY_PARAM_add1_host`]: https://www.openssl.org/docs/man1.1.0/crypto/X509_VERIFY_PARAM_set1_host.html
+    pub fn add_host(&mut self, host: &str) -> Result<(), ErrorStack> {
+        unsafe {
+            cvt(ffi::X509_VERIFY_PARAM_add1_host(
+                self.as_ptr(),
+                host.as_ptr() as *const _,
+                host.len(),
+            ))
+            .map(|_| ())
+        }
+    }
+
     /// Set the expected IPv4 or IPv6 address.
     ///
     /// This corresponds to [`X509_VERIFY_PARAM_set1_ip`].
@@ -0,0 +1,163 @@
+//! Async TLS streams over the [`futures-io`] traits.
+//!
+//! This module allows the TLS handshake and stream to be driven by any runtime whose I/O types
+//! implement `futures_io::AsyncRead` and `futures_io::AsyncWrite`, such as `async-std` or `smol`.
+//! It is only available with the `futures-io` feature.
+//!
+//! [`futures-io`]: https://docs.rs/futures-io
+use ::futures_io::{AsyncRead, AsyncWrite};
+use boring::ssl::{ConnectConfiguration, SslAcceptor, SslRef};
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio::io::ReadBuf;
+
+/// The error type returned after a failed handshake.
+pub type HandshakeError<S> = crate::HandshakeError<Compat<S>>;
+
+/// Asynchronously performs a client-side TLS handshake over the provided stream.
+pub async fn connect<S>(
+    config: ConnectConfiguration,
+    domain: &str,
+    stream: S,
+) -> Result<SslStream<S>, HandshakeError<S>>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    crate::connect(config, domain, Compat(stream))
+        .await
+        .map(SslStream)
+}
+
+/// Asynchronously performs a server-side TLS handshake over the provided stream.
+pub async fn accept<S>(acceptor: &SslAcceptor, stream: S) -> Result<SslStream<S>, HandshakeError<S>>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    crate::accept(acceptor, Compat(stream)).await.map(SslStream)
+}
+
+/// An adapter exposing a `futures-io` stream to the handshake machinery.
+#[derive(Debug)]
+pub struct Compat<S>(S);
+
+impl<S> Compat<S> {
+    /// Returns a shared reference to the underlying stream.
+    pub fn get_ref(&self) -> &S {
+        &self.0
+    }
+
+    /// Returns a mutable reference to the underlying stream.
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.0
+    }
+
+    /// Converts the adapter to the underlying stream.
+    pub fn into_inner(self) -> S {
+        self.0
+    }
+}
+
+impl<S> tokio::io::AsyncRead for Compat<S>
+where
+    S: AsyncRead + Unpin,
+{
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        ctx: &mut Context<'_>,
+        buf: &mut ReadBuf,
+    ) -> Poll<io::Result<()>> {
+        match Pin::new(&mut self.0).poll_read(ctx, buf.initialize_unfilled()) {
+            Poll::Ready(Ok(nread)) => {
+                buf.advance(nread);
+                Poll::Ready(Ok(()))
+            }
+            Poll::Ready(Err(e)) => Poll::Ready(Err(e)),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+impl<S> tokio::io::AsyncWrite for Compat<S>
+where
+    S: AsyncWrite + Unpin,
+{
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        ctx: &mut Context,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.0).poll_write(ctx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, ctx: &mut Context) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.0).poll_flush(ctx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, ctx: &mut Context) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.0).poll_close(ctx)
+    }
+}
+
+/// A wrapper around an underlying raw stream which implements the SSL
+/// protocol.
+///
+/// This is the `futures-io` counterpart of [`crate::SslStream`].
+#[derive(Debug)]
+pub struct SslStream<S>(crate::SslStream<Compat<S>>);
+
+impl<S> SslStream<S> {
+    /// Returns a shared reference to the `Ssl` object associated with this stream.
+    pub fn ssl(&self) -> &SslRef {
+        self.0.ssl()
+    }
+
+    /// Returns a shared reference to the underlying stream.
+    pub fn get_ref(&self) -> &S {
+        &self.0.get_ref().0
+    }
+
+    /// Returns a mutable reference to the underlying stream.
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.0.get_mut().0
+    }
+}
+
+impl<S> AsyncRead for SslStream<S>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        ctx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let mut buf = ReadBuf::new(buf);
+        match tokio::io::AsyncRead::poll_read(Pin::new(&mut self.0), ctx, &mut buf) {
+            Poll::Ready(Ok(())) => Poll::Ready(Ok(buf.filled().len())),
+            Poll::Ready(Err(e)) => Poll::Ready(Err(e)),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+impl<S> AsyncWrite for SslStream<S>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        ctx: &mut Context,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        tokio::io::AsyncWrite::poll_write(Pin::new(&mut self.0), ctx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, ctx: &mut Context) -> Poll<io::Result<()>> {
+        tokio::io::AsyncWrite::poll_flush(Pin::new(&mut self.0), ctx)
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, ctx: &mut Context) -> Poll<io::Result<()>> {
+        tokio::io::AsyncWrite::poll_shutdown(Pin::new(&mut self.0), ctx)
+    }
+}
@@ -12,19 +12,25 @@
 //! primarily done through the [`boring`] crate.
 #![warn(missing_docs)]
 
+use boring::ex_data::Index;
 use boring::ssl::{
-    self, ConnectConfiguration, ErrorCode, MidHandshakeSslStream, ShutdownResult, SslAcceptor,
+    self, ConnectConfiguration, ErrorCode, MidHandshakeSslStream, ShutdownResult, Ssl, SslAcceptor,
     SslRef,
 };
 use boring_sys as ffi;
+use once_cell::sync::OnceCell;
 use std::error::Error;
 use std::fmt;
 use std::future::Future;
 use std::io::{self, Read, Write};
 use std::pin::Pin;
-use std::task::{Context, Poll};
+use std::task::{Context, Poll, Waker};
+use std::time::Duration;
 use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
 
+#[cfg(feature = "futures-io")]
+pub mod futures_io;
+
 /// Asynchronously performs a client-side TLS handshake over the provided stream.
 pub async fn connect<S>(
     config: ConnectConfiguration,
@@ -34,7 +40,15 @@ pub async fn connect<S>(
 where
     S: AsyncRead + AsyncWrite + Unpin,
 {
-    handshake(|s| config.connect(domain, s), stream).await
+    handshake(
+        |s, waker| {
+            let mut config = config;
+            set_handshake_waker(&mut config, waker);
+            config.connect(domain, s)
+        },
+        stream,
+    )
+    .await
 }
 
 /// Asynchronously performs a server-side TLS handshake over the provided stream.
@@ -42,13 +56,54 @@ pub async fn accept<S>(acceptor: &SslAcceptor, stream: S) -> Result<SslStream<S>
 where
     S: AsyncRead + AsyncWrite + Unpin,
 {
-    handshake(|s| acceptor.accept(s), stream).await
+    handshake(
+        |s, waker| {
+            let mut ssl = Ssl::new(&acceptor.clone().into_context())?;
+            set_handshake_waker(&mut ssl, waker);
+            ssl.accept(s)
+        },
+        stream,
+    )
+    .await
+}
+
+fn waker_index() -> Index<Ssl, Waker> {
+    static INDEX: OnceCell<Index<Ssl, Waker>> = OnceCell::new();
+
+    *INDEX.get_or_init(|| Ssl::new_ex_index().unwrap())
+}
+
+/// Returns the waker of the task driving the handshake of `ssl`.
+///
+/// A callback which suspends the handshake, such as a certificate callback returning
+/// [`CertCallbackError::RETRY`], is not waiting on I/O, so nothing else will wake the task once
+/// it is ready to complete. It should keep this waker and wake it when its work is done, after
+/// which the handshake resumes and the callback is invoked again.
+///
+/// The waker is set before the handshake starts, so it is available from the first invocation of
+/// the callback. Returns `None` if the handshake is not driven by [`connect`] or [`accept`].
+///
+/// [`CertCallbackError::RETRY`]: https://docs.rs/boring/latest/boring/ssl/struct.CertCallbackError.html#associatedconstant.RETRY
+pub fn handshake_waker(ssl: &SslRef) -> Option<&Waker> {
+    ssl.ex_data(waker_index())
+}
+
+fn set_handshake_waker(ssl: &mut SslRef, waker: &Waker) {
+    match ssl.ex_data_mut(waker_index()) {
+        Some(old) => {
+            if !old.will_wake(waker) {
+                *old = waker.clone();
+            }
+        }
+        None => ssl.set_ex_data(waker_index(), waker.clone()),
+    }
 }
 
 async fn handshake<F, S>(f: F, stream: S) -> Result<SslStream<S>, HandshakeError<S>>
 where
     F: FnOnce(
             StreamWrapper<S>,
+            &Waker,
         )
             -> Result<ssl::SslStream<StreamWrapper<S>>, ssl::HandshakeError<StreamWrapper<S>>>
         + Unpin,
@@ -153,6 +208,13 @@ fn cvt<T>(r: io::Result<T>) -> Poll<io::Result<T>> {
 /// and both the server and the client are ready for receiving and sending
 /// data. Bytes read from a `SslStream` are decrypted from `S` and bytes written
 /// to a `SslStream` are encrypted when passing through to `S`.
+///
+/// To read and write concurrently from separate tasks, split the stream with
+/// [`tokio::io::split`]. The halves share the stream through a lock which is only held while a
+/// half is being polled, so the underlying `Ssl` is never accessed concurrently, and a read waiting
+/// for data does not block writes.
+///
+/// [`tokio::io::split`]: https://docs.rs/tokio/1/tokio/io/fn.split.html
 #[derive(Debug)]
 pub struct SslStream<S>(ssl::SslStream<StreamWrapper<S>>);
 
@@ -198,6 +260,57 @@ where
         let stream = StreamWrapper { stream, context: 0 };
         SslStream(ssl::SslStream::from_raw_parts(ssl, stream))
     }
+
+    /// Performs a bidirectional TLS shutdown.
+    ///
+    /// This sends a `close_notify` alert, waits for the peer's `close_notify` and then shuts down
+    /// the underlying stream. Unlike [`AsyncWrite::poll_shutdown`], which only sends our own
+    /// alert, this will not complete until the peer has responded, which may be never. Use
+    /// [`SslStream::shutdown_timeout`] to bound the wait.
+    ///
+    /// This is deliberately not named `shutdown`, so that it does not shadow
+    /// [`AsyncWriteExt::shutdown`].
+    ///
+    /// [`AsyncWriteExt::shutdown`]: https://docs.rs/tokio/1/tokio/io/trait.AsyncWriteExt.html#method.shutdown
+    pub async fn shutdown_bidirectional(&mut self) -> io::Result<()> {
+        ShutdownFuture(self).await
+    }
+
+    /// Like [`SslStream::shutdown_bidirectional`], but gives up after `timeout` has elapsed.
+    ///
+    /// An error of kind [`io::ErrorKind::TimedOut`] is returned if the peer did not send its
+    /// `close_notify` in time. The stream should be dropped in that case.
+    pub async fn shutdown_timeout(&mut self, timeout: Duration) -> io::Result<()> {
+        match tokio::time::timeout(timeout, self.shutdown_bidirectional()).await {
+            Ok(r) => r,
+            Err(_) => Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                "timed out waiting for close_notify",
+            )),
+        }
+    }
+
+    fn poll_shutdown_bidirectional(&mut self, ctx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        loop {
+            match self.with_context(ctx, |s| s.shutdown()) {
+                Ok(ShutdownResult::Sent) => {}
+                Ok(ShutdownResult::Received) => break,
+                Err(ref e) if e.code() == ErrorCode::ZERO_RETURN => break,
+                Err(ref e)
+                    if e.code() == ErrorCode::WANT_READ || e.code() == ErrorCode::WANT_WRITE =>
+                {
+                    return Poll::Pending;
+                }
+                Err(e) => {
+                    return Poll::Ready(Err(e
+                        .into_io_error()
+                        .unwrap_or_else(|e| io::Error::new(io::ErrorKind::Other, e))));
+                }
+            }
+        }
+
+        Pin::new(&mut self.0.get_mut().stream).poll_shutdown(ctx)
+    }
 }
 
 impl<S> AsyncRead for SslStream<S>
@@ -349,6 +462,7 @@ impl<F, S> Future for StartHandshakeFuture<F, S>
 where
     F: FnOnce(
             StreamWrapper<S>,
+            &Waker,
         )
             -> Result<ssl::SslStream<StreamWrapper<S>>, ssl::HandshakeError<StreamWrapper<S>>>
         + Unpin,
@@ -366,7 +480,7 @@ where
             stream: inner.stream,
             context: ctx as *mut _ as usize,
         };
-        match (inner.f)(stream) {
+        match (inner.f)(stream, ctx.waker()) {
             Ok(mut s) => {
                 s.get_mut().context = 0;
                 Poll::Ready(Ok(StartedHandshake::Done(SslStream(s))))
@@ -395,6 +509,7 @@ where
         let mut s = self.0.take().expect("future polled after completion");
 
         s.get_mut().context = ctx as *mut _ as usize;
+        set_handshake_waker(s.ssl_mut(), ctx.waker());
         match s.handshake() {
             Ok(mut s) => {
                 s.get_mut().context = 0;
@@ -409,3 +524,16 @@ where
         }
     }
 }
+
+struct ShutdownFuture<'a, S>(&'a mut SslStream<S>);
+
+impl<S> Future for ShutdownFuture<'_, S>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    type Output = io::Result<()>;
+
+    fn poll(mut self: Pin<&mut Self>, ctx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.0.poll_shutdown_bidirectional(ctx)
+    }
+}
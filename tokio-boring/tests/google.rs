@@ -1,10 +1,19 @@
-use boring::ssl::{SslAcceptor, SslConnector, SslFiletype, SslMethod};
+use boring::pkey::{PKey, Private};
+use boring::ssl::{CertCallbackError, SslAcceptor, SslConnector, SslFiletype, SslMethod};
+use boring::x509::X509;
 use futures::future;
 use std::future::Future;
+use std::io;
 use std::net::{SocketAddr, ToSocketAddrs};
 use std::pin::Pin;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::Waker;
+use std::thread;
+use std::time::Duration;
 use tokio::io::{AsyncReadExt, AsyncWrite, AsyncWriteExt};
 use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::oneshot;
 use tokio_boring::{HandshakeError, SslStream};
 
 #[tokio::test]
@@ -123,3 +132,255 @@ async fn handshake_error() {
 
     future::join(server, client).await;
 }
+
+async fn connect_client(addr: SocketAddr) -> SslStream<TcpStream> {
+    let mut connector = SslConnector::builder(SslMethod::tls()).unwrap();
+    connector.set_ca_file("tests/cert.pem").unwrap();
+    let config = connector.build().configure().unwrap();
+
+    let stream = TcpStream::connect(&addr).await.unwrap();
+    tokio_boring::connect(config, "localhost", stream)
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn mutual_shutdown() {
+    let (stream, addr) = create_server();
+
+    let server = async {
+        let mut stream = stream.await.unwrap();
+        stream.shutdown_bidirectional().await.unwrap();
+    };
+
+    let client = async {
+        let mut stream = connect_client(addr).await;
+        stream
+            .shutdown_timeout(Duration::from_secs(10))
+            .await
+            .unwrap();
+    };
+
+    future::join(server, client).await;
+}
+
+#[tokio::test]
+async fn shutdown_timeout_unresponsive_peer() {
+    let (stream, addr) = create_server();
+    let (tx, rx) = oneshot::channel::<()>();
+
+    // The server never answers the client's close_notify, but keeps the connection open.
+    let server = async {
+        let _stream = stream.await.unwrap();
+        let _ = rx.await;
+    };
+
+    let client = async {
+        let mut stream = connect_client(addr).await;
+        let err = stream
+            .shutdown_timeout(Duration::from_millis(100))
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+        tx.send(()).unwrap();
+    };
+
+    future::join(server, client).await;
+}
+
+#[tokio::test]
+async fn shutdown_timeout_dropped_peer() {
+    let (stream, addr) = create_server();
+
+    // The server reads the client's close_notify, then closes the connection without answering.
+    let server = async {
+        let mut stream = stream.await.unwrap();
+        assert_eq!(stream.read(&mut [0]).await.unwrap(), 0);
+    };
+
+    let client = async {
+        let mut stream = connect_client(addr).await;
+        let err = stream
+            .shutdown_timeout(Duration::from_secs(10))
+            .await
+            .unwrap_err();
+        // The connection was closed without a close_notify, which is reported immediately rather
+        // than as a timeout.
+        assert_eq!(err.kind(), io::ErrorKind::Other);
+    };
+
+    future::join(server, client).await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn shared_acceptor_and_connector() {
+    const CONNECTIONS: usize = 100;
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let mut acceptor = SslAcceptor::mozilla_intermediate(SslMethod::tls()).unwrap();
+    acceptor
+        .set_private_key_file("tests/key.pem", SslFiletype::PEM)
+        .unwrap();
+    acceptor
+        .set_certificate_chain_file("tests/cert.pem")
+        .unwrap();
+    let acceptor = acceptor.build();
+
+    let server = tokio::spawn(async move {
+        let mut tasks = Vec::with_capacity(CONNECTIONS);
+        for _ in 0..CONNECTIONS {
+            let stream = listener.accept().await.unwrap().0;
+            let acceptor = acceptor.clone();
+            tasks.push(tokio::spawn(async move {
+                let mut stream = tokio_boring::accept(&acceptor, stream).await.unwrap();
+                let mut buf = [0; 4];
+                stream.read_exact(&mut buf).await.unwrap();
+                stream.write_all(&buf).await.unwrap();
+                future::poll_fn(|ctx| Pin::new(&mut stream).poll_shutdown(ctx))
+                    .await
+                    .unwrap();
+            }));
+        }
+        for task in tasks {
+            task.await.unwrap();
+        }
+    });
+
+    let mut connector = SslConnector::builder(SslMethod::tls()).unwrap();
+    connector.set_ca_file("tests/cert.pem").unwrap();
+    let connector = connector.build();
+
+    let clients = (0..CONNECTIONS)
+        .map(|_| {
+            let connector = connector.clone();
+            tokio::spawn(async move {
+                let config = connector.configure().unwrap();
+                let stream = TcpStream::connect(&addr).await.unwrap();
+                let mut stream = tokio_boring::connect(config, "localhost", stream)
+                    .await
+                    .unwrap();
+                stream.write_all(b"ping").await.unwrap();
+                let mut buf = vec![];
+                stream.read_to_end(&mut buf).await.unwrap();
+                assert_eq!(buf, b"ping");
+            })
+        })
+        .collect::<Vec<_>>();
+
+    for client in clients {
+        client.await.unwrap();
+    }
+    server.await.unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn split() {
+    const MESSAGES: usize = 100;
+
+    let (stream, addr) = create_server();
+
+    let server = tokio::spawn(async move {
+        let stream = stream.await.unwrap();
+        let (mut reader, mut writer) = tokio::io::split(stream);
+
+        // Echo every message back while the client is still writing.
+        let mut buf = [0; 4];
+        for _ in 0..MESSAGES {
+            reader.read_exact(&mut buf).await.unwrap();
+            writer.write_all(&buf).await.unwrap();
+        }
+    });
+
+    let stream = connect_client(addr).await;
+    let (mut reader, mut writer) = tokio::io::split(stream);
+
+    let write = tokio::spawn(async move {
+        for i in 0..MESSAGES as u32 {
+            writer.write_all(&i.to_be_bytes()).await.unwrap();
+        }
+        writer
+    });
+
+    let read = tokio::spawn(async move {
+        let mut buf = [0; 4];
+        for i in 0..MESSAGES as u32 {
+            reader.read_exact(&mut buf).await.unwrap();
+            assert_eq!(u32::from_be_bytes(buf), i);
+        }
+        reader
+    });
+
+    let writer = write.await.unwrap();
+    let reader = read.await.unwrap();
+    server.await.unwrap();
+
+    let stream = reader.unsplit(writer);
+    assert!(!stream.ssl().is_server());
+}
+
+#[tokio::test]
+async fn async_cert_lookup() {
+    #[derive(Default)]
+    struct Lookup {
+        started: bool,
+        found: Option<(X509, PKey<Private>)>,
+        waker: Option<Waker>,
+    }
+
+    static CALLS: AtomicUsize = AtomicUsize::new(0);
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let lookup = Arc::new(Mutex::new(Lookup::default()));
+    let mut acceptor = SslAcceptor::mozilla_intermediate(SslMethod::tls()).unwrap();
+    acceptor.set_cert_callback(move |ssl| {
+        CALLS.fetch_add(1, Ordering::SeqCst);
+
+        let mut state = lookup.lock().unwrap();
+        if let Some((cert, key)) = &state.found {
+            ssl.set_certificate(cert)?;
+            ssl.set_private_key(key)?;
+            return Ok(());
+        }
+
+        // The waker is available from the very first invocation.
+        state.waker = Some(tokio_boring::handshake_waker(ssl).unwrap().clone());
+        if !state.started {
+            state.started = true;
+            let lookup = lookup.clone();
+            thread::spawn(move || {
+                thread::sleep(Duration::from_millis(50));
+                let cert = X509::from_pem(&std::fs::read("tests/cert.pem").unwrap()).unwrap();
+                let key =
+                    PKey::private_key_from_pem(&std::fs::read("tests/key.pem").unwrap()).unwrap();
+
+                let mut state = lookup.lock().unwrap();
+                state.found = Some((cert, key));
+                if let Some(waker) = state.waker.take() {
+                    waker.wake();
+                }
+            });
+        }
+        Err(CertCallbackError::RETRY)
+    });
+    let acceptor = acceptor.build();
+
+    let server = async {
+        let stream = listener.accept().await.unwrap().0;
+        let mut stream = tokio_boring::accept(&acceptor, stream).await.unwrap();
+        stream.write_all(b"done").await.unwrap();
+    };
+
+    let client = async {
+        let mut stream = connect_client(addr).await;
+        let mut buf = [0; 4];
+        stream.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"done");
+    };
+
+    future::join(server, client).await;
+    assert!(CALLS.load(Ordering::SeqCst) >= 2);
+}